@@ -104,6 +104,141 @@ pub struct CompileCommand {
     #[arg(long = "open")]
     pub open: Option<Option<String>>,
 
+    /// Fails PDF export instead of only warning when the document uses
+    /// characters that its fonts have no glyph for
+    #[arg(long = "deny-missing-glyphs")]
+    pub deny_missing_glyphs: bool,
+
+    /// Draws all text as vector paths in PDF export instead of embedding the
+    /// fonts
+    #[arg(long = "text-as-paths")]
+    pub text_as_paths: bool,
+
+    /// An ICC profile that replaces sRGB as the RGB working space in PDF
+    /// export
+    #[arg(long = "working-rgb-profile", value_name = "PATH")]
+    pub working_rgb_profile: Option<PathBuf>,
+
+    /// Downsamples raster images in PDF export whose resolution exceeds this
+    /// many pixels per inch at their placed size
+    #[arg(long = "max-image-dpi", value_parser = parse_positive)]
+    pub max_image_dpi: Option<f64>,
+
+    /// The rendering intent to apply to raster images in PDF export
+    #[arg(long = "image-rendering-intent")]
+    pub image_rendering_intent: Option<RenderingIntent>,
+
+    /// The scale, relative to their own size, at which parts of SVG images
+    /// that can't be represented as vectors are rasterized in PDF export
+    #[arg(long = "svg-raster-scale", value_parser = parse_positive)]
+    pub svg_raster_scale: Option<f64>,
+
+    /// The resolution in pixels per inch at which parts of SVG images that
+    /// can't be represented as vectors are rasterized in PDF export.
+    /// Overrides `--svg-raster-scale`
+    #[arg(long = "svg-raster-dpi", value_parser = parse_positive)]
+    pub svg_raster_dpi: Option<f64>,
+
+    /// Draws shapes stroked with zero thickness as hairlines in PDF export
+    /// instead of skipping their strokes
+    #[arg(long = "hairlines")]
+    pub hairlines: bool,
+
+    /// The number of decimal places (0-6) to which coordinates are rounded in
+    /// PDF export. Without it, they are written at full precision
+    #[arg(
+        long = "coordinate-precision",
+        value_name = "PLACES",
+        value_parser = clap::value_parser!(u8).range(..=6),
+    )]
+    pub coordinate_precision: Option<u8>,
+
+    /// Clips the content of each page to its bounds in PDF export
+    #[arg(long = "clip-to-page")]
+    pub clip_to_page: bool,
+
+    /// The transition effect that viewers show in presentation mode when
+    /// moving to a page in PDF export
+    #[arg(long = "page-transition")]
+    pub page_transition: Option<TransitionStyle>,
+
+    /// How long page transitions take, in seconds
+    #[arg(
+        long = "page-transition-duration",
+        default_value_t = 1.0,
+        value_parser = parse_non_negative
+    )]
+    pub page_transition_duration: f32,
+
+    /// Lets viewers in presentation mode advance to the next page after
+    /// showing a page for this many seconds in PDF export
+    #[arg(long = "page-advance", value_parser = parse_non_negative)]
+    pub page_advance: Option<f32>,
+
+    /// Embeds a thumbnail of each page in PDF export, at most this many
+    /// pixels (up to 1024) wide and high
+    #[arg(
+        long = "thumbnail-size",
+        value_name = "PIXELS",
+        value_parser = clap::value_parser!(u32).range(1..=1024),
+    )]
+    pub thumbnail_size: Option<u32>,
+
+    /// The JPEG quality (1-100) of embedded page thumbnails
+    #[arg(
+        long = "thumbnail-quality",
+        default_value_t = 75,
+        value_parser = clap::value_parser!(u8).range(1..=100),
+    )]
+    pub thumbnail_quality: u8,
+
+    /// Embeds the sources of the document into the PDF as attachments
+    #[arg(long = "embed-sources")]
+    pub embed_sources: Option<EmbedSources>,
+
+    /// The maximum total size in bytes of the sources embedded with
+    /// `--embed-sources`
+    #[arg(
+        long = "embed-sources-limit",
+        value_name = "BYTES",
+        default_value_t = 10_000_000
+    )]
+    pub embed_sources_limit: usize,
+
+    /// The URL under which the PDF is published. Links to it with a fragment
+    /// jump to the heading with that label in PDF export, like links to a
+    /// bare fragment do
+    #[arg(long = "document-url", value_name = "URL")]
+    pub document_url: Option<String>,
+
+    /// The version of the PDF specification to conform to in PDF export
+    #[arg(long = "pdf-version", default_value = "1.7")]
+    pub pdf_version: PdfVersion,
+
+    /// How viewers treat links in PDF export
+    #[arg(long = "link-visibility", default_value = "visible")]
+    pub link_visibility: LinkVisibility,
+
+    /// Makes black text and strokes overprint the content below them in PDF
+    /// export if they are given in CMYK
+    #[arg(long = "overprint-black")]
+    pub overprint_black: bool,
+
+    /// Adds bookmark sections listing all captioned figures and tables in
+    /// PDF export
+    #[arg(long = "figure-outline")]
+    pub figure_outline: bool,
+
+    /// Makes hyphens inserted by hyphenation extract as soft hyphens instead
+    /// of being left out of the text copied from an exported PDF
+    #[arg(long = "soft-hyphens")]
+    pub soft_hyphens: bool,
+
+    /// Prints statistics about the PDF export, such as the time spent on
+    /// each page and the size of embedded fonts and images
+    #[arg(long = "pdf-stats")]
+    pub pdf_stats: bool,
+
     /// The PPI (pixels per inch) to use for PNG export
     #[arg(long = "ppi", default_value_t = 144.0)]
     pub ppi: f32,
@@ -220,6 +355,24 @@ fn parse_source_date_epoch(raw: &str) -> Result<DateTime<Utc>, String> {
         .ok_or_else(|| "timestamp out of range".to_string())
 }
 
+/// Parses a positive, finite number.
+fn parse_positive(raw: &str) -> Result<f64, String> {
+    let value: f64 = raw.parse().map_err(|err| format!("{err}"))?;
+    if !value.is_finite() || value <= 0.0 {
+        return Err("value must be a positive number".to_string());
+    }
+    Ok(value)
+}
+
+/// Parses a non-negative, finite number.
+fn parse_non_negative(raw: &str) -> Result<f32, String> {
+    let value: f32 = raw.parse().map_err(|err| format!("{err}"))?;
+    if !value.is_finite() || value < 0.0 {
+        return Err("value must be a non-negative number".to_string());
+    }
+    Ok(value)
+}
+
 /// An input that is either stdin or a real path.
 #[derive(Debug, Clone)]
 pub enum Input {
@@ -384,6 +537,64 @@ pub struct UpdateCommand {
     pub revert: bool,
 }
 
+/// How a viewer or printer maps colors that are out of its gamut.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum RenderingIntent {
+    Perceptual,
+    RelativeColorimetric,
+    Saturation,
+    AbsoluteColorimetric,
+}
+
+/// The visual effect of a page transition.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum TransitionStyle {
+    Replace,
+    Split,
+    Blinds,
+    Box,
+    Wipe,
+    Dissolve,
+    Glitter,
+    Fly,
+    Push,
+    Cover,
+    Uncover,
+    Fade,
+}
+
+/// How viewers treat the links of an exported PDF.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum LinkVisibility {
+    /// Links are clickable on screen and kept when printing.
+    Visible,
+    /// Links are invisible and not clickable on screen, but kept when
+    /// printing.
+    NoView,
+    /// Links are neither shown, clickable, nor printed.
+    Hidden,
+}
+
+/// A version of the PDF specification.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum PdfVersion {
+    /// PDF 1.7.
+    #[value(name = "1.7")]
+    V17,
+    /// PDF 2.0.
+    #[value(name = "2.0")]
+    V20,
+}
+
+/// Which sources to embed into an exported PDF.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum EmbedSources {
+    /// Only the main file.
+    Main,
+    /// All Typst files of the project that the document depends on.
+    All,
+}
+
 /// Which format to use for the generated output file.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, ValueEnum)]
 pub enum OutputFormat {
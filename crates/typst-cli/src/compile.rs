@@ -8,17 +8,22 @@ use codespan_reporting::term;
 use ecow::{eco_format, EcoString};
 use parking_lot::RwLock;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use typst::diag::{bail, At, Severity, SourceDiagnostic, StrResult};
+use typst::diag::{bail, At, Severity, SourceDiagnostic, SourceResult, StrResult};
 use typst::eval::Tracer;
-use typst::foundations::{Datetime, Smart};
+use typst::foundations::{Bytes, Datetime, Smart};
 use typst::layout::{Frame, PageRanges};
 use typst::model::Document;
 use typst::syntax::{FileId, Source, Span};
 use typst::visualize::Color;
 use typst::{World, WorldExt};
+use typst_pdf::{
+    AssociatedFileRelationship, PdfEmbeddedFile, PdfOptions, PdfStats, PdfThumbnails,
+    PdfTransition,
+};
 
 use crate::args::{
-    CompileCommand, DiagnosticFormat, Input, Output, OutputFormat, PageRangeArgument,
+    CompileCommand, DiagnosticFormat, EmbedSources, Input, LinkVisibility, Output,
+    OutputFormat, PageRangeArgument, PdfVersion, RenderingIntent, TransitionStyle,
 };
 use crate::timings::Timer;
 use crate::watch::Status;
@@ -111,13 +116,13 @@ pub fn compile_once(
     }
 
     let mut tracer = Tracer::new();
-    let result = typst::compile(world, &mut tracer);
+    let result = typst::compile(world, &mut tracer)
+        .and_then(|document| export(world, &document, command, watching, &mut tracer));
     let warnings = tracer.warnings();
 
     match result {
         // Export the PDF / PNG.
-        Ok(document) => {
-            export(world, &document, command, watching)?;
+        Ok(()) => {
             let duration = start.elapsed();
 
             if watching {
@@ -167,32 +172,217 @@ fn export(
     document: &Document,
     command: &CompileCommand,
     watching: bool,
-) -> StrResult<()> {
-    match command.output_format()? {
+    tracer: &mut Tracer,
+) -> SourceResult<()> {
+    match command.output_format().at(Span::detached())? {
         OutputFormat::Png => {
             export_image(world, document, command, watching, ImageExportFormat::Png)
+                .at(Span::detached())
         }
         OutputFormat::Svg => {
             export_image(world, document, command, watching, ImageExportFormat::Svg)
+                .at(Span::detached())
         }
-        OutputFormat::Pdf => export_pdf(document, command),
+        OutputFormat::Pdf => export_pdf(world, document, command, tracer),
     }
 }
 
 /// Export to a PDF.
-fn export_pdf(document: &Document, command: &CompileCommand) -> StrResult<()> {
-    let timestamp = convert_datetime(
-        command.common.creation_timestamp.unwrap_or_else(chrono::Utc::now),
-    );
-    let exported_page_ranges = command.exported_page_ranges();
-    let buffer = typst_pdf::pdf(document, Smart::Auto, timestamp, exported_page_ranges);
+fn export_pdf(
+    world: &mut SystemWorld,
+    document: &Document,
+    command: &CompileCommand,
+    tracer: &mut Tracer,
+) -> SourceResult<()> {
+    let options = PdfOptions {
+        ident: Smart::Auto,
+        timestamp: convert_datetime(
+            command.common.creation_timestamp.unwrap_or_else(chrono::Utc::now),
+        ),
+        page_ranges: command.exported_page_ranges(),
+        version: match command.pdf_version {
+            PdfVersion::V17 => typst_pdf::PdfVersion::Pdf17,
+            PdfVersion::V20 => typst_pdf::PdfVersion::Pdf20,
+        },
+        missing_glyphs_are_errors: command.deny_missing_glyphs,
+        overprint_black: command.overprint_black,
+        overprint_colors: vec![],
+        figure_outline: command.figure_outline,
+        soft_hyphens: command.soft_hyphens,
+        text_as_paths: command.text_as_paths,
+        working_rgb_profile: command
+            .working_rgb_profile
+            .as_ref()
+            .map(|path| {
+                fs::read(path).map(Bytes::from).map_err(|err| {
+                    eco_format!("failed to read working RGB profile ({err})")
+                })
+            })
+            .transpose()
+            .at(Span::detached())?,
+        max_image_dpi: command.max_image_dpi,
+        image_rendering_intent: command
+            .image_rendering_intent
+            .map(convert_rendering_intent),
+        svg_raster_scale: command.svg_raster_scale,
+        svg_raster_dpi: command.svg_raster_dpi,
+        thumbnails: command.thumbnail_size.map(|max_size| PdfThumbnails {
+            max_size,
+            quality: command.thumbnail_quality,
+            render: render_thumbnail,
+        }),
+        zero_thickness_hairlines: command.hairlines,
+        coordinate_precision: command.coordinate_precision,
+        clip_to_page: command.clip_to_page,
+        page_transitions: page_transitions(command),
+        link_visibility: match command.link_visibility {
+            LinkVisibility::Visible => typst_pdf::LinkVisibility::Visible,
+            LinkVisibility::NoView => typst_pdf::LinkVisibility::NoView,
+            LinkVisibility::Hidden => typst_pdf::LinkVisibility::Hidden,
+        },
+        file_attachments: vec![],
+        document_url: command.document_url.as_deref(),
+        embedded_files: embedded_sources(world, command).at(Span::detached())?,
+        embedded_files_limit: Some(command.embed_sources_limit),
+    };
+    let buffer = if command.pdf_stats {
+        let (buffer, stats) = typst_pdf::pdf_with_stats(document, &options, tracer)?;
+        print_pdf_stats(&stats)
+            .map_err(|err| eco_format!("failed to print PDF statistics ({err})"))
+            .at(Span::detached())?;
+        buffer
+    } else {
+        typst_pdf::pdf_with_options(document, &options, tracer)?
+    };
     command
         .output()
         .write(&buffer)
-        .map_err(|err| eco_format!("failed to write PDF file ({err})"))?;
+        .map_err(|err| eco_format!("failed to write PDF file ({err})"))
+        .at(Span::detached())?;
     Ok(())
 }
 
+/// Render a page for a PDF thumbnail.
+fn render_thumbnail(frame: &Frame, pixel_per_pt: f32) -> (u32, u32, Vec<u8>) {
+    let pixmap = typst_render::render(frame, pixel_per_pt, Color::WHITE);
+
+    // The page is rendered onto an opaque background, so the colors are not
+    // affected by premultiplication and we can drop the alpha channel.
+    let rgb = pixmap
+        .data()
+        .chunks_exact(4)
+        .flat_map(|p| [p[0], p[1], p[2]])
+        .collect();
+    (pixmap.width(), pixmap.height(), rgb)
+}
+
+/// Print statistics about a PDF export to the terminal.
+fn print_pdf_stats(stats: &PdfStats) -> io::Result<()> {
+    let mut out = terminal::out();
+    for page in &stats.pages {
+        writeln!(
+            out,
+            "page {}: {:.2?}, {} text runs, {} shapes, {} images",
+            page.index + 1,
+            page.time,
+            page.glyph_runs,
+            page.shapes,
+            page.images,
+        )?;
+    }
+
+    writeln!(out, "fonts: {} ({} bytes)", stats.fonts, stats.font_bytes)?;
+    writeln!(out, "images: {} ({} bytes)", stats.images, stats.image_bytes)?;
+    writeln!(out, "page contents: {} bytes", stats.content_bytes)?;
+    writeln!(out, "total size: {} bytes", stats.size)?;
+    Ok(())
+}
+
+/// Gather the sources to embed into the PDF, if requested.
+fn embedded_sources(
+    world: &mut SystemWorld,
+    command: &CompileCommand,
+) -> StrResult<Vec<PdfEmbeddedFile>> {
+    let Some(which) = command.embed_sources else { return Ok(vec![]) };
+
+    // The sources are taken from the world, so that they are exactly what
+    // was compiled, even if they changed on disk since.
+    let main = world.main();
+    let mut sources = vec![world.source(main)?];
+    if which == EmbedSources::All {
+        let mut dependencies: Vec<_> = world
+            .sources()
+            // Files of packages live outside of the project and are skipped.
+            .filter(|source| source.id() != main && source.id().package().is_none())
+            .collect();
+        dependencies.sort_by(|a, b| a.id().vpath().cmp(b.id().vpath()));
+        sources.extend(dependencies);
+    }
+
+    Ok(sources
+        .into_iter()
+        .map(|source| PdfEmbeddedFile {
+            name: source
+                .id()
+                .vpath()
+                .as_rootless_path()
+                .to_string_lossy()
+                .replace('\\', "/")
+                .into(),
+            data: Bytes::from(source.text().as_bytes()),
+            description: None,
+            mime_type: Some("text/plain".into()),
+            modified: None,
+            relationship: Some(AssociatedFileRelationship::Source),
+        })
+        .collect())
+}
+
+/// Convert the CLI's rendering intent to the PDF exporter's.
+fn convert_rendering_intent(intent: RenderingIntent) -> typst_pdf::RenderingIntent {
+    match intent {
+        RenderingIntent::Perceptual => typst_pdf::RenderingIntent::Perceptual,
+        RenderingIntent::RelativeColorimetric => {
+            typst_pdf::RenderingIntent::RelativeColorimetric
+        }
+        RenderingIntent::Saturation => typst_pdf::RenderingIntent::Saturation,
+        RenderingIntent::AbsoluteColorimetric => {
+            typst_pdf::RenderingIntent::AbsoluteColorimetric
+        }
+    }
+}
+
+/// Build the page transitions requested on the command line, which apply to
+/// all pages.
+fn page_transitions(command: &CompileCommand) -> Vec<(PageRanges, PdfTransition)> {
+    if command.page_transition.is_none() && command.page_advance.is_none() {
+        return vec![];
+    }
+
+    let style = match command.page_transition.unwrap_or(TransitionStyle::Replace) {
+        TransitionStyle::Replace => typst_pdf::TransitionStyle::Replace,
+        TransitionStyle::Split => typst_pdf::TransitionStyle::Split,
+        TransitionStyle::Blinds => typst_pdf::TransitionStyle::Blinds,
+        TransitionStyle::Box => typst_pdf::TransitionStyle::Box,
+        TransitionStyle::Wipe => typst_pdf::TransitionStyle::Wipe,
+        TransitionStyle::Dissolve => typst_pdf::TransitionStyle::Dissolve,
+        TransitionStyle::Glitter => typst_pdf::TransitionStyle::Glitter,
+        TransitionStyle::Fly => typst_pdf::TransitionStyle::Fly,
+        TransitionStyle::Push => typst_pdf::TransitionStyle::Push,
+        TransitionStyle::Cover => typst_pdf::TransitionStyle::Cover,
+        TransitionStyle::Uncover => typst_pdf::TransitionStyle::Uncover,
+        TransitionStyle::Fade => typst_pdf::TransitionStyle::Fade,
+    };
+
+    let transition = PdfTransition {
+        style,
+        duration: command.page_transition_duration,
+        advance: command.page_advance,
+    };
+
+    vec![(PageRanges::new(vec![None..=None]), transition)]
+}
+
 /// Convert [`chrono::DateTime`] to [`Datetime`]
 fn convert_datetime(date_time: chrono::DateTime<chrono::Utc>) -> Option<Datetime> {
     Datetime::from_ymd_hms(
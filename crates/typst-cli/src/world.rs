@@ -147,6 +147,15 @@ impl SystemWorld {
             .filter_map(|slot| system_path(&self.root, slot.id).ok())
     }
 
+    /// Return the source files the last compilation parsed, with the text it
+    /// compiled.
+    pub fn sources(&mut self) -> impl Iterator<Item = Source> + '_ {
+        self.slots
+            .get_mut()
+            .values()
+            .filter_map(|slot| slot.source.accessed_data())
+    }
+
     /// Reset the compilation state in preparation of a new compilation.
     pub fn reset(&mut self) {
         for slot in self.slots.get_mut().values_mut() {
@@ -310,6 +319,12 @@ impl<T: Clone> SlotCell<T> {
         self.accessed = false;
     }
 
+    /// The processed data if the cell was accessed in the ongoing compilation
+    /// and loading it succeeded.
+    fn accessed_data(&self) -> Option<T> {
+        self.data.as_ref().filter(|_| self.accessed)?.as_ref().ok().cloned()
+    }
+
     /// Gets the contents of the cell or initialize them.
     fn get_or_init(
         &mut self,
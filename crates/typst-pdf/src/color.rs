@@ -113,8 +113,8 @@ impl ColorSpaces {
     }
 
     /// Write the necessary color spaces functions and ICC profiles to the
-    /// PDF file.
-    pub fn write_functions(&self, chunk: &mut Chunk) {
+    /// PDF file. If given, `rgb_profile` takes the place of the sRGB profile.
+    pub fn write_functions(&self, chunk: &mut Chunk, rgb_profile: Option<&[u8]>) {
         // Write the Oklab function & color space.
         if let Some(oklab) = self.oklab {
             chunk
@@ -126,8 +126,17 @@ impl ColorSpaces {
 
         // Write the sRGB color space.
         if let Some(srgb) = self.srgb {
+            let deflated;
+            let profile: &[u8] = match rgb_profile {
+                Some(profile) => {
+                    deflated = deflate(profile);
+                    &deflated
+                }
+                None => &SRGB_ICC_DEFLATED,
+            };
+
             chunk
-                .icc_profile(srgb, &SRGB_ICC_DEFLATED)
+                .icc_profile(srgb, profile)
                 .n(3)
                 .range([0.0, 1.0, 0.0, 1.0, 0.0, 1.0])
                 .filter(Filter::FlateDecode);
@@ -144,6 +153,11 @@ impl ColorSpaces {
     }
 }
 
+/// Whether ICC profile data describes an RGB color space.
+pub fn is_rgb_profile(data: &[u8]) -> bool {
+    data.get(16..20) == Some(&b"RGB "[..])
+}
+
 /// This function removes comments, line spaces and carriage returns from a
 /// PostScript program. This is necessary to optimize the size of the PDF file.
 fn minify(source: &str) -> String {
@@ -356,3 +370,132 @@ impl QuantizedColor for f32 {
         color.clamp(min, max)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::{Rgb, RgbImage};
+    use typst::foundations::Bytes;
+
+    use crate::tests::{pixel, Object, TestPdf, TestWorld};
+    use crate::PdfOptions;
+
+    /// The RGB colors set in the content streams, in 8-bit precision.
+    fn rgb_colors(pdf: &TestPdf) -> Vec<[u8; 3]> {
+        pdf.ops()
+            .into_iter()
+            .filter(|op| matches!(op.operator.as_str(), "sc" | "SC" | "scn" | "SCN"))
+            .filter_map(|op| {
+                let values: Vec<_> =
+                    op.numbers().iter().map(|v| (v * 255.0).round() as u8).collect();
+                values.try_into().ok()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_working_rgb_profile() {
+        let untagged =
+            RgbImage::from_fn(4, 2, |x, y| Rgb([60 * x as u8, 100 * y as u8, 7]));
+        let mut png = Cursor::new(vec![]);
+        untagged.write_to(&mut png, image::ImageFormat::Png).unwrap();
+        let world = TestWorld::new(
+            "#set page(fill: rgb(10, 20, 30))\n\
+             #text(fill: rgb(200, 0, 0))[Hello]\n\
+             #rect(fill: rgb(0, 128, 255), stroke: rgb(1, 2, 3))\n\
+             #image(\"/untagged.png\", width: 10pt)\n\
+             #text(fill: rgb(0, 0, 255, 50%))[World]",
+        )
+        .with_file("/untagged.png", png.into_inner());
+
+        // A copy of sRGB without its profile ID stands in for another
+        // working space.
+        let mut profile = typst_assets::icc::S_RGB_V4.to_vec();
+        profile[84..100].fill(0);
+
+        for working_rgb_profile in [None, Some(Bytes::from(profile))] {
+            let expected = working_rgb_profile
+                .as_deref()
+                .unwrap_or(typst_assets::icc::S_RGB_V4)
+                .to_vec();
+            let options = PdfOptions { working_rgb_profile, ..Default::default() };
+            let pdf = world.export(&options);
+            assert!(pdf.warnings.is_empty());
+
+            // There is exactly one RGB profile, which the colors of the
+            // content, the untagged image, and the transparency group use.
+            let profiles: Vec<_> = pdf
+                .file
+                .objects
+                .iter()
+                .filter_map(|(&id, object)| Some((id, object.as_stream()?)))
+                .filter(|(_, stream)| stream.dict.int("N") == Some(3))
+                .collect();
+            assert_eq!(profiles.len(), 1);
+            assert_eq!(profiles[0].1.data, expected);
+            let references: Vec<_> = pdf
+                .all()
+                .into_iter()
+                .filter_map(Object::as_array)
+                .filter(|array| array.first() == Some(&Object::Name("ICCBased".into())))
+                .map(|array| array[1].as_ref())
+                .collect();
+            assert!(references.len() >= 3);
+            assert!(references.iter().all(|&r| r == Some(profiles[0].0)));
+
+            // The colors are written as given.
+            let colors = rgb_colors(&pdf);
+            for color in
+                [[10, 20, 30], [200, 0, 0], [0, 128, 255], [1, 2, 3], [0, 0, 255]]
+            {
+                assert!(colors.contains(&color), "{color:?} is missing");
+            }
+            let image = pdf.subtype("Image")[0];
+            assert_eq!(image.data, untagged.as_raw().as_slice());
+        }
+    }
+
+    #[test]
+    fn test_working_rgb_profile_rendering() {
+        // A copy of sRGB whose red and green primaries are swapped, so that
+        // colors visibly change when a viewer applies it.
+        let mut profile = typst_assets::icc::S_RGB_V4.to_vec();
+        profile[84..100].fill(0);
+        let count = u32::from_be_bytes(profile[128..132].try_into().unwrap()) as usize;
+        let entry = |signature: &[u8]| {
+            (0..count)
+                .map(|i| 132 + 12 * i)
+                .find(|&at| &profile[at..at + 4] == signature)
+                .unwrap()
+        };
+        let (red, green) = (entry(b"rXYZ"), entry(b"gXYZ"));
+        for i in 4..12 {
+            profile.swap(red + i, green + i);
+        }
+
+        let world = TestWorld::new(
+            "#set page(width: 20pt, height: 20pt, fill: rgb(255, 0, 0))
+             #place(rect(width: 10pt, height: 10pt, fill: rgb(0, 0, 255)))",
+        );
+        let render = |working_rgb_profile| {
+            let options = PdfOptions { working_rgb_profile, ..Default::default() };
+            let pixmap = world.export(&options).render(0, 1.0);
+            (pixel(&pixmap, 15, 15), pixel(&pixmap, 5, 5))
+        };
+        let close = |[r, g, b, _]: [u8; 4], expected: [u8; 3]| {
+            [r, g, b].iter().zip(expected).all(|(&c, e)| c.abs_diff(e) <= 8)
+        };
+
+        // Without a working space, the colors are sRGB.
+        let (page, rect) = render(None);
+        assert!(close(page, [255, 0, 0]), "{page:?}");
+        assert!(close(rect, [0, 0, 255]), "{rect:?}");
+
+        // With one, viewers show red as what the profile makes of it, while
+        // blue is kept.
+        let (page, rect) = render(Some(Bytes::from(profile)));
+        assert!(close(page, [0, 255, 0]), "{page:?}");
+        assert!(close(rect, [0, 0, 255]), "{rect:?}");
+    }
+}
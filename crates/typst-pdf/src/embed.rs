@@ -0,0 +1,184 @@
+use ecow::EcoString;
+use md5::{Digest, Md5};
+use pdf_writer::{Filter, Finish, Name, Ref, Str, TextStr};
+
+use crate::{deflate, pdf_date, PdfContext, PdfEmbeddedFile};
+
+/// Write the document-level embedded files.
+///
+/// Returns the names and file specification references of the files, sorted
+/// by name as required for the embedded file name tree.
+pub(crate) fn write_embedded_files(ctx: &mut PdfContext) -> Vec<(EcoString, Ref)> {
+    let mut files = vec![];
+    for file in &ctx.options.embedded_files {
+        let spec_id = ctx.alloc.bump();
+        write_embedded_file(ctx, file, spec_id);
+        files.push((file.name.clone(), spec_id));
+    }
+
+    files.sort_by(|(a, _), (b, _)| a.cmp(b));
+    files
+}
+
+/// Write the file specification and embedded file stream of a file.
+pub(crate) fn write_embedded_file(
+    ctx: &mut PdfContext,
+    file: &PdfEmbeddedFile,
+    spec_id: Ref,
+) {
+    let file_id = ctx.alloc.bump();
+
+    let mut spec = ctx.pdf.indirect(spec_id).dict();
+    spec.pair(Name(b"Type"), Name(b"Filespec"));
+    spec.pair(Name(b"F"), Str(file.name.as_bytes()));
+    spec.pair(Name(b"UF"), TextStr(&file.name));
+    if let Some(description) = &file.description {
+        spec.pair(Name(b"Desc"), TextStr(description));
+    }
+    if let Some(relationship) = file.relationship {
+        spec.pair(Name(b"AFRelationship"), relationship.to_pdf());
+        ctx.associated_files.push(spec_id);
+    }
+    spec.insert(Name(b"EF")).dict().pair(Name(b"F"), file_id);
+    spec.finish();
+
+    let data = deflate(&file.data);
+    let mut stream = ctx.pdf.stream(file_id, &data);
+    stream.filter(Filter::FlateDecode);
+    stream.pair(Name(b"Type"), Name(b"EmbeddedFile"));
+    if let Some(mime_type) = &file.mime_type {
+        stream.pair(Name(b"Subtype"), Name(mime_type.as_bytes()));
+    }
+
+    // The checksum lets processors detect whether the file is damaged.
+    let mut params = stream.insert(Name(b"Params")).dict();
+    params.pair(Name(b"Size"), file.data.len() as i32);
+    params.pair(Name(b"CheckSum"), Str(&Md5::digest(&file.data)));
+    if let Some(date) = file.modified.and_then(|date| pdf_date(date, false)) {
+        params.pair(Name(b"ModDate"), date);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use md5::{Digest, Md5};
+    use typst::foundations::{Bytes, Datetime};
+
+    use crate::tests::{Object, TestPdf, TestWorld};
+    use crate::{AssociatedFileRelationship, PdfEmbeddedFile, PdfOptions};
+
+    /// The file specifications listed in the catalog's `/AF` array.
+    fn associated_files(pdf: &TestPdf) -> Vec<&Object> {
+        let associated = pdf.catalog().get("AF").expect("catalog without /AF");
+        pdf.get(associated).as_array().unwrap().iter().collect()
+    }
+
+    #[test]
+    fn test_embedded_source_round_trip() {
+        let source = "= Introduction\nHello";
+        let world = TestWorld::new(source);
+        assert_eq!(world.export(&PdfOptions::default()).count("EF"), 0);
+
+        let file = PdfEmbeddedFile {
+            name: "main.typ".into(),
+            data: Bytes::from(source.as_bytes()),
+            description: None,
+            mime_type: Some("text/plain".into()),
+            modified: None,
+            relationship: Some(AssociatedFileRelationship::Source),
+        };
+        let embedded_files = vec![file];
+        let pdf = world.export(&PdfOptions { embedded_files, ..Default::default() });
+
+        // The file is listed in the name tree and as an associated file of
+        // the document.
+        let associated = associated_files(&pdf);
+        assert_eq!(associated.len(), 1);
+        assert_eq!(pdf.count("AF"), 1);
+        let reference = associated[0];
+        let names = pdf.dict(pdf.dict(pdf.catalog(), "Names"), "EmbeddedFiles");
+        assert_eq!(
+            names.get("Names").and_then(Object::as_array).unwrap(),
+            [Object::Str(b"main.typ".to_vec()), reference.clone()]
+        );
+
+        // Extract the file and compare it to the source.
+        let spec = pdf.get(reference).as_dict().unwrap();
+        assert_eq!(spec.name("Type"), Some("Filespec"));
+        assert_eq!(spec.name("AFRelationship"), Some("Source"));
+        let file = pdf.get(pdf.dict(spec, "EF").get("F").unwrap()).as_stream().unwrap();
+        assert_eq!(file.dict.name("Subtype"), Some("text/plain"));
+        assert_eq!(file.data, source.as_bytes());
+        let params = pdf.dict(&file.dict, "Params");
+        assert_eq!(params.int("Size"), Some(source.len() as i64));
+        let checksum = params.get("CheckSum").and_then(Object::as_bytes).unwrap();
+        assert_eq!(checksum, Md5::digest(source.as_bytes()).as_slice());
+    }
+
+    #[test]
+    fn test_embedded_files_without_relationship() {
+        let file = PdfEmbeddedFile {
+            name: "notes.txt".into(),
+            data: Bytes::from_static(b"notes"),
+            description: None,
+            mime_type: None,
+            modified: None,
+            relationship: None,
+        };
+        let embedded_files = vec![file];
+        let pdf = TestWorld::new("Hello")
+            .export(&PdfOptions { embedded_files, ..Default::default() });
+
+        // The file is embedded, but not associated with the document.
+        assert_eq!(pdf.count("EF"), 1);
+        assert_eq!(pdf.count("AF"), 0);
+        assert_eq!(pdf.count("AFRelationship"), 0);
+    }
+
+    #[test]
+    fn test_electronic_invoice() {
+        // A hybrid invoice in the style of ZUGFeRD and Factur-X: the
+        // machine-readable invoice is attached as an alternative
+        // representation of the document under its prescribed name.
+        let invoice = br#"<?xml version="1.0" encoding="UTF-8"?>
+<rsm:CrossIndustryInvoice
+    xmlns:rsm="urn:un:unece:uncefact:data:standard:CrossIndustryInvoice:100">
+  <rsm:ExchangedDocument><ram:ID>INV-1</ram:ID></rsm:ExchangedDocument>
+</rsm:CrossIndustryInvoice>
+"#;
+        let modified = Datetime::from_ymd_hms(2024, 3, 1, 12, 30, 0).unwrap();
+        let file = PdfEmbeddedFile {
+            name: "factur-x.xml".into(),
+            data: Bytes::from_static(invoice),
+            description: Some("Factur-X Invoice".into()),
+            mime_type: Some("text/xml".into()),
+            modified: Some(modified),
+            relationship: Some(AssociatedFileRelationship::Alternative),
+        };
+        let embedded_files = vec![file];
+        let pdf = TestWorld::new("= Invoice INV-1")
+            .export(&PdfOptions { embedded_files, ..Default::default() });
+
+        // These are the entries that invoice validators check for.
+        let associated = associated_files(&pdf);
+        assert_eq!(associated.len(), 1);
+        let spec = pdf.get(associated[0]).as_dict().unwrap();
+        let text = |dict: &crate::tests::Dict, key| {
+            dict.get(key).and_then(Object::as_text).unwrap_or_default()
+        };
+        assert_eq!(text(spec, "F"), "factur-x.xml");
+        assert_eq!(text(spec, "UF"), "factur-x.xml");
+        assert_eq!(text(spec, "Desc"), "Factur-X Invoice");
+        assert_eq!(spec.name("AFRelationship"), Some("Alternative"));
+
+        let file = pdf.get(pdf.dict(spec, "EF").get("F").unwrap()).as_stream().unwrap();
+        assert_eq!(file.dict.name("Type"), Some("EmbeddedFile"));
+        assert_eq!(file.dict.name("Subtype"), Some("text/xml"));
+        assert_eq!(file.data, invoice);
+        let params = pdf.dict(&file.dict, "Params");
+        assert_eq!(params.int("Size"), Some(invoice.len() as i64));
+        assert_eq!(text(params, "ModDate"), "D:20240301123000");
+        let checksum = params.get("CheckSum").and_then(Object::as_bytes).unwrap();
+        assert_eq!(checksum, Md5::digest(invoice).as_slice());
+    }
+}
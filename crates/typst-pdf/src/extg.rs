@@ -1,3 +1,5 @@
+use pdf_writer::Name;
+
 use crate::PdfContext;
 
 /// A PDF external graphics state.
@@ -7,11 +9,20 @@ pub struct ExtGState {
     pub stroke_opacity: u8,
     // In the range 0-255, needs to be divided before being written into the graphics state!
     pub fill_opacity: u8,
+    // Whether strokes overprint the content below them.
+    pub stroke_overprint: bool,
+    // Whether fills overprint the content below them.
+    pub fill_overprint: bool,
 }
 
 impl Default for ExtGState {
     fn default() -> Self {
-        Self { stroke_opacity: 255, fill_opacity: 255 }
+        Self {
+            stroke_opacity: 255,
+            fill_opacity: 255,
+            stroke_overprint: false,
+            fill_overprint: false,
+        }
     }
 }
 
@@ -19,6 +30,10 @@ impl ExtGState {
     pub fn uses_opacities(&self) -> bool {
         self.stroke_opacity != 255 || self.fill_opacity != 255
     }
+
+    pub fn uses_overprint(&self) -> bool {
+        self.stroke_overprint || self.fill_overprint
+    }
 }
 
 /// Embed all used external graphics states into the PDF.
@@ -26,9 +41,63 @@ pub(crate) fn write_external_graphics_states(ctx: &mut PdfContext) {
     for external_gs in ctx.extg_map.items() {
         let id = ctx.alloc.bump();
         ctx.ext_gs_refs.push(id);
-        ctx.pdf
-            .ext_graphics(id)
+        let mut state = ctx.pdf.ext_graphics(id);
+        state
             .non_stroking_alpha(external_gs.fill_opacity as f32 / 255.0)
             .stroking_alpha(external_gs.stroke_opacity as f32 / 255.0);
+
+        // With overprint mode 1, zero components of a CMYK color leave the
+        // underlying inks alone instead of erasing them.
+        if external_gs.uses_overprint() {
+            state.pair(Name(b"OP"), external_gs.stroke_overprint);
+            state.pair(Name(b"op"), external_gs.fill_overprint);
+            state.pair(Name(b"OPM"), 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use typst::visualize::Color;
+
+    use crate::tests::{Object, TestWorld};
+    use crate::PdfOptions;
+
+    #[test]
+    fn test_overprint() {
+        let is_true = |value: &&Object| value == &&Object::Bool(true);
+        let options = PdfOptions { overprint_black: true, ..Default::default() };
+
+        // Black given in CMYK overprints.
+        let world = TestWorld::new(
+            "#set text(fill: cmyk(0%, 0%, 0%, 100%))
+             Hello
+             #line(length: 10pt, stroke: cmyk(0%, 0%, 0%, 100%))",
+        );
+        assert_eq!(world.export(&PdfOptions::default()).count("OPM"), 0);
+        let pdf = world.export(&options);
+        assert!(pdf.warnings.is_empty());
+        assert!(pdf.values("op").iter().any(is_true));
+        assert!(pdf.values("OP").iter().any(is_true));
+        assert!(pdf.values("OPM").iter().all(|value| value.as_int() == Some(1)));
+
+        // The default text and stroke colors are black, but not in CMYK, so
+        // they don't overprint and keep their color.
+        let world = TestWorld::new("Hello\n#line(length: 10pt)");
+        let pdf = world.export(&options);
+        assert_eq!(pdf.count("OPM"), 0);
+        assert_eq!(pdf.ops(), world.export(&PdfOptions::default()).ops());
+        assert_eq!(pdf.warnings.len(), 1);
+        assert!(pdf.warnings[0].message.contains("only applies to CMYK colors"));
+
+        // The same goes for other colors.
+        let world = TestWorld::new("#text(red)[Hello]");
+        let options = PdfOptions {
+            overprint_colors: vec![Color::RED],
+            ..Default::default()
+        };
+        let pdf = world.export(&options);
+        assert_eq!(pdf.count("OPM"), 0);
+        assert_eq!(pdf.warnings.len(), 1);
     }
 }
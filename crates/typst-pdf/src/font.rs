@@ -116,6 +116,10 @@ pub(crate) fn write_fonts(ctx: &mut PdfContext) {
         // Subset and write the font's bytes.
         let glyphs: Vec<_> = glyph_set.keys().copied().collect();
         let data = subset_font(font, &glyphs);
+        if let Some(stats) = &mut ctx.stats {
+            stats.fonts += 1;
+            stats.font_bytes += data.len();
+        }
 
         let mut stream = ctx.pdf.stream(data_ref, &data);
         stream.filter(Filter::FlateDecode);
@@ -7,9 +7,7 @@ use pdf_writer::writers::StreamShadingType;
 use pdf_writer::{Filter, Finish, Name, Ref};
 use typst::layout::{Abs, Angle, Point, Quadrant, Ratio, Transform};
 use typst::utils::Numeric;
-use typst::visualize::{
-    Color, ColorSpace, Gradient, RatioOrAngle, RelativeTo, WeightedColor,
-};
+use typst::visualize::{Color, ColorSpace, Gradient, RelativeTo, WeightedColor};
 
 use crate::color::{ColorSpaceExt, PaintEncode, QuantizedColor};
 use crate::page::{PageContext, PageResource, ResourceKind, Transforms};
@@ -156,21 +154,31 @@ fn shading_function(
     for window in gradient.stops_ref().windows(2) {
         let (first, second) = (window[0], window[1]);
 
-        // If we have a hue index, we will create several stops in-between
-        // to make the gradient smoother without interpolation issues with
-        // native color spaces.
-        let mut last_c = first.0;
-        if gradient.space().hue_index().is_some() {
-            for i in 0..=32 {
-                let t = i as f64 / 32.0;
-                let real_t = first.1.get() * (1.0 - t) + second.1.get() * t;
-
-                let c = gradient.sample(RatioOrAngle::Ratio(Ratio::new(real_t)));
-                functions.push(single_gradient(ctx, last_c, c, color_space));
-                bounds.push(real_t as f32);
+        // If we have a hue index, interpolating linearly in the encoded color
+        // space doesn't follow the gradient, so we split the segment into
+        // several smaller ones, with more of them where the gradient bends.
+        if gradient.space().hue_index().is_some() && first.1 != second.1 {
+            let start = (first.1.get(), first.0);
+            let end = (second.1.get(), second.0);
+            let mut samples = vec![start];
+            subdivide_segment(
+                gradient.space(),
+                color_space,
+                start,
+                end,
+                (0.0, first.0),
+                (1.0, second.0),
+                0,
+                &mut samples,
+            );
+
+            for pair in samples.windows(2) {
+                let ((_, c0), (t1, c1)) = (pair[0], pair[1]);
+                functions.push(single_gradient(ctx, c0, c1, color_space));
+                bounds.push(t1 as f32);
                 encode.extend([0.0, 1.0]);
-                last_c = c;
             }
+            continue;
         }
 
         bounds.push(second.1.get() as f32);
@@ -198,6 +206,87 @@ fn shading_function(
     function
 }
 
+/// The minimum number of times a hue-based segment is halved.
+///
+/// The error is only measured at the middle of a part. A hue rotation can
+/// cross the straight line between the colors at the ends right there, so
+/// every segment is first split into four parts and checked at five points.
+const MIN_SUBDIVISION_DEPTH: usize = 2;
+
+/// The maximum number of times a hue-based segment is halved.
+///
+/// Splitting a segment into 2^8 = 256 parts is enough for any color
+/// component to change by at most one 8-bit step per part, even if it
+/// sweeps the whole range. Finer parts can't be distinguished in 8-bit
+/// output.
+const MAX_SUBDIVISION_DEPTH: usize = 8;
+
+/// The maximum allowed distance in the encoded color space between the
+/// gradient and its piecewise linear approximation.
+///
+/// This is half of an 8-bit step, such that the approximated colors mostly
+/// round to the same 8-bit values as the exact ones.
+const MAX_SUBDIVISION_ERROR: f32 = 0.5 / 255.0;
+
+/// Approximates the part of a segment between the local progress values
+/// `t0` and `t1`, whose colors are `c0` and `c1`, by samples, pushing all of
+/// them except for the start.
+///
+/// The part is halved until the color at its middle lies close enough to
+/// the linear interpolation of its endpoints in the encoded color space.
+#[allow(clippy::too_many_arguments)]
+fn subdivide_segment(
+    space: ColorSpace,
+    encode_space: ColorSpace,
+    start: (f64, Color),
+    end: (f64, Color),
+    (t0, c0): (f64, Color),
+    (t1, c1): (f64, Color),
+    depth: usize,
+    samples: &mut Vec<(f64, Color)>,
+) {
+    let position = |t: f64| start.0 * (1.0 - t) + end.0 * t;
+
+    // If the colors can't be mixed, the part stays a linear interpolation
+    // of its endpoints.
+    let mid = (t0 + t1) / 2.0;
+    let mixed = (depth < MAX_SUBDIVISION_DEPTH).then(|| {
+        Color::mix_iter(
+            [WeightedColor::new(start.1, 1.0 - mid), WeightedColor::new(end.1, mid)],
+            space,
+        )
+    });
+
+    if let Some(Ok(color)) = mixed {
+        let actual: [f32; 3] = encode_space.convert(color);
+        let a: [f32; 3] = encode_space.convert(c0);
+        let b: [f32; 3] = encode_space.convert(c1);
+        let error = (0..3)
+            .map(|i| (actual[i] - (a[i] + b[i]) / 2.0).powi(2))
+            .sum::<f32>()
+            .sqrt();
+
+        if depth < MIN_SUBDIVISION_DEPTH || error > MAX_SUBDIVISION_ERROR {
+            let halves = [((t0, c0), (mid, color)), ((mid, color), (t1, c1))];
+            for (from, to) in halves {
+                subdivide_segment(
+                    space,
+                    encode_space,
+                    start,
+                    end,
+                    from,
+                    to,
+                    depth + 1,
+                    samples,
+                );
+            }
+            return;
+        }
+    }
+
+    samples.push((position(t1), c1));
+}
+
 /// Writes an expontential function that expresses a single segment (between two
 /// stops) of a gradient.
 fn single_gradient(
@@ -466,3 +555,47 @@ fn compute_vertex_stream(gradient: &Gradient, aspect_ratio: Ratio) -> Arc<Vec<u8
 
     Arc::new(deflate(&vertices))
 }
+
+#[cfg(test)]
+mod tests {
+    use typst::visualize::Color;
+
+    use crate::tests::{pixel, render_reference, TestWorld};
+    use crate::PdfOptions;
+
+    /// The Oklab distance below which two colors are commonly considered
+    /// indistinguishable. Without subdivision, these gradients are off by
+    /// more than ten times this.
+    const JUST_NOTICEABLE_DIFFERENCE: f32 = 0.02;
+
+    #[test]
+    fn test_hue_gradient_fidelity() {
+        for gradient in [
+            "blue, yellow, space: oklch",
+            "red, aqua, space: color.hsl",
+            "blue, yellow, red, green, space: color.hsv",
+            "rgb(\"#0000ff\"), rgb(\"#ffff00\"), space: oklch, angle: 30deg",
+        ] {
+            let world = TestWorld::new(&format!(
+                "#set page(width: auto, height: auto, margin: 0pt)\n\
+                 #rect(width: 200pt, height: 20pt, fill: gradient.linear({gradient}))"
+            ));
+            let pdf = world.export(&PdfOptions::default());
+            let ours = pdf.render(0, 1.0);
+            let reference = render_reference(&world.compile(), 1.0);
+
+            let mut worst: f32 = 0.0;
+            for y in 2..ours.height() - 2 {
+                for x in 2..ours.width() - 2 {
+                    let [r0, g0, b0, _] = pixel(&ours, x, y);
+                    let [r1, g1, b1, _] = pixel(&reference, x, y);
+                    let p = Color::from_u8(r0, g0, b0, 255).to_oklab().to_vec4();
+                    let q = Color::from_u8(r1, g1, b1, 255).to_oklab().to_vec4();
+                    let delta = (0..3).map(|i| (p[i] - q[i]).powi(2)).sum::<f32>().sqrt();
+                    worst = worst.max(delta);
+                }
+            }
+            assert!(worst < JUST_NOTICEABLE_DIFFERENCE, "{gradient}: ΔE {worst}");
+        }
+    }
+}
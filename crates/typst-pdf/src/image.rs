@@ -1,51 +1,145 @@
 use std::collections::HashMap;
 use std::io::Cursor;
 
-use image::{DynamicImage, GenericImageView, Rgba};
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{ColorType, DynamicImage, GenericImageView, GrayImage, Rgba};
 use pdf_writer::{Chunk, Filter, Finish, Ref};
-use typst::utils::Deferred;
+use typst::foundations::{Bytes, Smart};
+use typst::layout::Frame;
+use typst::utils::{hash128, Deferred, Scalar};
 use typst::visualize::{
-    ColorSpace, Image, ImageKind, RasterFormat, RasterImage, SvgImage,
+    ColorSpace, Image, ImageKind, ImageScaling, RasterFormat, RasterImage, SvgImage,
 };
 
-use crate::{deflate, PdfContext};
+use crate::{deflate, AbsExt, PdfContext, PdfThumbnails};
 
-/// Creates a new PDF image from the given image.
+/// Creates a new PDF image from the given image, encoded with the given
+/// settings.
 ///
 /// Also starts the deferred encoding of the image.
 #[comemo::memoize]
-pub fn deferred_image(image: Image) -> Deferred<EncodedImage> {
+pub fn deferred_image(image: Image, settings: ImageSettings) -> Deferred<EncodedImage> {
     Deferred::new(move || match image.kind() {
         ImageKind::Raster(raster) => {
             let raster = raster.clone();
-            let (width, height) = (raster.width(), raster.height());
-            let (data, filter, has_color) = encode_raster_image(&raster);
-            let icc = raster.icc().map(deflate);
+            let rotation = embedded_rotation(&raster);
+            let resized = settings.downsampled.map(|(w, h)| {
+                // Downsampled data keeps the orientation in which the data of
+                // the original is stored, so that the placements of the image
+                // are drawn the same either way.
+                let filter = FilterType::Lanczos3;
+                match rotation {
+                    Some(rotation @ 5..=8) => {
+                        stored_orientation(raster.dynamic(), rotation)
+                            .resize_exact(h, w, filter)
+                    }
+                    Some(rotation) => stored_orientation(raster.dynamic(), rotation)
+                        .resize_exact(w, h, filter),
+                    None => raster.dynamic().resize_exact(w, h, filter),
+                }
+            });
+            // Resampling introduces gray levels that a mask can't show.
+            let resized = resized.map(|resized| match image.fill() {
+                Some(_) => DynamicImage::ImageLuma8(threshold(&resized)),
+                None => resized,
+            });
+            let dynamic = resized.as_ref().unwrap_or(raster.dynamic());
+            let (mut width, mut height) = (dynamic.width(), dynamic.height());
+            let (data, filter, color_space, bits_per_component) =
+                encode_raster_image(&raster, resized.as_ref());
+
+            // Data that is embedded as is may still be stored transposed.
+            if resized.is_none() && matches!(rotation, Some(5..=8)) {
+                std::mem::swap(&mut width, &mut height);
+            }
 
-            let alpha =
-                raster.dynamic().color().has_alpha().then(|| encode_alpha(&raster));
+            let icc = raster.icc().map(deflate);
+            let alpha = dynamic
+                .color()
+                .has_alpha()
+                .then(|| encode_alpha(dynamic, bits_per_component));
 
-            EncodedImage::Raster { data, filter, has_color, width, height, icc, alpha }
+            EncodedImage::Raster {
+                data,
+                filter,
+                color_space,
+                width,
+                height,
+                bits_per_component,
+                icc,
+                alpha,
+            }
         }
-        ImageKind::Svg(svg) => EncodedImage::Svg(encode_svg(svg)),
+        ImageKind::Svg(svg) => EncodedImage::Svg(encode_svg(svg, settings)),
+    })
+}
+
+/// Renders a page at thumbnail size.
+///
+/// Also starts the deferred rendering and encoding of the thumbnail.
+pub fn deferred_thumbnail(
+    frame: Frame,
+    thumbnails: PdfThumbnails,
+) -> Deferred<EncodedThumbnail> {
+    Deferred::new(move || {
+        let size = frame.size();
+        let pixel_per_pt =
+            thumbnails.max_size as f32 / size.x.max(size.y).to_f32().max(1.0);
+        let (width, height, rgb) = (thumbnails.render)(&frame, pixel_per_pt);
+        assert_eq!(rgb.len(), 3 * width as usize * height as usize);
+
+        let mut data = vec![];
+        JpegEncoder::new_with_quality(&mut data, thumbnails.quality.clamp(1, 100))
+            .encode(&rgb, width, height, ColorType::Rgb8)
+            .unwrap();
+
+        EncodedThumbnail { data, width, height }
     })
 }
 
 /// Embed all used images into the PDF.
 #[typst_macros::time(name = "write images")]
 pub(crate) fn write_images(ctx: &mut PdfContext) {
-    for (i, _) in ctx.image_map.items().enumerate() {
-        let handle = ctx.image_deferred_map.get(&i).unwrap();
+    // Images often share the same profile (e.g. all photos from one camera),
+    // so we only write each distinct one once.
+    let mut icc_refs = HashMap::new();
+    let mut saved = 0;
+
+    // The settings of an image are only known once all of its placements
+    // have been seen, so the encoding starts here for all of them at once.
+    let handles: Vec<_> = ctx
+        .image_map
+        .items()
+        .enumerate()
+        .map(|(i, image)| deferred_image(image.clone(), ctx.image_settings[&i]))
+        .collect();
+
+    for (i, (typst_image, handle)) in ctx.image_map.items().zip(&handles).enumerate() {
+        let settings = ctx.image_settings[&i];
+        let interpolate = typst_image.scaling() == Smart::Custom(ImageScaling::Smooth);
         match handle.wait() {
             EncodedImage::Raster {
                 data,
                 filter,
-                has_color,
+                color_space,
                 width,
                 height,
+                bits_per_component,
                 icc,
                 alpha,
             } => {
+                if settings.downsampled.is_some() {
+                    saved += typst_image.data().len().saturating_sub(data.len());
+                }
+
+                if let Some(stats) = &mut ctx.stats {
+                    stats.images += 1;
+                    stats.image_bytes += data.len();
+                    stats.image_bytes +=
+                        alpha.as_ref().map_or(0, |(alpha, _)| alpha.len());
+                }
+
                 let image_ref = ctx.alloc.bump();
                 ctx.image_refs.push(image_ref);
 
@@ -53,18 +147,49 @@ pub(crate) fn write_images(ctx: &mut PdfContext) {
                 image.filter(*filter);
                 image.width(*width as i32);
                 image.height(*height as i32);
-                image.bits_per_component(8);
+                image.bits_per_component(i32::from(*bits_per_component));
+                if interpolate {
+                    image.interpolate(true);
+                }
+
+                // A filled black-and-white image is a stencil mask that paints
+                // its black pixels with the fill color set in the content
+                // stream. Those are the pixels whose sample is zero.
+                if typst_image.fill().is_some() {
+                    image.image_mask(true);
+                    image.finish();
+                    continue;
+                }
 
-                let mut icc_ref = None;
+                if let Some(intent) = ctx.options.image_rendering_intent {
+                    image.intent(intent.to_pdf());
+                }
+
+                let mut new_icc = None;
                 let space = image.color_space();
-                if icc.is_some() {
-                    let id = ctx.alloc.bump();
+                if let Some(icc) = icc {
+                    let key = hash128(&(icc, *color_space));
+                    let id = *icc_refs.entry(key).or_insert_with(|| {
+                        let id = ctx.alloc.bump();
+                        new_icc = Some((icc, id));
+                        id
+                    });
                     space.icc_based(id);
-                    icc_ref = Some(id);
-                } else if *has_color {
-                    ctx.colors.write(ColorSpace::Srgb, space, &mut ctx.alloc);
                 } else {
-                    ctx.colors.write(ColorSpace::D65Gray, space, &mut ctx.alloc);
+                    match color_space {
+                        EncodedColorSpace::Gray => {
+                            ctx.colors.write(ColorSpace::D65Gray, space, &mut ctx.alloc)
+                        }
+                        EncodedColorSpace::Rgb => {
+                            ctx.colors.write(ColorSpace::Srgb, space, &mut ctx.alloc)
+                        }
+                        EncodedColorSpace::Cmyk { .. } => space.device_cmyk(),
+                    }
+                }
+
+                // Adobe applications write CMYK JPEGs with inverted values.
+                if let EncodedColorSpace::Cmyk { inverted: true } = color_space {
+                    image.decode([1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0, 0.0]);
                 }
 
                 // Add a second gray-scale image containing the alpha values if
@@ -79,24 +204,39 @@ pub(crate) fn write_images(ctx: &mut PdfContext) {
                     mask.width(*width as i32);
                     mask.height(*height as i32);
                     mask.color_space().device_gray();
-                    mask.bits_per_component(8);
+                    mask.bits_per_component(i32::from(*bits_per_component));
+                    if interpolate {
+                        mask.interpolate(true);
+                    }
                 } else {
                     image.finish();
                 }
 
-                if let (Some(icc), Some(icc_ref)) = (icc, icc_ref) {
+                if let Some((icc, icc_ref)) = new_icc {
                     let mut stream = ctx.pdf.icc_profile(icc_ref, icc);
                     stream.filter(Filter::FlateDecode);
-                    if *has_color {
-                        stream.n(3);
-                        stream.alternate().srgb();
-                    } else {
-                        stream.n(1);
-                        stream.alternate().d65_gray();
+                    match color_space {
+                        EncodedColorSpace::Gray => {
+                            stream.n(1);
+                            stream.alternate().d65_gray();
+                        }
+                        EncodedColorSpace::Rgb => {
+                            stream.n(3);
+                            stream.alternate().srgb();
+                        }
+                        EncodedColorSpace::Cmyk { .. } => {
+                            stream.n(4);
+                            stream.alternate().device_cmyk();
+                        }
                     }
                 }
             }
             EncodedImage::Svg(chunk) => {
+                if let Some(stats) = &mut ctx.stats {
+                    stats.images += 1;
+                    stats.image_bytes += chunk.len();
+                }
+
                 let mut map = HashMap::new();
                 chunk.renumber_into(&mut ctx.pdf, |old| {
                     *map.entry(old).or_insert_with(|| ctx.alloc.bump())
@@ -105,81 +245,422 @@ pub(crate) fn write_images(ctx: &mut PdfContext) {
             }
         }
     }
+
+    if saved > 0 {
+        log::info!("downsampling images saved {saved} bytes");
+    }
+}
+
+/// Compute the pixel size to which an image with the given pixel size must be
+/// downsampled so that it does not exceed `max_dpi` when placed at `size` (in
+/// points). Returns `None` if the image does not exceed the limit.
+pub(crate) fn downsampled_size(
+    pixels: (u32, u32),
+    size: (f64, f64),
+    max_dpi: f64,
+) -> Option<(u32, u32)> {
+    // One inch has 72 points.
+    let limit = |px: u32, pt: f64| {
+        let max = (pt / 72.0 * max_dpi).ceil().max(1.0);
+        if f64::from(px) > max {
+            max as u32
+        } else {
+            px
+        }
+    };
+
+    let target = (limit(pixels.0, size.0), limit(pixels.1, size.1));
+    (target != pixels).then_some(target)
 }
 
-/// Encode an image with a suitable filter and return the data, filter and
-/// whether the image has color.
+/// Encode an image with a suitable filter and return the data, filter, color
+/// space, and the number of bits per component.
+///
+/// If `resized` is given, its pixels are encoded instead of the image's own.
+/// Photos (JPEGs) are then re-encoded as JPEG and everything else with Flate.
 ///
 /// Skips the alpha channel as that's encoded separately.
-fn encode_raster_image(image: &RasterImage) -> (Vec<u8>, Filter, bool) {
-    let dynamic = image.dynamic();
+fn encode_raster_image(
+    image: &RasterImage,
+    resized: Option<&DynamicImage>,
+) -> (Bytes, Filter, EncodedColorSpace, u8) {
+    let dynamic = resized.unwrap_or(image.dynamic());
     let channel_count = dynamic.color().channel_count();
-    let has_color = channel_count > 2;
+    let color_space =
+        if channel_count > 2 { EncodedColorSpace::Rgb } else { EncodedColorSpace::Gray };
 
     if image.format() == RasterFormat::Jpg {
+        // Most JPEGs can be embedded as they are. This avoids a lossy
+        // re-encoding and typically results in a much smaller file. It also
+        // keeps CMYK JPEGs in CMYK instead of round-tripping them through
+        // RGB.
+        if let Some(color_space) =
+            jpeg_pass_through_color_space(image).filter(|_| resized.is_none())
+        {
+            // The data is shared with the image, so this doesn't copy it.
+            return (image.data().clone(), Filter::DctDecode, color_space, 8);
+        }
+
         let mut data = Cursor::new(vec![]);
         dynamic.write_to(&mut data, image::ImageFormat::Jpeg).unwrap();
-        (data.into_inner(), Filter::DctDecode, has_color)
+        (data.into_inner().into(), Filter::DctDecode, color_space, 8)
     } else {
+        // Viewers that smooth images interpolate the color and the soft mask
+        // separately, so the color of invisible pixels (often black) would
+        // bleed into visible edges. We thus give them the color of the
+        // closest visible pixel.
+        let width = dynamic.width() as usize;
+        let visible: Option<Vec<bool>> = dynamic
+            .color()
+            .has_alpha()
+            .then(|| dynamic.pixels().map(|(_, _, Rgba([_, _, _, a]))| a > 0).collect());
+        let visible = visible.as_deref();
+
         // TODO: Encode flate streams with PNG-predictor?
-        let data = match (dynamic, channel_count) {
-            (DynamicImage::ImageLuma8(luma), _) => deflate(luma.as_raw()),
-            (DynamicImage::ImageRgb8(rgb), _) => deflate(rgb.as_raw()),
+        let (data, bits) = match (dynamic, channel_count) {
+            // Bilevel images like scanned signatures or line art only need a
+            // single bit per pixel.
+            (DynamicImage::ImageLuma8(luma), _)
+                if luma.as_raw().iter().all(|&v| v == 0 || v == 255) =>
+            {
+                (deflate(&pack_bits(luma)), 1)
+            }
+            (DynamicImage::ImageLuma8(luma), _) => (deflate(luma.as_raw()), 8),
+            (DynamicImage::ImageRgb8(rgb), _) => (deflate(rgb.as_raw()), 8),
+            // Images with 16 bits per channel keep their full depth.
+            (DynamicImage::ImageLuma16(_) | DynamicImage::ImageLumaA16(_), _) => {
+                let mut samples = dynamic.to_luma16().into_raw();
+                bleed_invisible(&mut samples, visible, width, 1);
+                (deflate(be_bytes(&mut samples)), 16)
+            }
+            (DynamicImage::ImageRgb16(_) | DynamicImage::ImageRgba16(_), _) => {
+                let mut samples = dynamic.to_rgb16().into_raw();
+                bleed_invisible(&mut samples, visible, width, 3);
+                (deflate(be_bytes(&mut samples)), 16)
+            }
             // Grayscale image
-            (_, 1 | 2) => deflate(dynamic.to_luma8().as_raw()),
+            (_, 1 | 2) => {
+                let mut samples = dynamic.to_luma8().into_raw();
+                bleed_invisible(&mut samples, visible, width, 1);
+                (deflate(&samples), 8)
+            }
             // Anything else
-            _ => deflate(dynamic.to_rgb8().as_raw()),
+            _ => {
+                let mut samples = dynamic.to_rgb8().into_raw();
+                bleed_invisible(&mut samples, visible, width, 3);
+                (deflate(&samples), 8)
+            }
         };
-        (data, Filter::FlateDecode, has_color)
+        (data.into(), Filter::FlateDecode, color_space, bits)
     }
 }
 
-/// Encode an image's alpha channel if present.
-fn encode_alpha(raster: &RasterImage) -> (Vec<u8>, Filter) {
-    let pixels: Vec<_> = raster
-        .dynamic()
-        .pixels()
-        .map(|(_, _, Rgba([_, _, _, a]))| a)
-        .collect();
+/// Give each invisible pixel the color of the closest visible pixel in its
+/// row or, if the row has no visible pixels, the color of the closest row
+/// that has some.
+fn bleed_invisible<T: Copy>(
+    samples: &mut [T],
+    visible: Option<&[bool]>,
+    width: usize,
+    channels: usize,
+) {
+    let Some(visible) = visible else { return };
+    if width == 0 || !visible.contains(&false) {
+        return;
+    }
+
+    let height = visible.len() / width;
+    let mut filled = vec![true; height];
+    let mut before = vec![None; width];
+
+    for (y, row) in visible.chunks(width).enumerate() {
+        // Find the closest visible pixel before each pixel.
+        let mut last = None;
+        for (x, &vis) in row.iter().enumerate() {
+            if vis {
+                last = Some(x);
+            }
+            before[x] = last;
+        }
+
+        if last.is_none() {
+            filled[y] = false;
+            continue;
+        }
+
+        // Then, walk back and also consider the closest one after it.
+        let mut after = None;
+        for x in (0..width).rev() {
+            if row[x] {
+                after = Some(x);
+            } else if let Some(source) = closest(x, before[x], after) {
+                let (from, to) =
+                    ((y * width + source) * channels, (y * width + x) * channels);
+                samples.copy_within(from..from + channels, to);
+            }
+        }
+    }
+
+    // Fill rows without any visible pixels from the closest filled row.
+    let rows_before = prefix_positions(filled.iter().copied());
+    let mut rows_after = prefix_positions(filled.iter().copied().rev());
+    rows_after.reverse();
+
+    let len = width * channels;
+    for y in (0..height).filter(|&y| !filled[y]) {
+        let after = rows_after[y].map(|offset| height - 1 - offset);
+        if let Some(source) = closest(y, rows_before[y], after) {
+            samples.copy_within(source * len..(source + 1) * len, y * len);
+        }
+    }
+}
+
+/// For each position, the last position up to it at which the iterator
+/// yielded `true`.
+fn prefix_positions(iter: impl Iterator<Item = bool>) -> Vec<Option<usize>> {
+    let mut last = None;
+    iter.enumerate()
+        .map(|(i, yes)| {
+            if yes {
+                last = Some(i);
+            }
+            last
+        })
+        .collect()
+}
+
+/// Which of the two positions before and after `x` is closer to it.
+fn closest(x: usize, before: Option<usize>, after: Option<usize>) -> Option<usize> {
+    match (before, after) {
+        (Some(b), Some(a)) => Some(if x - b <= a - x { b } else { a }),
+        (b, a) => b.or(a),
+    }
+}
+
+/// Turn an image into a black-and-white one.
+fn threshold(image: &DynamicImage) -> GrayImage {
+    let mut luma = image.to_luma8();
+    for v in luma.iter_mut() {
+        *v = if *v < 128 { 0 } else { 255 };
+    }
+    luma
+}
+
+/// Pack a black-and-white image into one bit per pixel, with each row padded
+/// to a full byte.
+fn pack_bits(image: &GrayImage) -> Vec<u8> {
+    let width = image.width() as usize;
+    let mut packed = Vec::with_capacity(width.div_ceil(8) * image.height() as usize);
+    for row in image.as_raw().chunks(width.max(1)) {
+        for pixels in row.chunks(8) {
+            let byte = pixels
+                .iter()
+                .enumerate()
+                .fold(0, |byte, (i, &v)| byte | (u8::from(v != 0) << (7 - i)));
+            packed.push(byte);
+        }
+    }
+    packed
+}
+
+/// Serialize 16-bit samples in the big-endian order expected by PDF.
+///
+/// The samples are reordered in place to avoid another copy of the image.
+fn be_bytes(samples: &mut [u16]) -> &[u8] {
+    for sample in samples.iter_mut() {
+        *sample = sample.to_be();
+    }
+    bytemuck::cast_slice(samples)
+}
+
+/// Turn the decoded pixels of an image back into the given EXIF orientation
+/// in which its data is stored.
+fn stored_orientation(displayed: &DynamicImage, rotation: u32) -> DynamicImage {
+    match rotation {
+        2 => displayed.fliph(),
+        3 => displayed.rotate180(),
+        4 => displayed.flipv(),
+        5 => displayed.rotate90().fliph(),
+        6 => displayed.rotate270(),
+        7 => displayed.rotate90().flipv(),
+        8 => displayed.rotate90(),
+        _ => displayed.clone(),
+    }
+}
+
+/// The EXIF orientation in which the embedded data of an image is stored.
+///
+/// Decoded pixels are already rotated, but JPEGs that are embedded without
+/// re-encoding need to be rotated when they are drawn.
+pub(crate) fn embedded_rotation(image: &RasterImage) -> Option<u32> {
+    image
+        .exif_rotation()
+        .filter(|rotation| (2..=8).contains(rotation))
+        .filter(|_| jpeg_pass_through_color_space(image).is_some())
+}
+
+/// The color space in which the raw data of a JPEG image can be embedded into
+/// the PDF without re-encoding it, if that is possible.
+fn jpeg_pass_through_color_space(image: &RasterImage) -> Option<EncodedColorSpace> {
+    if image.format() != RasterFormat::Jpg {
+        return None;
+    }
+
+    let info = jpeg_info(image.data())?;
+    match (info.components, image.dynamic().color().channel_count()) {
+        (1, 1) => Some(EncodedColorSpace::Gray),
+        (3, 3) => Some(EncodedColorSpace::Rgb),
+        // CMYK (and YCCK) JPEGs are decoded to RGB, but PDF readers can
+        // handle them natively.
+        (4, _) => Some(EncodedColorSpace::Cmyk { inverted: info.adobe }),
+        _ => None,
+    }
+}
+
+/// Header information about a JPEG image.
+struct JpegInfo {
+    /// The number of color components.
+    components: u8,
+    /// Whether the image has an Adobe APP14 segment.
+    adobe: bool,
+}
+
+/// Read the header of a JPEG that PDF readers can decode, that is, one with a
+/// Huffman-coded baseline, extended sequential, or progressive frame and eight
+/// bits per component.
+fn jpeg_info(data: &[u8]) -> Option<JpegInfo> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+
+    let mut adobe = false;
+    let mut i = 2;
+    loop {
+        if *data.get(i)? != 0xFF {
+            return None;
+        }
+
+        let marker = *data.get(i + 1)?;
+        i += 2;
+
+        match marker {
+            // Fill bytes.
+            0xFF => i -= 1,
+            // Markers without a payload.
+            0x01 | 0xD0..=0xD7 => {}
+            // Supported frame types.
+            0xC0..=0xC2 => {
+                let precision = *data.get(i + 2)?;
+                let components = *data.get(i + 7)?;
+                return (precision == 8).then_some(JpegInfo { components, adobe });
+            }
+            // Lossless, hierarchical, and arithmetic-coded frames, as well as
+            // a scan or the end of the image before any frame.
+            0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF | 0xD9 | 0xDA => {
+                return None;
+            }
+            // Any other segment, among them the Adobe APP14 segment.
+            _ => {
+                if marker == 0xEE && data.get(i + 2..i + 7) == Some(b"Adobe".as_slice()) {
+                    adobe = true;
+                }
+
+                let len = u16::from_be_bytes([*data.get(i)?, *data.get(i + 1)?]);
+                i += usize::from(len);
+            }
+        }
+    }
+}
+
+/// Encode an image's alpha channel if present, with the given number of bits
+/// per component.
+fn encode_alpha(dynamic: &DynamicImage, bits_per_component: u8) -> (Vec<u8>, Filter) {
+    // Read 16-bit alpha samples straight from the pixel buffer instead of
+    // converting the whole image first.
+    let alpha16 = |raw: &[u16], channels: usize| -> Vec<u8> {
+        raw.chunks_exact(channels)
+            .flat_map(|pixel| pixel[channels - 1].to_be_bytes())
+            .collect()
+    };
+
+    let pixels: Vec<_> = match dynamic {
+        DynamicImage::ImageLumaA16(luma) if bits_per_component == 16 => {
+            alpha16(luma.as_raw(), 2)
+        }
+        DynamicImage::ImageRgba16(rgba) if bits_per_component == 16 => {
+            alpha16(rgba.as_raw(), 4)
+        }
+        _ if bits_per_component == 16 => alpha16(dynamic.to_rgba16().as_raw(), 4),
+        _ => dynamic.pixels().map(|(_, _, Rgba([_, _, _, a]))| a).collect(),
+    };
     (deflate(&pixels), Filter::FlateDecode)
 }
 
 /// Encode an SVG into a chunk of PDF objects.
 ///
 /// The main XObject will have ID 1.
-fn encode_svg(svg: &SvgImage) -> Chunk {
+fn encode_svg(svg: &SvgImage, settings: ImageSettings) -> Chunk {
     let mut chunk = Chunk::new();
+    let mut options = svg2pdf::Options::default();
+    if let Some(scale) = settings.svg_raster_scale {
+        options.raster_scale = scale.get() as f32;
+    }
 
     // Safety: We do not keep any references to tree nodes beyond the
     // scope of `with`.
     unsafe {
         svg.with(|tree| {
-            svg2pdf::convert_tree_into(
-                tree,
-                svg2pdf::Options::default(),
-                &mut chunk,
-                Ref::new(1),
-            );
+            svg2pdf::convert_tree_into(tree, options, &mut chunk, Ref::new(1));
         });
     }
 
     chunk
 }
 
+/// Settings that affect how an image is encoded.
+///
+/// Each placement of an image needs its own settings. The image is embedded
+/// only once, with the merged settings of all its placements.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ImageSettings {
+    /// The pixel size to which a raster image is downsampled, if any.
+    pub downsampled: Option<(u32, u32)>,
+    /// The scale at which parts of an SVG that can't be converted to vector
+    /// graphics are rasterized. When `None`, svg2pdf's default is used.
+    pub svg_raster_scale: Option<Scalar>,
+}
+
+impl ImageSettings {
+    /// Settings that satisfy the needs of both `self` and `other`.
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            // A placement that needs the full resolution gets it.
+            downsampled: self
+                .downsampled
+                .zip(other.downsampled)
+                .map(|(a, b)| (a.0.max(b.0), a.1.max(b.1))),
+            svg_raster_scale: self.svg_raster_scale.max(other.svg_raster_scale),
+        }
+    }
+}
+
 /// A pre-encoded image.
 pub enum EncodedImage {
     /// A pre-encoded rasterized image.
     Raster {
         /// The raw, pre-deflated image data.
-        data: Vec<u8>,
+        data: Bytes,
         /// The filter to use for the image.
         filter: Filter,
-        /// Whether the image has color.
-        has_color: bool,
+        /// The color space of the image data.
+        color_space: EncodedColorSpace,
         /// The image's width.
         width: u32,
         /// The image's height.
         height: u32,
+        /// The number of bits per color component, for the image and its
+        /// alpha channel alike.
+        bits_per_component: u8,
         /// The image's ICC profile, pre-deflated, if any.
         icc: Option<Vec<u8>>,
         /// The alpha channel of the image, pre-deflated, if any.
@@ -190,3 +671,687 @@ pub enum EncodedImage {
     /// The chunk is the SVG converted to PDF objects.
     Svg(Chunk),
 }
+
+/// A page thumbnail encoded as an RGB JPEG.
+pub struct EncodedThumbnail {
+    /// The JPEG data.
+    pub data: Vec<u8>,
+    /// The thumbnail's width.
+    pub width: u32,
+    /// The thumbnail's height.
+    pub height: u32,
+}
+
+/// The color space of an encoded raster image.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum EncodedColorSpace {
+    /// Grayscale data.
+    Gray,
+    /// RGB data.
+    Rgb,
+    /// CMYK data, possibly with inverted values.
+    Cmyk { inverted: bool },
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::codecs::jpeg::JpegEncoder;
+    use image::{
+        DynamicImage, GrayImage, ImageBuffer, Luma, Rgb, RgbImage, Rgba, RgbaImage,
+    };
+    use typst::foundations::Bytes;
+    use typst::visualize::{RasterFormat, RasterImage};
+
+    use super::downsampled_size;
+    use crate::tests::{assert_renders_like, pixel, TestPdf, TestWorld};
+    use crate::{PdfOptions, RenderingIntent};
+
+    #[test]
+    fn test_downsampled_size() {
+        // Two by one and a half inches at 300 DPI.
+        assert_eq!(
+            downsampled_size((4000, 3000), (144.0, 108.0), 300.0),
+            Some((600, 450))
+        );
+        // Only the axis that exceeds the limit is reduced.
+        assert_eq!(downsampled_size((1000, 100), (72.0, 72.0), 300.0), Some((300, 100)));
+        // Fractional limits are rounded up so as not to fall below them.
+        assert_eq!(downsampled_size((1000, 1000), (10.0, 10.0), 100.0), Some((14, 14)));
+        // Tiny placements keep at least one pixel.
+        assert_eq!(downsampled_size((50, 50), (0.01, 0.01), 72.0), Some((1, 1)));
+        // Images at or below the limit are left alone.
+        assert_eq!(downsampled_size((300, 300), (72.0, 72.0), 300.0), None);
+        assert_eq!(downsampled_size((100, 80), (72.0, 72.0), 300.0), None);
+    }
+
+    #[test]
+    fn test_downsampling() {
+        let world = TestWorld::new("#image(\"/translucent.png\", width: 72pt)")
+            .with_file("/translucent.png", translucent_png());
+        let options = PdfOptions { max_image_dpi: Some(10.0), ..Default::default() };
+        let original = world.export(&PdfOptions::default());
+        let downsampled = world.export(&options);
+
+        // An inch wide at ten DPI are ten pixels, for the image and its mask.
+        let widths = |pdf: &TestPdf| {
+            pdf.subtype("Image")
+                .iter()
+                .map(|image| image.dict.int("Width"))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(widths(&downsampled), [Some(10), Some(10)]);
+        assert_ne!(widths(&original), [Some(10), Some(10)]);
+        assert!(downsampled.bytes.len() < original.bytes.len());
+
+        // An image placed at several sizes is embedded once, with enough
+        // pixels for the largest one.
+        let world = TestWorld::new(
+            "#image(\"/translucent.png\", width: 72pt)\n\
+             #image(\"/translucent.png\", width: 144pt)\n\
+             #image(\"/translucent.png\", width: 36pt)",
+        )
+        .with_file("/translucent.png", translucent_png());
+        assert_eq!(widths(&world.export(&options)), [Some(20), Some(20)]);
+        assert_renders_like(&world, &options);
+
+        // If one placement needs the full resolution, all get it.
+        let world = TestWorld::new(
+            "#image(\"/translucent.png\", width: 72pt)\n\
+             #image(\"/translucent.png\", width: 720pt)",
+        )
+        .with_file("/translucent.png", translucent_png());
+        assert_eq!(widths(&world.export(&options)), [Some(100), Some(100)]);
+    }
+
+    #[test]
+    fn test_downsampled_rotation() {
+        // Downsampled images are drawn like the originals, whatever the
+        // orientation in which their data is stored.
+        let options = PdfOptions { max_image_dpi: Some(36.0), ..Default::default() };
+        for orientation in 1..=8 {
+            let world = TestWorld::new(
+                "#set page(width: auto, height: auto, margin: 0pt)\n\
+                 #image(\"/test.jpg\", width: 48pt)",
+            )
+            .with_file("/test.jpg", oriented_jpeg(orientation));
+
+            let pdf = world.export(&options);
+            let image = pdf.subtype("Image")[0];
+            let size =
+                (image.dict.int("Width").unwrap(), image.dict.int("Height").unwrap());
+            // At 36 DPI, every displayed point needs half a pixel. Transposed
+            // images are displayed 48pt wide and 72pt high, but stored with
+            // their width and height swapped.
+            let stored = if orientation >= 5 { (36, 24) } else { (24, 16) };
+            assert_eq!(size, stored, "{orientation}");
+            assert_renders_like(&world, &options);
+        }
+    }
+
+    /// A hundred pixels wide PNG with an alpha channel, so that it is
+    /// embedded with a soft mask.
+    fn translucent_png() -> Vec<u8> {
+        let image = RgbaImage::from_fn(100, 50, |x, y| Rgba([x as u8, y as u8, 0, 128]));
+        let mut data = Cursor::new(vec![]);
+        image.write_to(&mut data, image::ImageFormat::Png).unwrap();
+        data.into_inner()
+    }
+
+    /// Encode an image as PNG.
+    fn png<P, C>(image: &ImageBuffer<P, C>) -> Vec<u8>
+    where
+        P: image::PixelWithColorType,
+        [P::Subpixel]: image::EncodableLayout,
+        C: std::ops::Deref<Target = [P::Subpixel]>,
+    {
+        let mut data = Cursor::new(vec![]);
+        image.write_to(&mut data, image::ImageFormat::Png).unwrap();
+        data.into_inner()
+    }
+
+    #[test]
+    fn test_bilevel_images() {
+        let bilevel = GrayImage::from_fn(10, 4, |x, y| Luma([255 * ((x + y) % 2) as u8]));
+        let gray = GrayImage::from_fn(10, 4, |x, _| Luma([20 * x as u8]));
+        let pdf = TestWorld::new(
+            "#image(\"/bilevel.png\", width: 10pt)\n\
+             #image(\"/gray.png\", width: 10pt)",
+        )
+        .with_file("/bilevel.png", png(&bilevel))
+        .with_file("/gray.png", png(&gray))
+        .export(&PdfOptions::default());
+
+        let images = pdf.subtype("Image");
+        assert_eq!(images.len(), 2);
+
+        // Black-and-white pixels take one bit each, with every row of ten
+        // pixels padded to two bytes. Other gray levels keep eight bits.
+        let (one, eight): (Vec<_>, Vec<_>) = images
+            .into_iter()
+            .partition(|image| image.dict.int("BitsPerComponent") == Some(1));
+        assert_eq!(one.len(), 1);
+        assert_eq!(one[0].data.len(), 2 * 4);
+        assert_eq!(one[0].data[..2], [0b0101_0101, 0b0100_0000]);
+        assert_eq!(eight[0].dict.int("BitsPerComponent"), Some(8));
+        assert_eq!(eight[0].data.len(), 10 * 4);
+    }
+
+    #[test]
+    fn test_filled_bilevel_images() {
+        let bilevel = GrayImage::from_fn(10, 4, |x, y| Luma([255 * ((x + y) % 2) as u8]));
+        let world = TestWorld::new(
+            "#set page(width: auto, height: auto, margin: 5pt, fill: yellow)\n\
+             #image(\"/bilevel.png\", width: 100pt, fill: red, scaling: \"pixelated\")\n\
+             #image(\"/bilevel.png\", width: 100pt, scaling: \"pixelated\")",
+        )
+        .with_file("/bilevel.png", png(&bilevel));
+        let pdf = world.export(&PdfOptions::default());
+
+        // The filled image becomes a stencil mask with one bit per pixel,
+        // while the other one stays a gray image.
+        let images = pdf.subtype("Image");
+        assert_eq!(images.len(), 2);
+        let (masks, grays): (Vec<_>, Vec<_>) =
+            images.into_iter().partition(|image| image.dict.is_true("ImageMask"));
+        assert_eq!(masks.len(), 1);
+        assert_eq!(masks[0].dict.int("BitsPerComponent"), Some(1));
+        assert!(!masks[0].dict.contains("ColorSpace"));
+        assert_eq!(masks[0].data.len(), 2 * 4);
+        assert_eq!(grays[0].dict.int("BitsPerComponent"), Some(1));
+        assert!(grays[0].dict.contains("ColorSpace"));
+
+        // The black pixels are painted red and the white ones show the page.
+        assert_renders_like(&world, &PdfOptions::default());
+
+        // Downsampling keeps the mask black and white.
+        let options = PdfOptions { max_image_dpi: Some(3.6), ..Default::default() };
+        let pdf = world.export(&options);
+        let mask = pdf
+            .subtype("Image")
+            .into_iter()
+            .find(|image| image.dict.is_true("ImageMask"));
+        assert_eq!(mask.unwrap().dict.int("Width"), Some(5));
+        assert_eq!(mask.unwrap().dict.int("BitsPerComponent"), Some(1));
+    }
+
+    #[test]
+    fn test_image_interpolation() {
+        let export = |scaling: &str| {
+            TestWorld::new(&format!("#image(\"/translucent.png\", scaling: {scaling})"))
+                .with_file("/translucent.png", translucent_png())
+                .export(&PdfOptions::default())
+        };
+
+        // Smooth scaling applies to both the image and its soft mask.
+        let interpolated = |pdf: TestPdf| {
+            pdf.subtype("Image")
+                .iter()
+                .filter(|image| image.dict.is_true("Interpolate"))
+                .count()
+        };
+        assert_eq!(interpolated(export("\"smooth\"")), 2);
+        assert_eq!(export("\"pixelated\"").count("Interpolate"), 0);
+        assert_eq!(export("auto").count("Interpolate"), 0);
+    }
+
+    #[test]
+    fn test_soft_shadow() {
+        // A dark shadow that fades out towards the edges. Its invisible
+        // pixels store green, which must not show up anywhere.
+        let shadow = RgbaImage::from_fn(64, 64, |x, y| {
+            let distance = (x as f32 - 31.5).hypot(y as f32 - 31.5);
+            match (255.0 - distance * 10.0).max(0.0) as u8 {
+                0 => Rgba([0, 255, 0, 0]),
+                alpha => Rgba([40, 40, 40, alpha]),
+            }
+        });
+        let world = TestWorld::new(
+            "#set page(width: auto, height: auto, margin: 0pt)\n\
+             #rect(width: 64pt, height: 64pt, inset: 0pt, fill: red)[\n\
+               #image(\"/shadow.png\", width: 64pt, scaling: \"smooth\")\n\
+             ]",
+        )
+        .with_file("/shadow.png", png(&shadow));
+        let pdf = world.export(&PdfOptions::default());
+
+        // The color is written as it is, without being premultiplied, and
+        // the soft mask holds the alpha channel with eight bits.
+        let images = pdf.subtype("Image");
+        assert_eq!(images.len(), 2);
+        let (masks, colors): (Vec<_>, Vec<_>) = images
+            .into_iter()
+            .partition(|image| image.dict.name("ColorSpace") == Some("DeviceGray"));
+        let (mask, color) = (masks[0], colors[0]);
+        assert!(pdf.get(color.dict.get("SMask").unwrap()).as_stream() == Some(mask));
+        assert_eq!(mask.dict.int("BitsPerComponent"), Some(8));
+        let alpha: Vec<u8> = shadow.pixels().map(|Rgba([.., a])| *a).collect();
+        assert_eq!(mask.data, alpha);
+
+        // Invisible pixels take the color of visible ones.
+        assert_eq!(color.data.len(), 64 * 64 * 3);
+        assert!(color.data.chunks(3).all(|rgb| rgb == [40, 40, 40]));
+
+        // The shadow is blended with straight alpha, so it darkens the red
+        // rectangle without dark fringes and leaves its corners untouched.
+        let pixmap = pdf.render(0, 1.0);
+        for (x, y) in [(32, 32), (40, 32), (32, 50), (50, 50), (2, 2)] {
+            let [r, g, b, _] = pixel(&pixmap, x, y);
+            let Rgba([.., a]) = shadow.get_pixel(x, y);
+            let a = f32::from(*a) / 255.0;
+            let expected = [255.0, 65.0, 54.0].map(|v: f32| v * (1.0 - a) + 40.0 * a);
+            for (shown, expected) in [r, g, b].into_iter().zip(expected) {
+                assert!(
+                    (f32::from(shown) - expected).abs() <= 3.0,
+                    "({x}, {y}): {shown} {expected}"
+                );
+            }
+        }
+        assert_renders_like(&world, &PdfOptions::default());
+    }
+
+    /// The gray levels of the image's three by two blocks.
+    const LEVELS: [u8; 6] = [0, 50, 100, 150, 200, 250];
+
+    /// The size of a block. It is a multiple of the JPEG block size, so that
+    /// the blocks survive compression.
+    const BLOCK: u32 = 16;
+
+    /// A grayscale JPEG whose blocks each have their own level, stored with
+    /// the given EXIF orientation.
+    fn oriented_jpeg(orientation: u8) -> Vec<u8> {
+        let image = GrayImage::from_fn(3 * BLOCK, 2 * BLOCK, |x, y| {
+            Luma([LEVELS[(y / BLOCK * 3 + x / BLOCK) as usize]])
+        });
+
+        let mut jpeg = vec![];
+        JpegEncoder::new_with_quality(&mut jpeg, 100)
+            .encode_image(&image)
+            .unwrap();
+
+        // An APP1 segment with a big-endian TIFF structure whose only entry
+        // is the orientation.
+        let mut exif = vec![0xFF, 0xE1, 0, 34];
+        exif.extend(b"Exif\0\0MM\0\x2A\0\0\0\x08");
+        exif.extend([0, 1, 0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, orientation, 0, 0]);
+        exif.extend([0, 0, 0, 0]);
+        jpeg.splice(2..2, exif);
+        jpeg
+    }
+
+    #[test]
+    fn test_sixteen_bit_images() {
+        // Smooth ramps that need more than 256 levels.
+        let gray = ImageBuffer::from_fn(300, 2, |x, _| Luma([x as u16 * 200]));
+        let rgba = ImageBuffer::from_fn(300, 2, |x, _| {
+            Rgba([x as u16 * 200, 0, 1000, 65535 - x as u16])
+        });
+        let world = TestWorld::new(
+            "#image(\"/gray.png\", width: 30pt)\n\
+             #image(\"/rgba.png\", width: 30pt)\n\
+             #image(\"/translucent.png\", width: 30pt)",
+        )
+        .with_file("/gray.png", png(&gray))
+        .with_file("/rgba.png", png(&rgba))
+        .with_file("/translucent.png", translucent_png());
+        let pdf = world.export(&PdfOptions::default());
+
+        let images = pdf.subtype("Image");
+        let depths: Vec<_> = images
+            .iter()
+            .map(|image| (image.dict.int("Width"), image.dict.int("BitsPerComponent")))
+            .collect();
+        assert_eq!(
+            depths,
+            [
+                (Some(300), Some(16)),
+                (Some(300), Some(16)),
+                (Some(300), Some(16)),
+                (Some(100), Some(8)),
+                (Some(100), Some(8)),
+            ]
+        );
+
+        // The samples are kept exactly, big-endian, and so is the alpha
+        // channel in the soft mask.
+        let big_endian = |samples: &[u16]| -> Vec<u8> {
+            samples.iter().flat_map(|sample| sample.to_be_bytes()).collect()
+        };
+        let color: Vec<u16> =
+            rgba.pixels().flat_map(|Rgba([r, g, b, _])| [*r, *g, *b]).collect();
+        let alpha: Vec<u16> = rgba.pixels().map(|Rgba([.., a])| *a).collect();
+        let data: Vec<&[u8]> = images.iter().map(|image| image.data.as_slice()).collect();
+        assert!(data.contains(&big_endian(gray.as_raw()).as_slice()));
+        assert!(data.contains(&big_endian(&color).as_slice()));
+        assert!(data.contains(&big_endian(&alpha).as_slice()));
+    }
+
+    #[test]
+    fn test_cmyk_jpegs() {
+        for adobe in [false, true] {
+            let data = cmyk_jpeg(adobe);
+            let world = TestWorld::new(
+                "#set page(width: auto, height: auto, margin: 0pt)\n\
+                 #image(\"/print.jpg\", width: 64pt)",
+            )
+            .with_file("/print.jpg", data.clone());
+            let pdf = world.export(&PdfOptions::default());
+
+            // The data stays in CMYK, only inverted if it was written by
+            // Adobe applications.
+            let image = pdf.subtype("Image")[0];
+            assert_eq!(image.raw, data);
+            assert_eq!(image.dict.name("ColorSpace"), Some("DeviceCMYK"));
+            assert_eq!(image.dict.contains("Decode"), adobe);
+
+            // Either way, each block shows its ink.
+            let pixmap = pdf.render(0, 1.0);
+            for (i, ink) in INKS.iter().enumerate() {
+                let [c, m, y, k] = ink.map(|v| 1.0 - f32::from(v) / 255.0);
+                let expected = [c * k, m * k, y * k].map(|v| (v * 255.0).round() as u8);
+                let x = i as u32 * BLOCK + BLOCK / 2;
+                let [r, g, b, _] = pixel(&pixmap, x, BLOCK / 2);
+                for (shown, expected) in [r, g, b].into_iter().zip(expected) {
+                    assert!(
+                        shown.abs_diff(expected) <= 3,
+                        "{adobe} {i}: {shown} {expected}"
+                    );
+                }
+            }
+
+            // The image decoder of typst-render always takes data to be
+            // inverted, so it only agrees for Adobe's files.
+            if adobe {
+                assert_renders_like(&world, &PdfOptions::default());
+            }
+        }
+    }
+
+    /// The ink of each block in a CMYK JPEG.
+    const INKS: [[u8; 4]; 4] =
+        [[255, 0, 0, 0], [0, 200, 0, 0], [0, 0, 255, 50], [0, 0, 0, 128]];
+
+    /// A CMYK JPEG whose blocks each have their own ink, optionally with
+    /// inverted values and an Adobe APP14 segment, as Adobe applications
+    /// write them.
+    ///
+    /// The image crate can't encode CMYK, so each component is encoded as a
+    /// grayscale JPEG and its data goes into a scan of its own. The grayscale
+    /// JPEGs all use the same standard tables.
+    fn cmyk_jpeg(adobe: bool) -> Vec<u8> {
+        let scans = (0..4).map(|c| {
+            let plane = GrayImage::from_fn(INKS.len() as u32 * BLOCK, BLOCK, |x, _| {
+                let ink = INKS[(x / BLOCK) as usize][c];
+                Luma([if adobe { 255 - ink } else { ink }])
+            });
+            let mut jpeg = vec![];
+            JpegEncoder::new_with_quality(&mut jpeg, 100)
+                .encode_image(&plane)
+                .unwrap();
+            jpeg
+        });
+
+        // Take the tables from the first component and the scan data from
+        // all of them.
+        let mut tables = vec![];
+        let mut data = vec![];
+        for (i, jpeg) in scans.enumerate() {
+            let mut at = 2;
+            loop {
+                let marker = jpeg[at + 1];
+                let len = usize::from(u16::from_be_bytes([jpeg[at + 2], jpeg[at + 3]]));
+                let segment = &jpeg[at..at + 2 + len];
+                if marker == 0xDA {
+                    data.push(jpeg[at + 2 + len..jpeg.len() - 2].to_vec());
+                    break;
+                } else if i == 0 && matches!(marker, 0xDB | 0xC4) {
+                    tables.extend_from_slice(segment);
+                }
+                at += 2 + len;
+            }
+        }
+
+        let (width, height) = (INKS.len() as u16 * BLOCK as u16, BLOCK as u16);
+        let mut jpeg = vec![0xFF, 0xD8];
+        if adobe {
+            jpeg.extend([0xFF, 0xEE, 0, 14]);
+            jpeg.extend(b"Adobe\0\x64\0\0\0\0\0");
+        }
+        jpeg.extend(tables);
+        jpeg.extend([0xFF, 0xC0, 0, 20, 8]);
+        jpeg.extend(height.to_be_bytes());
+        jpeg.extend(width.to_be_bytes());
+        jpeg.push(4);
+        for id in 1..=4 {
+            jpeg.extend([id, 0x11, 0]);
+        }
+        for (id, data) in (1..=4).zip(data) {
+            jpeg.extend([0xFF, 0xDA, 0, 8, 1, id, 0, 0, 63, 0]);
+            jpeg.extend(data);
+        }
+        jpeg.extend([0xFF, 0xD9]);
+        jpeg
+    }
+
+    #[test]
+    fn test_jpeg_pass_through() {
+        let gray = GrayImage::from_fn(30, 20, |x, y| Luma([(x * 8 + y) as u8]));
+        let rgb = RgbImage::from_fn(30, 20, |x, y| Rgb([x as u8 * 8, y as u8 * 12, 90]));
+        for (data, components) in [(jpeg(&gray.into()), 1), (jpeg(&rgb.into()), 3)] {
+            let world = TestWorld::new("#image(\"/photo.jpg\", width: 30pt)")
+                .with_file("/photo.jpg", data.clone());
+            let pdf = world.export(&PdfOptions::default());
+
+            // The stream holds the file's bytes, exactly, in a color space
+            // with as many components.
+            let image = pdf.subtype("Image")[0];
+            assert_eq!(image.dict.name("Filter"), Some("DCTDecode"));
+            assert_eq!(image.raw, data);
+            let space =
+                pdf.get(image.dict.get("ColorSpace").unwrap()).as_array().unwrap();
+            let profile = pdf.get(&space[1]).as_stream().unwrap();
+            assert_eq!(profile.dict.int("N"), Some(components));
+
+            // The whole file stays the same, byte for byte, between exports.
+            assert_eq!(pdf.bytes, world.export(&PdfOptions::default()).bytes);
+
+            // Downsampled photos are re-encoded.
+            let options = PdfOptions { max_image_dpi: Some(36.0), ..Default::default() };
+            let pdf = world.export(&options);
+            let image = pdf.subtype("Image")[0];
+            assert_eq!(image.dict.name("Filter"), Some("DCTDecode"));
+            assert_ne!(image.raw, data);
+        }
+    }
+
+    /// Encode an image as JPEG.
+    fn jpeg(image: &DynamicImage) -> Vec<u8> {
+        let mut data = vec![];
+        JpegEncoder::new_with_quality(&mut data, 90)
+            .encode(image.as_bytes(), image.width(), image.height(), image.color())
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn test_rendering_intents() {
+        // A copy of sRGB without its profile ID, to tell it apart from the
+        // one that untagged images get.
+        let mut profile = typst_assets::icc::S_RGB_V4.to_vec();
+        profile[84..100].fill(0);
+        let mut tagged = jpeg(&RgbImage::from_pixel(8, 8, Rgb([200, 30, 60])).into());
+        let mut app2 = vec![0xFF, 0xE2];
+        app2.extend((2 + 14 + profile.len() as u16).to_be_bytes());
+        app2.extend(b"ICC_PROFILE\0\x01\x01");
+        app2.extend(&profile);
+        tagged.splice(2..2, app2);
+
+        let gray = GrayImage::from_fn(4, 4, |x, _| Luma([40 * x as u8]));
+        let world = TestWorld::new(
+            "#image(\"/gray.png\", width: 10pt)\n\
+             #image(\"/translucent.png\", width: 10pt)\n\
+             #image(\"/tagged.jpg\", width: 10pt)\n\
+             #image(\"/print.jpg\", width: 10pt)",
+        )
+        .with_file("/gray.png", png(&gray))
+        .with_file("/translucent.png", translucent_png())
+        .with_file("/tagged.jpg", tagged)
+        .with_file("/print.jpg", cmyk_jpeg(true));
+        assert_eq!(world.export(&PdfOptions::default()).count("Intent"), 0);
+
+        for (intent, name) in [
+            (RenderingIntent::Perceptual, "Perceptual"),
+            (RenderingIntent::RelativeColorimetric, "RelativeColorimetric"),
+            (RenderingIntent::Saturation, "Saturation"),
+            (RenderingIntent::AbsoluteColorimetric, "AbsoluteColorimetric"),
+        ] {
+            let options = PdfOptions {
+                image_rendering_intent: Some(intent),
+                ..Default::default()
+            };
+            let pdf = world.export(&options);
+
+            // Each gray, RGB, ICC-based and CMYK image has the intent in its
+            // dictionary, while the soft mask, which only holds alpha values,
+            // doesn't need one.
+            let (masks, colors): (Vec<_>, Vec<_>) = pdf
+                .subtype("Image")
+                .into_iter()
+                .partition(|image| image.dict.name("ColorSpace") == Some("DeviceGray"));
+            assert_eq!(colors.len(), 4);
+            assert!(colors.iter().all(|image| image.dict.name("Intent") == Some(name)));
+            assert_eq!(masks.len(), 1);
+            assert!(!masks[0].dict.contains("Intent"));
+
+            // The embedded profile is still used.
+            assert!(pdf.streams().iter().any(|stream| stream.data == profile));
+            assert!(colors
+                .iter()
+                .any(|image| image.dict.name("ColorSpace") == Some("DeviceCMYK")));
+        }
+    }
+
+    #[test]
+    fn test_embedded_rotation() {
+        for orientation in 1..=8 {
+            let data = oriented_jpeg(orientation);
+            let decoded =
+                RasterImage::new(Bytes::from(data.clone()), RasterFormat::Jpg).unwrap();
+            let displayed = decoded.dynamic().to_luma8();
+
+            let pdf = TestWorld::new(
+                "#set page(width: auto, height: auto, margin: 0pt)\n\
+                 #image(\"/test.jpg\", width: 48pt)",
+            )
+            .with_file("/test.jpg", data)
+            .export(&PdfOptions::default());
+
+            // The data is embedded as is, in its stored orientation.
+            let image = pdf.subtype("Image")[0];
+            assert_eq!(image.dict.name("Filter"), Some("DCTDecode"));
+            assert_eq!(image.dict.int("Width"), Some(48), "{orientation}");
+            assert_eq!(image.dict.int("Height"), Some(32), "{orientation}");
+
+            // The transformation right before the image is drawn.
+            let ops = pdf.page_ops(0);
+            let at = ops.iter().position(|op| op.operator == "Do").unwrap();
+            assert_eq!(ops[at - 1].operator, "cm");
+            let m = ops[at - 1].numbers();
+            let map =
+                |u: f64, v: f64| (m[0] * u + m[2] * v + m[4], m[1] * u + m[3] * v + m[5]);
+
+            // The rectangle the image covers on the page.
+            let corners = [map(0.0, 0.0), map(1.0, 0.0), map(0.0, 1.0), map(1.0, 1.0)];
+            let min_x = corners.iter().map(|c| c.0).fold(f64::INFINITY, f64::min);
+            let max_x = corners.iter().map(|c| c.0).fold(f64::NEG_INFINITY, f64::max);
+            let min_y = corners.iter().map(|c| c.1).fold(f64::INFINITY, f64::min);
+            let max_y = corners.iter().map(|c| c.1).fold(f64::NEG_INFINITY, f64::max);
+
+            // Each stored block must end up where the decoded image, which
+            // already has the orientation applied, shows it. The first row of
+            // image data is at the top of the unit square.
+            for (i, &level) in LEVELS.iter().enumerate() {
+                let u = ((i % 3) as f64 + 0.5) / 3.0;
+                let v = 1.0 - ((i / 3) as f64 + 0.5) / 2.0;
+                let (x, y) = map(u, v);
+                let px = (x - min_x) / (max_x - min_x) * f64::from(displayed.width());
+                let py = (y - min_y) / (max_y - min_y) * f64::from(displayed.height());
+                let shown = displayed.get_pixel(px as u32, py as u32).0[0];
+                assert!(shown.abs_diff(level) < 20, "{orientation}: block {i}");
+            }
+        }
+    }
+
+    /// A 100 by 50 SVG with a blurred rectangle, which svg2pdf rasterizes,
+    /// next to a plain one, which stays vector.
+    const BLURRED_SVG: &str = r#"
+      <svg xmlns="http://www.w3.org/2000/svg" width="100" height="50">
+        <filter id="blur"><feGaussianBlur stdDeviation="2"/></filter>
+        <rect x="10" y="10" width="30" height="30" fill="red" filter="url(#blur)"/>
+        <rect x="60" y="10" width="30" height="30" fill="blue"/>
+      </svg>
+    "#;
+
+    /// Export a document showing the blurred SVG and return the pixel size
+    /// of the image it is rasterized into.
+    fn rasterized_size(source: &str, options: &PdfOptions) -> (i64, i64) {
+        let pdf = TestWorld::new(source)
+            .with_file("/blurred.svg", BLURRED_SVG.as_bytes())
+            .export(options);
+
+        // Only the blurred rectangle is rasterized, into an image and its
+        // soft mask of the same size. The plain one is filled as a path.
+        let images = pdf.subtype("Image");
+        assert_eq!(images.len(), 2);
+        let sizes: Vec<_> = images
+            .iter()
+            .map(|image| {
+                (image.dict.int("Width").unwrap(), image.dict.int("Height").unwrap())
+            })
+            .collect();
+        assert_eq!(sizes[0], sizes[1]);
+        assert_eq!(pdf.ops_named("f").len(), 1);
+        sizes[0]
+    }
+
+    #[test]
+    fn test_svg_raster_scale() {
+        let source = "#image(\"/blurred.svg\", width: 100pt)";
+        let scaled = |scale| {
+            let options = PdfOptions { svg_raster_scale: scale, ..Default::default() };
+            rasterized_size(source, &options)
+        };
+
+        // The blurred rectangle with the filter's margins of a tenth on each
+        // side, at the default scale of one, then at twice and half of it.
+        assert_eq!(scaled(None), (36, 36));
+        assert_eq!(scaled(Some(1.0)), (36, 36));
+        assert_eq!(scaled(Some(2.0)), (72, 72));
+        assert_eq!(scaled(Some(0.5)), (18, 18));
+    }
+
+    #[test]
+    fn test_svg_raster_dpi() {
+        let dpi = |source, dpi| {
+            let options = PdfOptions {
+                svg_raster_dpi: Some(dpi),
+                svg_raster_scale: Some(0.5),
+                ..Default::default()
+            };
+            rasterized_size(source, &options)
+        };
+
+        // The blurred region is half an inch wide, so it gets half as many
+        // pixels as the DPI, regardless of the raster scale.
+        let source = "#image(\"/blurred.svg\", width: 100pt)";
+        assert_eq!(dpi(source, 96.0), (48, 48));
+        assert_eq!(dpi(source, 300.0), (150, 150));
+
+        // Scaling the image up makes its region larger, so it needs more
+        // pixels for the same resolution.
+        let source = "#scale(200%, reflow: true, image(\"/blurred.svg\", width: 100pt))";
+        assert_eq!(dpi(source, 96.0), (96, 96));
+        assert_eq!(dpi(source, 300.0), (300, 300));
+    }
+}
@@ -1,6 +1,7 @@
 //! Exporting of Typst documents into PDFs.
 
 mod color;
+mod embed;
 mod extg;
 mod font;
 mod gradient;
@@ -8,33 +9,39 @@ mod image;
 mod outline;
 mod page;
 mod pattern;
+#[cfg(test)]
+mod tests;
 
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::hash::Hash;
 use std::sync::Arc;
+use std::time::Duration;
 
 use base64::Engine;
-use ecow::{eco_format, EcoString};
+use ecow::{eco_format, EcoString, EcoVec};
 use indexmap::IndexMap;
-use pdf_writer::types::Direction;
+use pdf_writer::types::{AnnotationFlags, Direction};
 use pdf_writer::writers::Destination;
 use pdf_writer::{Finish, Name, Pdf, Rect, Ref, Str, TextStr};
-use typst::foundations::{Datetime, Label, NativeElement, Smart};
+use typst::diag::{bail, SourceDiagnostic, SourceResult};
+use typst::eval::Tracer;
+use typst::foundations::{Bytes, Datetime, Label, NativeElement, Smart};
 use typst::introspection::Location;
-use typst::layout::{Abs, Dir, Em, Frame, PageRanges, Transform};
+use typst::layout::{Abs, Dir, Em, Frame, PageRanges, Point, Size, Transform};
 use typst::model::{Document, HeadingElem};
+use typst::syntax::Span;
 use typst::text::color::frame_for_glyph;
-use typst::text::{Font, Lang};
+use typst::text::{Font, Lang, Region};
 use typst::utils::Deferred;
-use typst::visualize::Image;
+use typst::visualize::{Color, Image, Pattern};
 use xmp_writer::{DateTime, LangId, RenditionClass, Timezone, XmpWriter};
 
 use crate::color::ColorSpaces;
 use crate::extg::ExtGState;
 use crate::gradient::PdfGradient;
-use crate::image::EncodedImage;
-use crate::page::EncodedPage;
-use crate::pattern::PdfPattern;
+use crate::image::ImageSettings;
+use crate::page::{EncodedForm, EncodedPage, ReusedFrame};
+use crate::pattern::{PdfPattern, PdfPatternTile};
 
 /// Export a document into a PDF file.
 ///
@@ -58,38 +65,600 @@ use crate::pattern::PdfPattern;
 ///
 /// The `page_ranges` option specifies which ranges of pages should be exported
 /// in the PDF. When `None`, all pages should be exported.
-#[typst_macros::time(name = "pdf")]
+///
+/// All other settings keep their defaults. Use [`pdf_with_options`] to change
+/// them and to receive the warnings that arise during export.
 pub fn pdf(
     document: &Document,
     ident: Smart<&str>,
     timestamp: Option<Datetime>,
     page_ranges: Option<PageRanges>,
 ) -> Vec<u8> {
-    let mut ctx = PdfContext::new(document, page_ranges);
+    let options = PdfOptions {
+        ident,
+        timestamp,
+        page_ranges,
+        ..Default::default()
+    };
+    pdf_with_options(document, &options, &mut Tracer::new())
+        .expect("export with default options cannot fail")
+}
+
+/// Export a document into a PDF file with the given settings.
+///
+/// Returns the raw bytes making up the PDF file. Warnings that arise during
+/// export (for instance about glyphs missing from their font) are reported to
+/// the `tracer`. Fails if the options are invalid or if an option turns a
+/// problem into an error, like [`PdfOptions::missing_glyphs_are_errors`].
+pub fn pdf_with_options(
+    document: &Document,
+    options: &PdfOptions,
+    tracer: &mut Tracer,
+) -> SourceResult<Vec<u8>> {
+    export(document, options, tracer, false).map(|(buffer, _)| buffer)
+}
+
+/// Export a document into a PDF file like [`pdf_with_options`], and also
+/// return statistics about the export.
+pub fn pdf_with_stats(
+    document: &Document,
+    options: &PdfOptions,
+    tracer: &mut Tracer,
+) -> SourceResult<(Vec<u8>, PdfStats)> {
+    export(document, options, tracer, true)
+        .map(|(buffer, stats)| (buffer, stats.unwrap_or_default()))
+}
+
+/// Export a document, collecting statistics if requested.
+#[typst_macros::time(name = "pdf")]
+fn export(
+    document: &Document,
+    options: &PdfOptions,
+    tracer: &mut Tracer,
+    collect_stats: bool,
+) -> SourceResult<(Vec<u8>, Option<PdfStats>)> {
+    check_options(options)?;
+    let mut ctx = PdfContext::new(document, options);
+    if collect_stats {
+        ctx.stats = Some(PdfStats::default());
+    }
+    let (major, minor) = options.version.numbers();
+    ctx.pdf.set_version(major, minor);
     page::construct_pages(&mut ctx, &document.pages);
     font::write_fonts(&mut ctx);
     image::write_images(&mut ctx);
     gradient::write_gradients(&mut ctx);
     extg::write_external_graphics_states(&mut ctx);
+    page::write_forms(&mut ctx);
     pattern::write_patterns(&mut ctx);
     write_named_destinations(&mut ctx);
     page::write_page_tree(&mut ctx);
     page::write_global_resources(&mut ctx);
-    write_catalog(&mut ctx, ident, timestamp);
-    ctx.pdf.finish()
+    write_catalog(&mut ctx);
+    ctx.finish(tracer)
+}
+
+/// Ensure that the numeric export options are in range.
+fn check_options(options: &PdfOptions) -> SourceResult<()> {
+    let positive = |value: Option<f64>| value.map_or(true, |v| v.is_finite() && v > 0.0);
+    if !positive(options.max_image_dpi) {
+        bail!(Span::detached(), "maximum image resolution must be a positive number");
+    }
+
+    if !positive(options.svg_raster_scale) {
+        bail!(Span::detached(), "SVG raster scale must be a positive number");
+    }
+
+    if !positive(options.svg_raster_dpi) {
+        bail!(Span::detached(), "SVG raster resolution must be a positive number");
+    }
+
+    let non_negative = |seconds: f32| seconds.is_finite() && seconds >= 0.0;
+    for (_, transition) in &options.page_transitions {
+        if !non_negative(transition.duration) {
+            bail!(
+                Span::detached(),
+                "page transition duration must be a non-negative number"
+            );
+        }
+
+        if !transition.advance.map_or(true, non_negative) {
+            bail!(Span::detached(), "page advance time must be a non-negative number");
+        }
+    }
+
+    if options
+        .coordinate_precision
+        .is_some_and(|places| places > PdfOptions::MAX_COORDINATE_PRECISION)
+    {
+        bail!(
+            Span::detached(),
+            "coordinate precision must be at most {} decimal places",
+            PdfOptions::MAX_COORDINATE_PRECISION,
+        );
+    }
+
+    if options
+        .working_rgb_profile
+        .as_deref()
+        .is_some_and(|profile| !color::is_rgb_profile(profile))
+    {
+        bail!(Span::detached(), "the working RGB profile is not an RGB ICC profile");
+    }
+
+    if let Some(limit) = options.embedded_files_limit {
+        let size: usize = options.embedded_files.iter().map(|file| file.data.len()).sum();
+        if size > limit {
+            bail!(
+                Span::detached(),
+                "embedded files are too large ({size} bytes, limit is {limit} bytes)"
+            );
+        }
+    }
+
+    if let Some(thumbnails) = options.thumbnails {
+        if !(1..=PdfThumbnails::MAX_SIZE).contains(&thumbnails.max_size) {
+            bail!(
+                Span::detached(),
+                "thumbnail size must be between 1 and {} pixels",
+                PdfThumbnails::MAX_SIZE,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Settings for PDF export.
+#[derive(Default)]
+pub struct PdfOptions<'a> {
+    /// If given, shall be a string that uniquely and stably identifies the
+    /// document. It should not change between compilations of the same
+    /// document.  **If you cannot provide such a stable identifier, just pass
+    /// `Smart::Auto` rather than trying to come up with one.** The CLI, for
+    /// example, does not have a well-defined notion of a long-lived project
+    /// and as such just passes `Smart::Auto`.
+    ///
+    /// If an `ident` is given, the hash of it will be used to create a PDF
+    /// document identifier (the identifier itself is not leaked). If `ident`
+    /// is `Auto`, a hash of the document's title and author is used instead
+    /// (which is reasonably unique and stable).
+    pub ident: Smart<&'a str>,
+    /// If given, is expected to be the creation date of the document as a UTC
+    /// datetime. It will only be used if `set document(date: ..)` is `auto`.
+    pub timestamp: Option<Datetime>,
+    /// Specifies which ranges of pages should be exported in the PDF. When
+    /// `None`, all pages should be exported.
+    pub page_ranges: Option<PageRanges>,
+    /// The version of the PDF specification that the output conforms to.
+    pub version: PdfVersion,
+    /// Whether text that uses a font's `.notdef` glyph (because the font has
+    /// no glyph for a character) should fail the export instead of only
+    /// producing a warning.
+    pub missing_glyphs_are_errors: bool,
+    /// Whether black text and strokes overprint the content below them
+    /// instead of knocking it out, so that registration errors in print
+    /// don't show white halos. Only CMYK black overprints: black in other
+    /// color spaces, like the default text color, is painted as usual with
+    /// a warning, as overprinting has no effect on screen output.
+    pub overprint_black: bool,
+    /// Additional CMYK colors whose fills and strokes overprint, e.g. colors
+    /// that stand in for spot inks. Only CMYK colors overprint; overprinting
+    /// other colors is ignored with a warning.
+    pub overprint_colors: Vec<Color>,
+    /// Whether the outline gets two additional, collapsed sections "Figures"
+    /// and "Tables" that list every captioned image and table figure by its
+    /// numbered caption, e.g. "Figure 1: Results". The sections are titled in
+    /// the document's main language and region. The figures are numbered per
+    /// kind, so manual updates of their counters are not reflected.
+    pub figure_outline: bool,
+    /// Whether hyphens inserted by hyphenation are extracted from the PDF as
+    /// soft hyphens (U+00AD). Otherwise, they are left out of the extracted
+    /// text. Hyphens typed by the user are extracted either way.
+    pub soft_hyphens: bool,
+    /// Whether to draw all text as vector paths instead of embedding fonts.
+    ///
+    /// The text remains searchable and copyable, but the file does not
+    /// contain any font programs, as some print workflows demand.
+    pub text_as_paths: bool,
+    /// If given, an ICC profile that replaces sRGB as the document's RGB
+    /// working space, e.g. Display P3 for screen-only output. RGB colors and
+    /// images without an embedded profile are then interpreted in this color
+    /// space. Like sRGB, the profile is embedded once and shared by all
+    /// content. Export fails if it is not an RGB profile.
+    pub working_rgb_profile: Option<Bytes>,
+    /// If given, raster images whose resolution at their placed size exceeds
+    /// this many pixels per inch are downsampled before embedding. An image
+    /// that is placed several times is embedded once, with the resolution
+    /// its largest placement needs. Must be a positive, finite number.
+    pub max_image_dpi: Option<f64>,
+    /// The rendering intent to write for raster images. When `None`, the
+    /// viewer's default (relative colorimetric) applies.
+    pub image_rendering_intent: Option<RenderingIntent>,
+    /// The scale, relative to an SVG image's own size, at which parts of it
+    /// that can't be represented as vector graphics (like filters) are
+    /// rasterized. When `None`, a default scale is used. Must be a positive,
+    /// finite number.
+    ///
+    /// Text in SVG images is always converted to paths, independently of
+    /// [`text_as_paths`](Self::text_as_paths).
+    pub svg_raster_scale: Option<f64>,
+    /// If given, parts of SVG images that can't be represented as vector
+    /// graphics are rasterized at this many pixels per inch at the size the
+    /// image is placed at. Takes precedence over
+    /// [`svg_raster_scale`](Self::svg_raster_scale). Must be a positive,
+    /// finite number.
+    pub svg_raster_dpi: Option<f64>,
+    /// If given, each page carries a pre-rendered thumbnail image that some
+    /// viewers show in their page navigation.
+    pub thumbnails: Option<PdfThumbnails>,
+    /// Whether shapes stroked with zero thickness are drawn as hairlines, the
+    /// thinnest lines the output device can draw. Otherwise, such strokes are
+    /// skipped.
+    pub zero_thickness_hairlines: bool,
+    /// If given, the number of decimal places to which coordinates in content
+    /// streams are rounded, at most
+    /// [`MAX_COORDINATE_PRECISION`](Self::MAX_COORDINATE_PRECISION). Four
+    /// places are far below the resolution of any output device. Otherwise,
+    /// coordinates are written at full precision.
+    ///
+    /// Each number is rounded where it is written, relative to the transforms
+    /// in effect there. Transforms are themselves rounded, though to two more
+    /// places, so in nested or scaled content the error on the page can be
+    /// larger than the last kept place.
+    pub coordinate_precision: Option<u8>,
+    /// Whether the content of each page is clipped to the page's bounds.
+    /// Links and file attachments that lie fully outside of their page are
+    /// then dropped.
+    pub clip_to_page: bool,
+    /// Transition effects that viewers show in presentation mode when moving
+    /// to a page. Each page uses the first entry whose ranges contain it.
+    pub page_transitions: Vec<(PageRanges, PdfTransition)>,
+    /// How viewers treat the PDF's link annotations.
+    pub link_visibility: LinkVisibility,
+    /// Files that are embedded into the PDF and pinned to a spot on a page.
+    ///
+    /// The exporter has no PDF/A conformance modes, so attachments are not
+    /// checked against a standard. PDF/A-2 forbids them while PDF/A-3 allows
+    /// them; leave this empty when the PDF has to conform to PDF/A-2.
+    pub file_attachments: Vec<PdfFileAttachment>,
+    /// The URL under which the document is published. If given, links to
+    /// this URL with a fragment jump to the heading labelled with the
+    /// fragment instead of leaving the document, like links to a bare
+    /// fragment such as `#intro` always do. Other URLs stay external.
+    pub document_url: Option<&'a str>,
+    /// Files that are embedded into the PDF as a whole, e.g. the sources the
+    /// document was compiled from. Viewers list them in their attachment
+    /// panel. Nothing is embedded by default.
+    pub embedded_files: Vec<PdfEmbeddedFile>,
+    /// If given, the maximum total size in bytes of the
+    /// [`embedded_files`](Self::embedded_files). Export fails if they are
+    /// larger, so that a document doesn't grow unexpectedly large.
+    pub embedded_files_limit: Option<usize>,
+}
+
+impl PdfOptions<'_> {
+    /// The largest coordinate precision. Coordinates written with more
+    /// decimal places would exceed the precision of a 32-bit float.
+    pub const MAX_COORDINATE_PRECISION: u8 = 6;
+}
+
+/// Statistics about a PDF export, for tracking down slow or large exports.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PdfStats {
+    /// Statistics for each exported page.
+    pub pages: Vec<PdfPageStats>,
+    /// The number of embedded font programs.
+    pub fonts: usize,
+    /// The size of the embedded font programs in bytes.
+    pub font_bytes: usize,
+    /// The number of embedded images.
+    pub images: usize,
+    /// The size of the embedded image data in bytes, including alpha masks.
+    pub image_bytes: usize,
+    /// The size of the compressed page content streams in bytes.
+    pub content_bytes: usize,
+    /// The size of the whole PDF file in bytes.
+    pub size: usize,
+}
+
+/// Statistics about the export of a single page.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PdfPageStats {
+    /// The index of the page in the document, starting at zero.
+    pub index: usize,
+    /// How long it took to convert the page's frame into PDF content. Does
+    /// not include the compression of the content, which happens in the
+    /// background.
+    pub time: Duration,
+    /// The number of text runs on the page.
+    pub glyph_runs: usize,
+    /// The number of shapes on the page.
+    pub shapes: usize,
+    /// The number of images placed on the page.
+    pub images: usize,
+}
+
+/// Settings for page thumbnails.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PdfThumbnails {
+    /// The maximum width and height of a thumbnail in pixels. Must be at
+    /// least one and at most [`MAX_SIZE`](Self::MAX_SIZE).
+    pub max_size: u32,
+    /// The JPEG quality of the thumbnails, from 1 (worst) to 100 (best).
+    pub quality: u8,
+    /// Renders the pages. The exporter doesn't rasterize frames itself, so
+    /// this is typically a wrapper around `typst_render::render`.
+    pub render: ThumbnailRenderer,
+}
+
+impl PdfThumbnails {
+    /// The largest thumbnail size, in pixels.
+    ///
+    /// Thumbnails are meant to be small previews, so this bounds the memory
+    /// needed to render one to a few megabytes per page.
+    pub const MAX_SIZE: u32 = 1024;
+}
+
+/// Renders a frame with the given number of pixels per point onto an opaque
+/// background.
+///
+/// Returns the width and height of the image in pixels and its pixels as
+/// interleaved 8-bit RGB values, row by row.
+pub type ThumbnailRenderer = fn(frame: &Frame, pixel_per_pt: f32) -> (u32, u32, Vec<u8>);
+
+/// A version of the PDF specification.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum PdfVersion {
+    /// PDF 1.7.
+    #[default]
+    Pdf17,
+    /// PDF 2.0. The document information dictionary then only holds the
+    /// document's dates, since its other entries are deprecated in favor of
+    /// the XMP metadata.
+    Pdf20,
+}
+
+impl PdfVersion {
+    /// The major and minor version number.
+    fn numbers(self) -> (u8, u8) {
+        match self {
+            Self::Pdf17 => (1, 7),
+            Self::Pdf20 => (2, 0),
+        }
+    }
+
+    /// The version as written in the XMP metadata, e.g. `1.7`.
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Pdf17 => "1.7",
+            Self::Pdf20 => "2.0",
+        }
+    }
+}
+
+/// How a viewer or printer maps colors that are out of its gamut.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum RenderingIntent {
+    /// Compress the whole gamut, keeping the relation between colors. Suits
+    /// photos.
+    Perceptual,
+    /// Keep colors within the gamut exactly and clip the others, adapting to
+    /// the white point of the medium. Suits logos and charts.
+    RelativeColorimetric,
+    /// Keep the saturation of colors at the cost of their hue. Suits business
+    /// graphics.
+    Saturation,
+    /// Like relative colorimetric, but without adapting to the white point.
+    AbsoluteColorimetric,
+}
+
+impl RenderingIntent {
+    /// The corresponding PDF rendering intent.
+    fn to_pdf(self) -> pdf_writer::types::RenderingIntent {
+        match self {
+            Self::Perceptual => pdf_writer::types::RenderingIntent::Perceptual,
+            Self::RelativeColorimetric => {
+                pdf_writer::types::RenderingIntent::RelativeColorimetric
+            }
+            Self::Saturation => pdf_writer::types::RenderingIntent::Saturation,
+            Self::AbsoluteColorimetric => {
+                pdf_writer::types::RenderingIntent::AbsoluteColorimetric
+            }
+        }
+    }
+}
+
+/// A transition effect for presentation mode.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PdfTransition {
+    /// The visual effect of the transition.
+    pub style: TransitionStyle,
+    /// How long the transition takes, in seconds. Must be a non-negative,
+    /// finite number.
+    pub duration: f32,
+    /// If given, viewers advance to the next page after showing the page for
+    /// this many seconds. Must be a non-negative, finite number.
+    pub advance: Option<f32>,
+}
+
+/// The visual effect of a page transition.
+///
+/// The fly, push, cover, uncover and fade styles need PDF 1.5, which is
+/// below the version that is written.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum TransitionStyle {
+    /// Replace the page without any effect.
+    Replace,
+    /// Sweep two lines across the screen to reveal the page.
+    Split,
+    /// Reveal the page through multiple lines, like a venetian blind.
+    Blinds,
+    /// Reveal the page through a growing or shrinking rectangle.
+    Box,
+    /// Sweep a single line across the screen to reveal the page.
+    Wipe,
+    /// Dissolve the old page into the new one piecewise.
+    Dissolve,
+    /// Like dissolve, but sweeping across the screen.
+    Glitter,
+    /// Fly the page in.
+    Fly,
+    /// Push the old page off the screen with the new one.
+    Push,
+    /// Slide the new page over the old one.
+    Cover,
+    /// Slide the old page off to uncover the new one.
+    Uncover,
+    /// Fade from the old page to the new one.
+    Fade,
+}
+
+impl TransitionStyle {
+    /// The corresponding PDF transition style.
+    fn to_pdf(self) -> Name<'static> {
+        Name(match self {
+            Self::Replace => b"R",
+            Self::Split => b"Split",
+            Self::Blinds => b"Blinds",
+            Self::Box => b"Box",
+            Self::Wipe => b"Wipe",
+            Self::Dissolve => b"Dissolve",
+            Self::Glitter => b"Glitter",
+            Self::Fly => b"Fly",
+            Self::Push => b"Push",
+            Self::Cover => b"Cover",
+            Self::Uncover => b"Uncover",
+            Self::Fade => b"Fade",
+        })
+    }
+}
+
+/// How viewers treat link annotations.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum LinkVisibility {
+    /// Links are clickable on screen and kept when printing.
+    #[default]
+    Visible,
+    /// Links are neither shown nor clickable on screen, but kept when
+    /// printing.
+    NoView,
+    /// Links are neither shown, clickable, nor printed, but remain in the file
+    /// for tools that read them.
+    Hidden,
+}
+
+impl LinkVisibility {
+    /// The corresponding annotation flags.
+    fn to_pdf(self) -> AnnotationFlags {
+        match self {
+            Self::Visible => AnnotationFlags::PRINT,
+            Self::NoView => AnnotationFlags::PRINT | AnnotationFlags::NO_VIEW,
+            Self::Hidden => AnnotationFlags::HIDDEN,
+        }
+    }
+}
+
+/// A file that is embedded into the PDF and that viewers show as an icon at
+/// a spot on a page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdfFileAttachment {
+    /// The index of the page, starting at zero. Attachments on pages that
+    /// are not exported are left out.
+    pub page: usize,
+    /// The position of the icon's top-left corner on the page.
+    pub pos: Point,
+    /// The size of the icon's clickable area.
+    pub size: Size,
+    /// The icon that viewers show.
+    pub icon: AttachmentIcon,
+    /// The attached file. Its description is shown by viewers on hover.
+    pub file: PdfEmbeddedFile,
+}
+
+/// A file that is embedded into the PDF.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdfEmbeddedFile {
+    /// The name of the file.
+    pub name: EcoString,
+    /// The contents of the file.
+    pub data: Bytes,
+    /// A description of the file.
+    pub description: Option<EcoString>,
+    /// The MIME type of the file, e.g. `text/csv`.
+    pub mime_type: Option<EcoString>,
+    /// When the file was last modified.
+    pub modified: Option<Datetime>,
+    /// How the file relates to the document. If given, the file is also
+    /// registered as one of the document's associated files.
+    pub relationship: Option<AssociatedFileRelationship>,
+}
+
+/// How an associated file relates to the document.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum AssociatedFileRelationship {
+    /// The original source material of the document.
+    Source,
+    /// Data that the document visualizes, e.g. the table behind a chart.
+    Data,
+    /// An alternative representation of the document's content.
+    Alternative,
+    /// A supplemental representation of the document's content, e.g. a
+    /// machine-readable invoice.
+    Supplement,
+    /// A relationship that is none of the others.
+    Unspecified,
+}
+
+impl AssociatedFileRelationship {
+    /// The corresponding PDF relationship name.
+    fn to_pdf(self) -> Name<'static> {
+        Name(match self {
+            Self::Source => b"Source",
+            Self::Data => b"Data",
+            Self::Alternative => b"Alternative",
+            Self::Supplement => b"Supplement",
+            Self::Unspecified => b"Unspecified",
+        })
+    }
+}
+
+/// The icon of a file attachment.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum AttachmentIcon {
+    /// A push pin.
+    #[default]
+    PushPin,
+    /// A paperclip.
+    Paperclip,
+    /// A chart.
+    Graph,
+    /// A tag.
+    Tag,
+}
+
+impl AttachmentIcon {
+    /// The corresponding PDF icon name.
+    fn to_pdf(self) -> Name<'static> {
+        Name(match self {
+            Self::PushPin => b"PushPin",
+            Self::Paperclip => b"Paperclip",
+            Self::Graph => b"Graph",
+            Self::Tag => b"Tag",
+        })
+    }
 }
 
 /// Context for exporting a whole PDF document.
 struct PdfContext<'a> {
     /// The document that we're currently exporting.
     document: &'a Document,
+    /// The options for the export.
+    options: &'a PdfOptions<'a>,
     /// The writer we are writing the PDF into.
     pdf: Pdf,
     /// Content of exported pages.
     pages: Vec<Option<EncodedPage>>,
-    /// Page ranges to export.
-    /// When `None`, all pages are exported.
-    exported_pages: Option<PageRanges>,
     /// For each font a mapping from used glyphs to their text representation.
     /// May contain multiple chars in case of ligatures or similar things. The
     /// same glyph can have a different text representation within one document,
@@ -101,6 +670,9 @@ struct PdfContext<'a> {
     /// We keep track of this to determine the main document language.
     /// BTreeMap is used to write sorted list of languages to metadata.
     languages: BTreeMap<Lang, usize>,
+    /// The number of glyphs for each language and region, to determine the
+    /// region of the main document language.
+    regions: BTreeMap<(Lang, Region), usize>,
 
     /// Allocator for indirect reference IDs.
     alloc: Ref,
@@ -125,6 +697,11 @@ struct PdfContext<'a> {
     pattern_refs: Vec<Ref>,
     /// The IDs of written external graphics states.
     ext_gs_refs: Vec<Ref>,
+    /// The IDs of written form XObjects.
+    form_refs: Vec<Ref>,
+    /// The IDs of the file specifications of the document's associated
+    /// files.
+    associated_files: Vec<Ref>,
     /// Handles color space writing.
     colors: ColorSpaces,
 
@@ -132,12 +709,19 @@ struct PdfContext<'a> {
     font_map: Remapper<Font>,
     /// Deduplicates images used across the document.
     image_map: Remapper<Image>,
-    /// Handles to deferred image conversions.
-    image_deferred_map: HashMap<usize, Deferred<EncodedImage>>,
+    /// The settings each image is encoded with, covering all of its
+    /// placements.
+    image_settings: HashMap<usize, ImageSettings>,
     /// Deduplicates gradients used across the document.
     gradient_map: Remapper<PdfGradient>,
     /// Deduplicates patterns used across the document.
     pattern_map: Remapper<PdfPattern>,
+    /// The rendered tiles of the patterns used across the document.
+    pattern_tiles: IndexMap<Pattern, PdfPatternTile>,
+    /// The frames that are placed on several pages, by hash.
+    reused_frames: HashMap<u128, ReusedFrame>,
+    /// Frames that are used on several pages, by hash.
+    form_map: IndexMap<u128, EncodedForm>,
     /// Deduplicates external graphics states used across the document.
     extg_map: Remapper<ExtGState>,
     /// Deduplicates color glyphs.
@@ -147,21 +731,37 @@ struct PdfContext<'a> {
     dests: Vec<(Label, Ref)>,
     /// Maps from locations to named destinations that point to them.
     loc_to_dest: HashMap<Location, Label>,
+
+    /// The characters for which a missing glyph was already reported, per
+    /// font.
+    missing_glyphs: HashSet<(Font, char)>,
+    /// Warnings that arose during export.
+    warnings: Vec<SourceDiagnostic>,
+    /// Whether the warning about overprinting a non-CMYK color was issued.
+    overprint_ignored: bool,
+    /// Statistics about the export, if they are collected.
+    stats: Option<PdfStats>,
+    /// How coordinates are rounded before they are written.
+    rounding: Rounding,
+    /// Errors that arose during export. They don't abort the export right
+    /// away so that all of them can be reported at once.
+    errors: EcoVec<SourceDiagnostic>,
 }
 
 impl<'a> PdfContext<'a> {
-    fn new(document: &'a Document, page_ranges: Option<PageRanges>) -> Self {
+    fn new(document: &'a Document, options: &'a PdfOptions<'a>) -> Self {
         let mut alloc = Ref::new(1);
         let page_tree_ref = alloc.bump();
         let global_resources_ref = alloc.bump();
         let type3_font_resources_ref = alloc.bump();
         Self {
             document,
+            options,
             pdf: Pdf::new(),
             pages: vec![],
-            exported_pages: page_ranges,
             glyph_sets: HashMap::new(),
             languages: BTreeMap::new(),
+            regions: BTreeMap::new(),
             alloc,
             page_tree_ref,
             global_resources_ref,
@@ -171,22 +771,74 @@ impl<'a> PdfContext<'a> {
             gradient_refs: vec![],
             pattern_refs: vec![],
             ext_gs_refs: vec![],
+            form_refs: vec![],
+            associated_files: vec![],
             colors: ColorSpaces::default(),
             font_map: Remapper::new(),
             image_map: Remapper::new(),
-            image_deferred_map: HashMap::default(),
+            image_settings: HashMap::default(),
             gradient_map: Remapper::new(),
             pattern_map: Remapper::new(),
+            pattern_tiles: IndexMap::new(),
+            reused_frames: HashMap::new(),
+            form_map: IndexMap::new(),
             extg_map: Remapper::new(),
             color_font_map: ColorFontMap::new(),
             dests: vec![],
             loc_to_dest: HashMap::new(),
+            missing_glyphs: HashSet::new(),
+            warnings: vec![],
+            overprint_ignored: false,
+            stats: None,
+            rounding: Rounding::new(options.coordinate_precision),
+            errors: EcoVec::new(),
+        }
+    }
+
+    /// Report that `font` has no glyph for the character `c`, which is used in
+    /// the text at `span`.
+    ///
+    /// Each character is only reported once per font.
+    fn missing_glyph(&mut self, font: &Font, c: char, span: Span) {
+        if !self.missing_glyphs.insert((font.clone(), c)) {
+            return;
+        }
+
+        let message = eco_format!(
+            "the font `{}` does not contain a glyph for {c:?}",
+            font.info().family
+        );
+        if self.options.missing_glyphs_are_errors {
+            self.errors.push(SourceDiagnostic::error(span, message));
+        } else {
+            self.warnings.push(SourceDiagnostic::warning(span, message));
+        }
+    }
+
+    /// Report the collected diagnostics and return the finished PDF.
+    fn finish(
+        mut self,
+        tracer: &mut Tracer,
+    ) -> SourceResult<(Vec<u8>, Option<PdfStats>)> {
+        for warning in self.warnings {
+            tracer.warn(warning);
         }
+
+        if !self.errors.is_empty() {
+            return Err(self.errors);
+        }
+
+        let buffer = self.pdf.finish();
+        if let Some(stats) = &mut self.stats {
+            stats.size = buffer.len();
+        }
+
+        Ok((buffer, self.stats))
     }
 }
 
 /// Write the document catalog.
-fn write_catalog(ctx: &mut PdfContext, ident: Smart<&str>, timestamp: Option<Datetime>) {
+fn write_catalog(ctx: &mut PdfContext) {
     let lang = ctx.languages.iter().max_by_key(|(_, &count)| count).map(|(&l, _)| l);
 
     let dir = if lang.map(Lang::dir) == Some(Dir::RTL) {
@@ -196,16 +848,28 @@ fn write_catalog(ctx: &mut PdfContext, ident: Smart<&str>, timestamp: Option<Dat
     };
 
     // Write the outline tree.
-    let outline_root_id = outline::write_outline(ctx);
+    let region = lang.and_then(|lang| {
+        ctx.regions
+            .iter()
+            .filter(|((l, _), _)| *l == lang)
+            .max_by_key(|(_, &count)| count)
+            .map(|(&(_, region), _)| region)
+    });
+    let outline_root_id = outline::write_outline(ctx, lang, region);
 
     // Write the page labels.
     let page_labels = page::write_page_labels(ctx);
 
-    // Write the document information.
+    // Write the document information. PDF 2.0 deprecates all of its entries
+    // except for the dates.
+    let version = ctx.options.version;
+    let legacy_info = version == PdfVersion::Pdf17;
     let mut info = ctx.pdf.document_info(ctx.alloc.bump());
     let mut xmp = XmpWriter::new();
     if let Some(title) = &ctx.document.title {
-        info.title(TextStr(title));
+        if legacy_info {
+            info.title(TextStr(title));
+        }
         xmp.title([(None, title.as_str())]);
     }
 
@@ -228,22 +892,28 @@ fn write_catalog(ctx: &mut PdfContext, ident: Smart<&str>, timestamp: Option<Dat
         // bit weird to not use the array (and it makes Acrobat show the author
         // list in quotes), but there's not much we can do about that.
         let joined = authors.join(", ");
-        info.author(TextStr(&joined));
+        if legacy_info {
+            info.author(TextStr(&joined));
+        }
         xmp.creator([joined.as_str()]);
     }
 
     let creator = eco_format!("Typst {}", env!("CARGO_PKG_VERSION"));
-    info.creator(TextStr(&creator));
+    if legacy_info {
+        info.creator(TextStr(&creator));
+    }
     xmp.creator_tool(&creator);
 
     let keywords = &ctx.document.keywords;
     if !keywords.is_empty() {
         let joined = keywords.join(", ");
-        info.keywords(TextStr(&joined));
+        if legacy_info {
+            info.keywords(TextStr(&joined));
+        }
         xmp.pdf_keywords(&joined);
     }
 
-    if let Some(date) = ctx.document.date.unwrap_or(timestamp) {
+    if let Some(date) = ctx.document.date.unwrap_or(ctx.options.timestamp) {
         let tz = ctx.document.date.is_auto();
         if let Some(pdf_date) = pdf_date(date, tz) {
             info.creation_date(pdf_date);
@@ -266,14 +936,17 @@ fn write_catalog(ctx: &mut PdfContext, ident: Smart<&str>, timestamp: Option<Dat
     let instance_id = hash_base64(&ctx.pdf.as_bytes());
 
     // Determine the document's ID. It should be as stable as possible.
-    const PDF_VERSION: &str = "PDF-1.7";
-    let doc_id = if let Smart::Custom(ident) = ident {
+    let pdf_version = match version {
+        PdfVersion::Pdf17 => "PDF-1.7",
+        PdfVersion::Pdf20 => "PDF-2.0",
+    };
+    let doc_id = if let Smart::Custom(ident) = ctx.options.ident {
         // We were provided with a stable ID. Yay!
-        hash_base64(&(PDF_VERSION, ident))
+        hash_base64(&(pdf_version, ident))
     } else if ctx.document.title.is_some() && !ctx.document.author.is_empty() {
         // If not provided from the outside, but title and author were given, we
         // compute a hash of them, which should be reasonably stable and unique.
-        hash_base64(&(PDF_VERSION, &ctx.document.title, &ctx.document.author))
+        hash_base64(&(pdf_version, &ctx.document.title, &ctx.document.author))
     } else {
         // The user provided no usable metadata which we can use as an `/ID`.
         instance_id.clone()
@@ -286,7 +959,7 @@ fn write_catalog(ctx: &mut PdfContext, ident: Smart<&str>, timestamp: Option<Dat
         .set_file_id((doc_id.clone().into_bytes(), instance_id.into_bytes()));
 
     xmp.rendition_class(RenditionClass::Proof);
-    xmp.pdf_version("1.7");
+    xmp.pdf_version(version.as_str());
 
     let xmp_buf = xmp.finish(None);
     let meta_ref = ctx.alloc.bump();
@@ -295,6 +968,9 @@ fn write_catalog(ctx: &mut PdfContext, ident: Smart<&str>, timestamp: Option<Dat
         .pair(Name(b"Type"), Name(b"Metadata"))
         .pair(Name(b"Subtype"), Name(b"XML"));
 
+    // Write the document-level embedded files.
+    let embedded_files = embed::write_embedded_files(ctx);
+
     // Write the document catalog.
     let mut catalog = ctx.pdf.catalog(ctx.alloc.bump());
     catalog.pages(ctx.page_tree_ref);
@@ -310,6 +986,15 @@ fn write_catalog(ctx: &mut PdfContext, ident: Smart<&str>, timestamp: Option<Dat
     }
     names.finish();
     dests_name_tree.finish();
+
+    // Write the embedded file name tree.
+    if !embedded_files.is_empty() {
+        let mut files_name_tree = name_dict.embedded_files();
+        let mut names = files_name_tree.names();
+        for (name, spec_ref) in &embedded_files {
+            names.insert(Str(name.as_bytes()), *spec_ref);
+        }
+    }
     name_dict.finish();
 
     // Insert the page labels.
@@ -329,6 +1014,13 @@ fn write_catalog(ctx: &mut PdfContext, ident: Smart<&str>, timestamp: Option<Dat
         catalog.lang(TextStr(lang.as_str()));
     }
 
+    if !ctx.associated_files.is_empty() {
+        catalog
+            .insert(Name(b"AF"))
+            .array()
+            .items(ctx.associated_files.iter().copied());
+    }
+
     catalog.finish();
 }
 
@@ -585,6 +1277,32 @@ impl AbsExt for Abs {
     }
 }
 
+/// Rounds coordinates to a fixed number of decimal places, if any.
+#[derive(Debug, Copy, Clone)]
+struct Rounding(Option<f32>);
+
+impl Rounding {
+    /// Round to the given number of decimal places, or not at all.
+    fn new(places: Option<u8>) -> Self {
+        Self(places.map(|places| 10f32.powi(i32::from(places))))
+    }
+
+    /// Round a coordinate.
+    fn round(self, value: f32) -> f32 {
+        match self.0 {
+            Some(factor) => (value * factor).round() / factor,
+            None => value,
+        }
+    }
+
+    /// Round a scale or skew factor of a transform. Those are kept two
+    /// decimal places more precise, since they scale everything drawn after
+    /// them.
+    fn round_factor(self, value: f32) -> f32 {
+        Self(self.0.map(|factor| factor * 100.0)).round(value)
+    }
+}
+
 /// Additional methods for [`Em`].
 trait EmExt {
     /// Convert an em length to a number of PDF font units.
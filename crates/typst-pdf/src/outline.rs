@@ -1,14 +1,24 @@
 use std::num::NonZeroUsize;
 
+use ecow::{eco_format, EcoString};
 use pdf_writer::{Finish, Ref, TextStr};
-use typst::foundations::{NativeElement, Packed, StyleChain};
+use typst::foundations::{NativeElement, Packed, Smart, StyleChain};
+use typst::introspection::Location;
 use typst::layout::Abs;
-use typst::model::HeadingElem;
+use typst::model::{
+    FigureElem, FigureKind, HeadingElem, Numbering, Supplement, TableElem,
+};
+use typst::text::{localized_str, Lang, Region};
+use typst::visualize::ImageElem;
 
 use crate::{AbsExt, PdfContext};
 
 /// Construct the outline for the document.
-pub(crate) fn write_outline(ctx: &mut PdfContext) -> Option<Ref> {
+pub(crate) fn write_outline(
+    ctx: &mut PdfContext,
+    lang: Option<Lang>,
+    region: Option<Region>,
+) -> Option<Ref> {
     let mut tree: Vec<HeadingNode> = vec![];
 
     // Stores the level of the topmost skipped ancestor of the next bookmarked
@@ -20,7 +30,7 @@ pub(crate) fn write_outline(ctx: &mut PdfContext) -> Option<Ref> {
     let elements = ctx.document.introspector.query(&HeadingElem::elem().select());
 
     for elem in elements.iter() {
-        if let Some(page_ranges) = &ctx.exported_pages {
+        if let Some(page_ranges) = &ctx.options.page_ranges {
             if !page_ranges
                 .includes_page(ctx.document.introspector.page(elem.location().unwrap()))
             {
@@ -91,30 +101,123 @@ pub(crate) fn write_outline(ctx: &mut PdfContext) -> Option<Ref> {
         }
     }
 
-    if tree.is_empty() {
+    let sections = if ctx.options.figure_outline {
+        figure_sections(ctx, lang, region)
+    } else {
+        vec![]
+    };
+
+    if tree.is_empty() && sections.is_empty() {
         return None;
     }
 
     let root_id = ctx.alloc.bump();
     let start_ref = ctx.alloc;
-    let len = tree.len();
+    let len = tree.len() + sections.len();
 
     let mut prev_ref = None;
     for (i, node) in tree.iter().enumerate() {
         prev_ref = Some(write_outline_item(ctx, node, root_id, prev_ref, i + 1 == len));
     }
 
+    for (i, (title, figures)) in sections.iter().enumerate() {
+        let is_last = tree.len() + i + 1 == len;
+        prev_ref =
+            Some(write_figure_section(ctx, title, figures, root_id, prev_ref, is_last));
+    }
+
     ctx.pdf
         .outline(root_id)
         .first(start_ref)
-        .last(Ref::new(
-            ctx.alloc.get() - tree.last().map(|child| child.len() as i32).unwrap_or(1),
-        ))
-        .count(tree.len() as i32);
+        .last(prev_ref.unwrap())
+        .count(len as i32);
 
     Some(root_id)
 }
 
+/// Collect the captioned figures and tables for the secondary outline
+/// sections, together with their titles in the document's language and
+/// region. Sections without any entries are left out.
+fn figure_sections(
+    ctx: &PdfContext,
+    lang: Option<Lang>,
+    region: Option<Region>,
+) -> Vec<(&'static str, Vec<FigureNode>)> {
+    let elements = ctx.document.introspector.query(&FigureElem::elem().select());
+
+    let mut figures = vec![];
+    let mut tables = vec![];
+    let mut counts: Vec<(FigureKind, usize)> = vec![];
+    for elem in elements.iter() {
+        let figure = elem.to_packed::<FigureElem>().unwrap();
+        let Smart::Custom(kind) = figure.kind(StyleChain::default()) else {
+            continue;
+        };
+
+        // Number the figures per kind, like their shared counter does.
+        let number = match counts.iter_mut().find(|(k, _)| *k == kind) {
+            Some((_, count)) => {
+                *count += 1;
+                *count
+            }
+            None => {
+                counts.push((kind.clone(), 1));
+                1
+            }
+        };
+
+        let Some(caption) = figure.caption(StyleChain::default()) else {
+            continue;
+        };
+
+        let loc = figure.location().unwrap();
+        if let Some(page_ranges) = &ctx.options.page_ranges {
+            if !page_ranges.includes_page(ctx.document.introspector.page(loc)) {
+                // Don't bookmark figures in non-exported pages
+                continue;
+            }
+        }
+
+        let mut title = EcoString::new();
+        if let Some(numbering) = figure.numbering(StyleChain::default()) {
+            if let Smart::Custom(Some(Supplement::Content(supplement))) =
+                figure.supplement(StyleChain::default())
+            {
+                title.push_str(supplement.plain_text().trim());
+                title.push('\u{a0}');
+            }
+            match numbering {
+                Numbering::Pattern(pattern) => title.push_str(&pattern.apply(&[number])),
+                Numbering::Func(_) => title.push_str(&eco_format!("{number}")),
+            }
+            title.push_str(": ");
+        }
+        title.push_str(caption.body().plain_text().trim());
+        let node = FigureNode { title, loc };
+        match kind {
+            FigureKind::Elem(elem) if elem == TableElem::elem() => tables.push(node),
+            FigureKind::Elem(elem) if elem == ImageElem::elem() => figures.push(node),
+            _ => {}
+        }
+    }
+
+    let lang = lang.unwrap_or(Lang::ENGLISH);
+    [
+        (localized_str(lang, region, "figures"), figures),
+        (localized_str(lang, region, "tables"), tables),
+    ]
+    .into_iter()
+    .filter(|(_, nodes)| !nodes.is_empty())
+    .collect()
+}
+
+/// A captioned figure in a secondary outline section.
+#[derive(Debug, Clone)]
+struct FigureNode {
+    title: EcoString,
+    loc: Location,
+}
+
 /// A heading in the outline panel.
 #[derive(Debug, Clone)]
 struct HeadingNode<'a> {
@@ -153,6 +256,7 @@ fn write_outline_item(
     let id = ctx.alloc.bump();
     let next_ref = Ref::new(id.get() + node.len() as i32);
 
+    let dest = outline_dest(ctx, node.element.location().unwrap());
     let mut outline = ctx.pdf.outline_item(id);
     outline.parent(parent_ref);
 
@@ -173,18 +277,8 @@ fn write_outline_item(
     let body = node.element.body();
     outline.title(TextStr(body.plain_text().trim()));
 
-    let loc = node.element.location().unwrap();
-    let pos = ctx.document.introspector.position(loc);
-    let index = pos.page.get() - 1;
-
-    // Don't link to non-exported pages.
-    if let Some(Some(page)) = ctx.pages.get(index) {
-        let y = (pos.point.y - Abs::pt(10.0)).max(Abs::zero());
-        outline.dest().page(page.id).xyz(
-            pos.point.x.to_f32(),
-            (page.size.y - y).to_f32(),
-            None,
-        );
+    if let Some((page_id, x, y)) = dest {
+        outline.dest().page(page_id).xyz(x, y, None);
     }
 
     outline.finish();
@@ -202,3 +296,136 @@ fn write_outline_item(
 
     id
 }
+
+/// Write a collapsed outline section listing figures, followed by its
+/// entries.
+fn write_figure_section(
+    ctx: &mut PdfContext,
+    title: &str,
+    figures: &[FigureNode],
+    parent_ref: Ref,
+    prev_ref: Option<Ref>,
+    is_last: bool,
+) -> Ref {
+    let id = ctx.alloc.bump();
+    let len = figures.len() as i32;
+
+    let mut outline = ctx.pdf.outline_item(id);
+    outline.parent(parent_ref);
+
+    if !is_last {
+        outline.next(Ref::new(id.get() + len + 1));
+    }
+
+    if let Some(prev_rev) = prev_ref {
+        outline.prev(prev_rev);
+    }
+
+    outline.first(Ref::new(id.get() + 1));
+    outline.last(Ref::new(id.get() + len));
+    outline.count(-len);
+    outline.title(TextStr(title));
+    outline.finish();
+
+    for (i, figure) in figures.iter().enumerate() {
+        let item_id = ctx.alloc.bump();
+        let dest = outline_dest(ctx, figure.loc);
+        let mut item = ctx.pdf.outline_item(item_id);
+        item.parent(id);
+
+        if i + 1 < figures.len() {
+            item.next(Ref::new(item_id.get() + 1));
+        }
+
+        if i > 0 {
+            item.prev(Ref::new(item_id.get() - 1));
+        }
+
+        item.title(TextStr(&figure.title));
+
+        if let Some((page_id, x, y)) = dest {
+            item.dest().page(page_id).xyz(x, y, None);
+        }
+    }
+
+    id
+}
+
+/// Find the page and coordinates an outline item at a location jumps to,
+/// unless the location is on a non-exported page.
+fn outline_dest(ctx: &PdfContext, loc: Location) -> Option<(Ref, f32, f32)> {
+    let pos = ctx.document.introspector.position(loc);
+    let index = pos.page.get() - 1;
+
+    // Don't link to non-exported pages.
+    let page = ctx.pages.get(index)?.as_ref()?;
+    let y = (pos.point.y - Abs::pt(10.0)).max(Abs::zero());
+    Some((page.id, pos.point.x.to_f32(), (page.size.y - y).to_f32()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::TestWorld;
+    use crate::PdfOptions;
+
+    const FIGURES: &str = "\
+        #figure(rect(), caption: [A])\n\
+        #figure(rect(), caption: [B])\n\
+        #pagebreak()\n\
+        #figure(table[x], caption: [C])\n\
+        #pagebreak()\n\
+        #figure(rect(), caption: [D])\n\
+        #figure(table[y], caption: [E])";
+
+    #[test]
+    fn test_figure_outline() {
+        let world = TestWorld::new(FIGURES);
+        assert_eq!(world.export(&PdfOptions::default()).count("Outlines"), 0);
+
+        let options = PdfOptions { figure_outline: true, ..Default::default() };
+        let pdf = world.export(&options);
+
+        // Both sections are collapsed and list their entries in order.
+        let titles: Vec<_> =
+            pdf.values("Title").iter().filter_map(|t| t.as_text()).collect();
+        assert_eq!(
+            titles,
+            [
+                "Figures",
+                "Figure\u{a0}1: A",
+                "Figure\u{a0}2: B",
+                "Figure\u{a0}3: D",
+                "Tables",
+                "Table\u{a0}1: C",
+                "Table\u{a0}2: E",
+            ]
+        );
+        let counts: Vec<_> =
+            pdf.values("Count").iter().filter_map(|c| c.as_int()).collect();
+        assert!(counts.contains(&-3) && counts.contains(&-2));
+
+        // Each entry jumps to the page of its figure.
+        let pages = pdf.dict(pdf.catalog(), "Pages").get("Kids").unwrap();
+        let pages = pdf.get(pages).as_array().unwrap();
+        let targets: Vec<_> = pdf
+            .values("Dest")
+            .iter()
+            .map(|dest| dest.as_array().unwrap()[0].clone())
+            .collect();
+        let expected = [0, 0, 2, 1, 2].map(|i| pages[i].clone());
+        assert_eq!(targets, expected);
+    }
+
+    #[test]
+    fn test_figure_outline_localized() {
+        let world = TestWorld::new(&format!("#set text(lang: \"de\")\n{FIGURES}"));
+        let options = PdfOptions { figure_outline: true, ..Default::default() };
+        let pdf = world.export(&options);
+        let titles: Vec<_> =
+            pdf.values("Title").iter().filter_map(|t| t.as_text()).collect();
+        assert!(titles.contains(&"Abbildungen".into()));
+        assert!(titles.contains(&"Abbildung\u{a0}1: A".into()));
+        assert!(titles.contains(&"Tabellen".into()));
+        assert!(titles.contains(&"Tabelle\u{a0}2: E".into()));
+    }
+}
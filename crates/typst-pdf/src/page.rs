@@ -1,10 +1,19 @@
 use std::collections::HashMap;
 use std::num::NonZeroUsize;
+use std::ops::{Deref, DerefMut};
+use std::time::Instant;
 
 use crate::color::PaintEncode;
+use crate::embed::write_embedded_file;
 use crate::extg::ExtGState;
-use crate::image::deferred_image;
-use crate::{deflate_deferred, AbsExt, EmExt, PdfContext};
+use crate::image::{
+    deferred_thumbnail, downsampled_size, embedded_rotation, EncodedThumbnail,
+    ImageSettings,
+};
+use crate::{
+    deflate_deferred, AbsExt, EmExt, PdfContext, PdfFileAttachment, PdfPageStats,
+    Rounding,
+};
 use ecow::{eco_format, EcoString};
 use pdf_writer::types::{
     ActionType, AnnotationFlags, AnnotationType, ColorSpaceOperand, LineCapStyle,
@@ -12,33 +21,59 @@ use pdf_writer::types::{
 };
 use pdf_writer::writers::{PageLabel, Resources};
 use pdf_writer::{Content, Filter, Finish, Name, Rect, Ref, Str, TextStr};
+use ttf_parser::{GlyphId, OutlineBuilder};
+use typst::diag::SourceDiagnostic;
 use typst::introspection::Meta;
 use typst::layout::{
     Abs, Em, Frame, FrameItem, GroupItem, Page, Point, Ratio, Size, Transform,
 };
 use typst::model::{Destination, Numbering};
+use typst::syntax::{FileId, Span};
 use typst::text::color::is_color_glyph;
-use typst::text::{Case, Font, TextItem, TextItemView};
-use typst::utils::{Deferred, Numeric, SliceExt};
+use typst::text::{Case, Font, Glyph, TextItem, TextItemView};
+use typst::utils::{hash128, Deferred, Numeric, Scalar, SliceExt};
 use typst::visualize::{
-    FixedStroke, Geometry, Image, LineCap, LineJoin, Paint, Path, PathItem, Shape,
+    Color, ColorSpace, DashPattern, FixedStroke, Geometry, Image, ImageKind, LineCap,
+    LineJoin, Paint, PaintOrder, Path, PathItem, Shape,
 };
 
 /// Construct page objects.
 #[typst_macros::time(name = "construct pages")]
 pub(crate) fn construct_pages(ctx: &mut PdfContext, pages: &[Page]) {
-    let mut skipped_pages = 0;
-    for (i, page) in pages.iter().enumerate() {
-        if ctx
-            .exported_pages
+    let options = ctx.options;
+    let excluded = |i: usize| {
+        options
+            .page_ranges
             .as_ref()
             .is_some_and(|ranges| !ranges.includes_page_index(i))
-        {
+    };
+
+    ctx.reused_frames = find_reused_frames(pages, excluded);
+
+    let mut skipped_pages = 0;
+    for (i, page) in pages.iter().enumerate() {
+        if excluded(i) {
             // Don't export this page.
             ctx.pages.push(None);
             skipped_pages += 1;
         } else {
-            let mut encoded = construct_page(ctx, &page.frame);
+            let start = ctx.stats.is_some().then(Instant::now);
+            let mut encoded = construct_page(ctx, &page.frame, true);
+            if let (Some(stats), Some(start)) = (&mut ctx.stats, start) {
+                let time = start.elapsed();
+                let counts = encoded.stats.take().unwrap_or_default();
+                stats.pages.push(PdfPageStats { index: i, time, ..counts });
+            }
+            encoded.thumbnail = ctx
+                .options
+                .thumbnails
+                .map(|thumbnails| deferred_thumbnail(page.frame.clone(), thumbnails));
+            encoded.attachments = (0..ctx.options.file_attachments.len())
+                .filter(|&k| ctx.options.file_attachments[k].page == i)
+                .collect();
+            if ctx.options.clip_to_page {
+                drop_outside_annotations(ctx, &mut encoded, i);
+            }
             encoded.label = page
                 .numbering
                 .as_ref()
@@ -58,13 +93,157 @@ pub(crate) fn construct_pages(ctx: &mut PdfContext, pages: &[Page]) {
     }
 }
 
+/// Find the frames that are placed directly on several of the exported pages
+/// and can be written once as a form XObject, like running headers.
+///
+/// Only these placements are measured, so only groups that are items of a
+/// page's frame are written as forms. The same frames nested anywhere else
+/// are written as usual.
+fn find_reused_frames(
+    pages: &[Page],
+    excluded: impl Fn(usize) -> bool,
+) -> HashMap<u128, ReusedFrame> {
+    struct Uses<'a> {
+        frame: &'a Frame,
+        count: usize,
+        visible: Option<(Point, Point)>,
+        scale: (f64, f64),
+    }
+
+    let mut uses: HashMap<u128, Uses> = HashMap::new();
+    for (i, page) in pages.iter().enumerate() {
+        if excluded(i) {
+            continue;
+        }
+
+        let size = page.frame.size();
+        for (pos, item) in page.frame.items() {
+            let FrameItem::Group(group) = item else { continue };
+            let entry = uses.entry(hash128(&group.frame)).or_insert(Uses {
+                frame: &group.frame,
+                count: 0,
+                visible: Some((Point::splat(Abs::inf()), Point::splat(-Abs::inf()))),
+                scale: (0.0, 0.0),
+            });
+            entry.count += 1;
+
+            // Images in the form are encoded for its largest placement.
+            let transform =
+                Transform::translate(pos.x, pos.y).pre_concat(group.transform);
+            let Transform { sx, ky, kx, sy, .. } = transform;
+            entry.scale.0 = entry.scale.0.max(sx.get().hypot(ky.get()));
+            entry.scale.1 = entry.scale.1.max(kx.get().hypot(sy.get()));
+
+            // Map the page's corners into the frame's coordinate system.
+            entry.visible = entry.visible.zip(transform.invert()).map(
+                |((mut min, mut max), inverse)| {
+                    for corner in [
+                        Point::zero(),
+                        Point::with_x(size.x),
+                        Point::with_y(size.y),
+                        size.to_point(),
+                    ] {
+                        let p = corner.transform(inverse);
+                        min = min.min(p);
+                        max = max.max(p);
+                    }
+                    (min, max)
+                },
+            );
+        }
+    }
+
+    uses.into_iter()
+        .filter(|(_, uses)| uses.count > 1 && is_self_contained(uses.frame))
+        .filter_map(|(hash, uses)| {
+            let (min, max) = uses.visible?;
+            let bbox =
+                Rect::new(min.x.to_f32(), min.y.to_f32(), max.x.to_f32(), max.y.to_f32());
+            let scale =
+                Transform::scale(Ratio::new(uses.scale.0), Ratio::new(uses.scale.1));
+            Some((hash, ReusedFrame { bbox, scale }))
+        })
+        .collect()
+}
+
+/// Whether a frame looks the same wherever it is placed, so that it can be
+/// written as a form XObject: It may not contain links, whose rectangles
+/// belong to the page, nor gradients or patterns, which are positioned
+/// relative to the page.
+fn is_self_contained(frame: &Frame) -> bool {
+    let solid = |paint: &Paint| matches!(paint, Paint::Solid(_));
+    let solid_stroke =
+        |stroke: &Option<FixedStroke>| stroke.as_ref().map_or(true, |s| solid(&s.paint));
+    frame.items().all(|(_, item)| match item {
+        FrameItem::Group(group) => is_self_contained(&group.frame),
+        FrameItem::Text(text) => solid(&text.fill) && solid_stroke(&text.stroke),
+        FrameItem::Shape(shape, _) => {
+            shape.fill.as_ref().map_or(true, solid) && solid_stroke(&shape.stroke)
+        }
+        FrameItem::Image(..) => true,
+        FrameItem::Meta(meta, _) => !matches!(meta, Meta::Link(_)),
+    })
+}
+
+/// Remove the links and file attachments of a page that lie fully outside of
+/// it, since the content they belong to is clipped away.
+fn drop_outside_annotations(ctx: &mut PdfContext, page: &mut EncodedPage, i: usize) {
+    let count = page.links.len() + page.attachments.len();
+    let size = page.size;
+    page.links.retain(|(_, rect)| overlaps_page(rect, size));
+    page.attachments.retain(|&k| {
+        overlaps_page(&attachment_rect(&ctx.options.file_attachments[k], size), size)
+    });
+
+    let dropped = count - page.links.len() - page.attachments.len();
+    if dropped > 0 {
+        ctx.warnings.push(
+            SourceDiagnostic::warning(
+                Span::detached(),
+                eco_format!(
+                    "dropped {dropped} {} outside of page {}",
+                    if dropped == 1 { "annotation" } else { "annotations" },
+                    i + 1,
+                ),
+            )
+            .with_hint("the content of each page is clipped to its bounds"),
+        );
+    }
+}
+
+/// Whether an annotation's rectangle in the PDF coordinate system touches a
+/// page of the given size.
+fn overlaps_page(rect: &Rect, size: Size) -> bool {
+    let (w, h) = (size.x.to_f32(), size.y.to_f32());
+    rect.x1.max(rect.x2) >= 0.0
+        && rect.x1.min(rect.x2) <= w
+        && rect.y1.max(rect.y2) >= 0.0
+        && rect.y1.min(rect.y2) <= h
+}
+
+/// The clickable area of a file attachment in the PDF coordinate system of a
+/// page of the given size.
+fn attachment_rect(attachment: &PdfFileAttachment, size: Size) -> Rect {
+    let x = attachment.pos.x.to_f32();
+    let y = (size.y - attachment.pos.y).to_f32();
+    Rect::new(x, y - attachment.size.y.to_f32(), x + attachment.size.x.to_f32(), y)
+}
+
 /// Construct a page object.
+///
+/// Only the frames of exported pages may share the form XObjects found by
+/// [`find_reused_frames`]. Other content like pattern tiles is written as is.
 #[typst_macros::time(name = "construct page")]
-pub(crate) fn construct_page(ctx: &mut PdfContext, frame: &Frame) -> EncodedPage {
+pub(crate) fn construct_page(
+    ctx: &mut PdfContext,
+    frame: &Frame,
+    on_page: bool,
+) -> EncodedPage {
     let page_ref = ctx.alloc.bump();
 
     let size = frame.size();
     let mut ctx = PageContext::new(ctx, size);
+    ctx.stats = ctx.parent.stats.is_some().then(PdfPageStats::default);
 
     // Make the coordinate system start at the top-left.
     ctx.bottom = size.y.to_f32();
@@ -77,7 +256,14 @@ pub(crate) fn construct_page(ctx: &mut PdfContext, frame: &Frame) -> EncodedPage
         ty: size.y,
     });
 
+    if ctx.parent.options.clip_to_page {
+        ctx.content.rect(0.0, 0.0, size.x.to_f32(), size.y.to_f32());
+        ctx.content.clip_nonzero();
+        ctx.content.end_path();
+    }
+
     // Encode the page into the content stream.
+    ctx.on_page = on_page;
     write_frame(&mut ctx, frame);
 
     EncodedPage {
@@ -88,6 +274,43 @@ pub(crate) fn construct_page(ctx: &mut PdfContext, frame: &Frame) -> EncodedPage
         links: ctx.links,
         label: None,
         resources: ctx.resources,
+        thumbnail: None,
+        attachments: vec![],
+        stats: ctx.stats,
+    }
+}
+
+/// A frame that is placed directly on several pages.
+pub struct ReusedFrame {
+    /// The part of the frame's coordinate system that is visible on any of
+    /// the pages.
+    bbox: Rect,
+    /// The largest scale at which the frame is placed on any of the pages.
+    scale: Transform,
+}
+
+/// A frame that is used on several pages, written once as a form XObject.
+pub struct EncodedForm {
+    /// The frame's content stream.
+    content: Deferred<Vec<u8>>,
+    /// The part of the frame that is visible on any page.
+    bbox: Rect,
+    /// Whether the frame uses opacities.
+    uses_opacities: bool,
+    /// The counts of the frame's items, if statistics are collected.
+    stats: Option<PdfPageStats>,
+}
+
+/// Write the form XObjects for frames that are used on several pages.
+pub(crate) fn write_forms(ctx: &mut PdfContext) {
+    for form in ctx.form_map.values() {
+        let id = ctx.alloc.bump();
+        ctx.form_refs.push(id);
+
+        let mut xobject = ctx.pdf.form_xobject(id, form.content.wait());
+        xobject.bbox(form.bbox);
+        xobject.pair(Name(b"Resources"), ctx.global_resources_ref);
+        xobject.filter(Filter::FlateDecode);
     }
 }
 
@@ -120,6 +343,11 @@ pub(crate) fn write_global_resources(ctx: &mut PdfContext) {
         let name = eco_format!("Im{}", im);
         images.pair(Name(name.as_bytes()), image_ref);
     }
+
+    for (fr, form_ref) in ctx.form_refs.iter().enumerate() {
+        let name = eco_format!("Fr{}", fr);
+        images.pair(Name(name.as_bytes()), *form_ref);
+    }
     images.finish();
 
     let mut patterns = ctx.pdf.indirect(patterns_ref).dict();
@@ -184,8 +412,10 @@ pub(crate) fn write_global_resources(ctx: &mut PdfContext) {
         resources.finish();
     }
 
-    // Write all of the functions used by the document.
-    ctx.colors.write_functions(&mut ctx.pdf);
+    // Write all of the functions used by the document, with the working RGB
+    // profile in place of sRGB if one was given.
+    let rgb_profile = ctx.options.working_rgb_profile.as_deref();
+    ctx.colors.write_functions(&mut ctx.pdf, rgb_profile);
 }
 
 /// Write a page tree node.
@@ -195,6 +425,12 @@ fn write_page(ctx: &mut PdfContext, i: usize, refs: &mut Vec<Ref>) {
         return;
     };
     let content_id = ctx.alloc.bump();
+    let attachments: Vec<_> = page
+        .attachments
+        .iter()
+        .map(|&k| (&ctx.options.file_attachments[k], ctx.alloc.bump()))
+        .collect();
+    let group_space = page.uses_opacities.then(|| ctx.colors.srgb(&mut ctx.alloc));
 
     refs.push(page.id);
 
@@ -207,24 +443,76 @@ fn write_page(ctx: &mut PdfContext, i: usize, refs: &mut Vec<Ref>) {
     page_writer.contents(content_id);
     page_writer.pair(Name(b"Resources"), ctx.global_resources_ref);
 
-    if page.uses_opacities {
+    let transition = ctx
+        .options
+        .page_transitions
+        .iter()
+        .find(|(ranges, _)| ranges.includes_page_index(i))
+        .map(|(_, transition)| transition);
+    if let Some(transition) = transition {
+        page_writer
+            .insert(Name(b"Trans"))
+            .dict()
+            .pair(Name(b"Type"), Name(b"Trans"))
+            .pair(Name(b"S"), transition.style.to_pdf())
+            .pair(Name(b"D"), transition.duration);
+        if let Some(advance) = transition.advance {
+            page_writer.pair(Name(b"Dur"), advance);
+        }
+    }
+
+    let thumbnail_id = page.thumbnail.as_ref().map(|_| ctx.alloc.bump());
+    if let Some(thumbnail_id) = thumbnail_id {
+        page_writer.pair(Name(b"Thumb"), thumbnail_id);
+    }
+
+    // Blend transparent content in an isolated group with an explicit
+    // color space, so that viewers don't composite against a backdrop of
+    // their own choosing.
+    if let Some(group_space) = group_space {
         page_writer
             .group()
             .transparency()
-            .isolated(false)
+            .isolated(true)
             .knockout(false)
             .color_space()
-            .srgb();
+            .icc_based(group_space);
     }
 
     let mut annotations = page_writer.annotations();
+    let mut dead_links = 0;
     for (dest, rect) in &page.links {
         let mut annotation = annotations.push();
         annotation.subtype(AnnotationType::Link).rect(*rect);
-        annotation.border(0.0, 0.0, 0.0, None).flags(AnnotationFlags::PRINT);
+        annotation
+            .border(0.0, 0.0, 0.0, None)
+            .flags(ctx.options.link_visibility.to_pdf());
 
         let pos = match dest {
             Destination::Url(uri) => {
+                if let Some(fragment) = internal_fragment(uri, ctx.options.document_url) {
+                    if ctx.dests.iter().any(|(label, _)| label.as_str() == fragment) {
+                        annotation
+                            .action()
+                            .action_type(ActionType::GoTo)
+                            .pair(Name(b"D"), Str(fragment.as_bytes()));
+                        continue;
+                    }
+
+                    ctx.warnings.push(
+                        SourceDiagnostic::warning(
+                            Span::detached(),
+                            eco_format!(
+                                "link to `#{fragment}` does not match any heading"
+                            ),
+                        )
+                        .with_hint(
+                            "only labelled headings on exported pages can be linked \
+                             to by fragment",
+                        ),
+                    );
+                }
+
                 annotation
                     .action()
                     .action_type(ActionType::Uri)
@@ -257,15 +545,74 @@ fn write_page(ctx: &mut PdfContext, i: usize, refs: &mut Vec<Ref>) {
                 .destination()
                 .page(page.id)
                 .xyz(pos.point.x.to_f32(), (page.size.y - y).to_f32(), None);
+        } else {
+            dead_links += 1;
         }
     }
 
+    if dead_links > 0 {
+        ctx.warnings.push(
+            SourceDiagnostic::warning(
+                Span::detached(),
+                eco_format!(
+                    "{dead_links} {} on page {} {} to pages that are not exported",
+                    if dead_links == 1 { "link" } else { "links" },
+                    i + 1,
+                    if dead_links == 1 { "points" } else { "point" },
+                ),
+            )
+            .with_hint("these links do nothing when clicked"),
+        );
+    }
+
+    for &(attachment, spec_id) in &attachments {
+        let rect = attachment_rect(attachment, page.size);
+        let mut annotation = annotations.push();
+        annotation.subtype(AnnotationType::FileAttachment).rect(rect);
+        annotation.flags(AnnotationFlags::PRINT);
+        annotation.pair(Name(b"Name"), attachment.icon.to_pdf());
+        if let Some(description) = &attachment.file.description {
+            annotation.contents(TextStr(description));
+        }
+        annotation.pair(Name(b"FS"), spec_id);
+    }
+
     annotations.finish();
     page_writer.finish();
 
-    ctx.pdf
-        .stream(content_id, page.content.wait())
-        .filter(Filter::FlateDecode);
+    let content = page.content.wait();
+    if let Some(stats) = &mut ctx.stats {
+        stats.content_bytes += content.len();
+    }
+
+    ctx.pdf.stream(content_id, content).filter(Filter::FlateDecode);
+
+    if let (Some(thumbnail), Some(thumbnail_id)) = (&page.thumbnail, thumbnail_id) {
+        let thumbnail = thumbnail.wait();
+        let mut image = ctx.pdf.image_xobject(thumbnail_id, &thumbnail.data);
+        image.filter(Filter::DctDecode);
+        image.width(thumbnail.width as i32);
+        image.height(thumbnail.height as i32);
+        image.color_space().device_rgb();
+        image.bits_per_component(8);
+    }
+
+    for (attachment, spec_id) in attachments {
+        write_embedded_file(ctx, &attachment.file, spec_id);
+    }
+}
+
+/// Extract the fragment of a URL that points into the document itself,
+/// either because the URL consists only of a fragment or because it starts
+/// with the URL of the document, if one is given.
+fn internal_fragment<'u>(url: &'u str, document_url: Option<&str>) -> Option<&'u str> {
+    if let Some(fragment) = url.strip_prefix('#') {
+        return Some(fragment);
+    }
+
+    let document_url = document_url?;
+    let (base, fragment) = url.split_once('#')?;
+    (base.trim_end_matches('/') == document_url.trim_end_matches('/')).then_some(fragment)
 }
 
 /// Write the page labels.
@@ -430,6 +777,12 @@ pub struct EncodedPage {
     pub resources: HashMap<PageResource, usize>,
     /// The page's PDF label.
     label: Option<PdfPageLabel>,
+    /// The page's pre-rendered thumbnail, if enabled.
+    thumbnail: Option<Deferred<EncodedThumbnail>>,
+    /// The indices of the file attachments that are pinned to the page.
+    attachments: Vec<usize>,
+    /// The counts of the page's items, if statistics are collected.
+    stats: Option<PdfPageStats>,
 }
 
 /// Represents a resource being used in a PDF page by its name.
@@ -449,6 +802,7 @@ impl PageResource {
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
 pub enum ResourceKind {
     XObject,
+    Form,
     Font,
     Gradient,
     Pattern,
@@ -466,6 +820,11 @@ impl PageResource {
         matches!(self.kind, ResourceKind::XObject)
     }
 
+    /// Returns whether the resource is a form XObject.
+    pub fn is_form(&self) -> bool {
+        matches!(self.kind, ResourceKind::Form)
+    }
+
     /// Returns whether the resource is a font.
     pub fn is_font(&self) -> bool {
         matches!(self.kind, ResourceKind::Font)
@@ -490,31 +849,157 @@ impl PageResource {
 /// An exporter for the contents of a single PDF page.
 pub struct PageContext<'a, 'b> {
     pub(crate) parent: &'a mut PdfContext<'b>,
-    pub content: Content,
+    pub content: RoundedContent,
     state: State,
     saves: Vec<State>,
     pub bottom: f32,
-    uses_opacities: bool,
+    pub uses_opacities: bool,
     links: Vec<(Destination, Rect)>,
     /// Keep track of the resources being used in the page.
     pub resources: HashMap<PageResource, usize>,
+    /// Whether the items being written are placed directly on a page, where
+    /// groups may be written as shared form XObjects.
+    on_page: bool,
+    /// The largest scale at which the content being written is placed on the
+    /// pages, on top of its own transforms. This is only not the identity
+    /// for shared form XObjects, whose images must have enough pixels for
+    /// every placement.
+    form_scale: Transform,
+    /// The counts of the items being written, if statistics are collected
+    /// for them. Content that is not tied to a single page, like color
+    /// glyphs and patterns, is not counted.
+    stats: Option<PdfPageStats>,
 }
 
 impl<'a, 'b> PageContext<'a, 'b> {
     pub fn new(parent: &'a mut PdfContext<'b>, size: Size) -> Self {
         PageContext {
+            content: RoundedContent::new(parent.rounding),
             parent,
             uses_opacities: false,
-            content: Content::new(),
             state: State::new(size),
             saves: vec![],
             bottom: 0.0,
             links: vec![],
             resources: HashMap::default(),
+            on_page: false,
+            form_scale: Transform::identity(),
+            stats: None,
+        }
+    }
+
+    /// Count an item that is being written, if statistics are collected.
+    fn count(&mut self, f: impl FnOnce(&mut PdfPageStats)) {
+        if let Some(stats) = &mut self.stats {
+            f(stats);
         }
     }
 }
 
+/// A content stream that rounds the coordinates written into it.
+///
+/// All other operators are available through dereferencing.
+pub struct RoundedContent {
+    content: Content,
+    rounding: Rounding,
+}
+
+impl RoundedContent {
+    fn new(rounding: Rounding) -> Self {
+        Self { content: Content::new(), rounding }
+    }
+
+    /// Finish the content stream and return its bytes.
+    pub fn finish(self) -> Vec<u8> {
+        self.content.finish()
+    }
+
+    /// `cm`: Pre-concatenate a transform to the current one.
+    pub fn transform(&mut self, matrix: [f32; 6]) -> &mut Self {
+        let matrix = self.round_matrix(matrix);
+        self.content.transform(matrix);
+        self
+    }
+
+    /// `Tm`: Set the text matrix.
+    pub fn set_text_matrix(&mut self, matrix: [f32; 6]) -> &mut Self {
+        let matrix = self.round_matrix(matrix);
+        self.content.set_text_matrix(matrix);
+        self
+    }
+
+    /// `m`: Begin a new subpath.
+    pub fn move_to(&mut self, x: f32, y: f32) -> &mut Self {
+        let r = self.rounding;
+        self.content.move_to(r.round(x), r.round(y));
+        self
+    }
+
+    /// `l`: Append a straight line to the current subpath.
+    pub fn line_to(&mut self, x: f32, y: f32) -> &mut Self {
+        let r = self.rounding;
+        self.content.line_to(r.round(x), r.round(y));
+        self
+    }
+
+    /// `c`: Append a cubic Bézier curve to the current subpath.
+    pub fn cubic_to(
+        &mut self,
+        x1: f32,
+        y1: f32,
+        x2: f32,
+        y2: f32,
+        x3: f32,
+        y3: f32,
+    ) -> &mut Self {
+        let r = self.rounding;
+        self.content.cubic_to(
+            r.round(x1),
+            r.round(y1),
+            r.round(x2),
+            r.round(y2),
+            r.round(x3),
+            r.round(y3),
+        );
+        self
+    }
+
+    /// `re`: Append a rectangle to the current path.
+    pub fn rect(&mut self, x: f32, y: f32, width: f32, height: f32) -> &mut Self {
+        let r = self.rounding;
+        self.content
+            .rect(r.round(x), r.round(y), r.round(width), r.round(height));
+        self
+    }
+
+    /// Round the factors and the translation of a matrix.
+    fn round_matrix(&self, [a, b, c, d, e, f]: [f32; 6]) -> [f32; 6] {
+        let r = self.rounding;
+        [
+            r.round_factor(a),
+            r.round_factor(b),
+            r.round_factor(c),
+            r.round_factor(d),
+            r.round(e),
+            r.round(f),
+        ]
+    }
+}
+
+impl Deref for RoundedContent {
+    type Target = Content;
+
+    fn deref(&self) -> &Self::Target {
+        &self.content
+    }
+}
+
+impl DerefMut for RoundedContent {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.content
+    }
+}
+
 /// A simulated graphics state used to deduplicate graphics state changes and
 /// keep track of the current transformation matrix for link annotations.
 #[derive(Debug, Clone)]
@@ -601,7 +1086,15 @@ impl PageContext<'_, '_> {
         }
     }
 
-    fn set_opacities(&mut self, stroke: Option<&FixedStroke>, fill: Option<&Paint>) {
+    /// Set the opacities and overprinting of the following strokes and
+    /// fills. Whether black fills overprint depends on whether they belong to
+    /// text.
+    fn set_opacities(
+        &mut self,
+        stroke: Option<&FixedStroke>,
+        fill: Option<&Paint>,
+        text: bool,
+    ) {
         let stroke_opacity = stroke
             .map(|stroke| {
                 let color = match &stroke.paint {
@@ -622,13 +1115,53 @@ impl PageContext<'_, '_> {
                 color.alpha().map_or(255, |v| (v * 255.0).round() as u8)
             })
             .unwrap_or(255);
-        self.set_external_graphics_state(&ExtGState { stroke_opacity, fill_opacity });
+        let stroke_overprint =
+            stroke.is_some_and(|stroke| self.overprints(&stroke.paint, true));
+        let fill_overprint = fill.is_some_and(|paint| self.overprints(paint, text));
+        self.set_external_graphics_state(&ExtGState {
+            stroke_opacity,
+            fill_opacity,
+            stroke_overprint,
+            fill_overprint,
+        });
+    }
+
+    /// Whether painting with the paint overprints the content below it
+    /// instead of knocking it out. Only CMYK colors overprint, since the
+    /// effect relies on separate inks. Other colors, including black ones,
+    /// are painted as usual with a warning.
+    fn overprints(&mut self, paint: &Paint, black: bool) -> bool {
+        let Paint::Solid(color) = paint else { return false };
+        let options = self.parent.options;
+        let black = black && options.overprint_black && is_black(*color);
+        if !black && !options.overprint_colors.contains(color) {
+            return false;
+        }
+
+        if matches!(color, Color::Cmyk(_)) {
+            return true;
+        }
+
+        if !self.parent.overprint_ignored {
+            self.parent.overprint_ignored = true;
+            self.parent.warnings.push(
+                SourceDiagnostic::warning(
+                    Span::detached(),
+                    "overprinting only applies to CMYK colors",
+                )
+                .with_hint("use `cmyk` colors for content that should overprint"),
+            );
+        }
+
+        false
     }
 
     pub fn transform(&mut self, transform: Transform) {
         let Transform { sx, ky, kx, sy, tx, ty } = transform;
         self.state.transform = self.state.transform.pre_concat(transform);
-        if self.state.container_transform.is_identity() {
+        // Outside of any group, the frame being written is the container.
+        // Nested hard frames set their container transform explicitly.
+        if self.saves.is_empty() {
             self.state.container_transform = self.state.transform;
         }
         self.content.transform([
@@ -641,9 +1174,8 @@ impl PageContext<'_, '_> {
         ]);
     }
 
-    fn group_transform(&mut self, transform: Transform) {
-        self.state.container_transform =
-            self.state.container_transform.pre_concat(transform);
+    fn set_container_transform(&mut self, transform: Transform) {
+        self.state.container_transform = transform;
     }
 
     fn set_font(&mut self, font: &Font, size: Abs) {
@@ -662,8 +1194,10 @@ impl PageContext<'_, '_> {
     }
 
     fn set_fill(&mut self, fill: &Paint, on_text: bool, transforms: Transforms) {
+        // Gradients and patterns depend on the transform, so they are
+        // always set anew.
         if self.state.fill.as_ref() != Some(fill)
-            || matches!(self.state.fill, Some(Paint::Gradient(_)))
+            || matches!(self.state.fill, Some(Paint::Gradient(_) | Paint::Pattern(_)))
         {
             fill.set_as_fill(self, on_text, transforms);
             self.state.fill = Some(fill.clone());
@@ -690,7 +1224,7 @@ impl PageContext<'_, '_> {
         if self.state.stroke.as_ref() != Some(stroke)
             || matches!(
                 self.state.stroke.as_ref().map(|s| &s.paint),
-                Some(Paint::Gradient(_))
+                Some(Paint::Gradient(_) | Paint::Pattern(_))
             )
         {
             let FixedStroke { paint, thickness, cap, join, dash, miter_limit } = stroke;
@@ -704,17 +1238,15 @@ impl PageContext<'_, '_> {
                 self.content.set_line_join(to_pdf_line_join(*join));
             }
             if self.state.stroke.as_ref().map(|s| &s.dash) != Some(dash) {
-                if let Some(pattern) = dash {
-                    self.content.set_dash_pattern(
-                        pattern.array.iter().map(|l| l.to_f32()),
-                        pattern.phase.to_f32(),
-                    );
-                } else {
-                    self.content.set_dash_pattern([], 0.0);
-                }
+                match dash.as_ref().and_then(to_pdf_dash_pattern) {
+                    Some((array, phase)) => self.content.set_dash_pattern(array, phase),
+                    None => self.content.set_dash_pattern([], 0.0),
+                };
             }
             if self.state.stroke.as_ref().map(|s| &s.miter_limit) != Some(miter_limit) {
-                self.content.set_miter_limit(miter_limit.get() as f32);
+                // PDF requires a miter limit of at least one. Smaller limits
+                // behave like one: All corners are beveled.
+                self.content.set_miter_limit((miter_limit.get() as f32).max(1.0));
             }
             self.state.stroke = Some(stroke.clone());
         }
@@ -741,53 +1273,251 @@ impl PageContext<'_, '_> {
 
 /// Encode a frame into the content stream.
 pub(crate) fn write_frame(ctx: &mut PageContext, frame: &Frame) {
-    for &(pos, ref item) in frame.items() {
-        let x = pos.x.to_f32();
-        let y = pos.y.to_f32();
-        match item {
-            FrameItem::Group(group) => write_group(ctx, pos, group),
-            FrameItem::Text(text) => write_text(ctx, pos, text),
-            FrameItem::Shape(shape, _) => write_shape(ctx, pos, shape),
-            FrameItem::Image(image, size, _) => write_image(ctx, x, y, image, *size),
-            FrameItem::Meta(meta, size) => match meta {
-                Meta::Link(dest) => write_link(ctx, pos, dest, *size),
-                Meta::Elem(_) => {}
-                Meta::Hide => {}
-            },
+    write_frame_at(ctx, Point::zero(), frame);
+}
+
+/// Encode a frame into the content stream, with its origin at `offset` in the
+/// current coordinate system.
+fn write_frame_at(ctx: &mut PageContext, offset: Point, frame: &Frame) {
+    let items = frame.items().as_slice();
+    for (baseline, run) in items.group_by_key(|(pos, item)| match item {
+        FrameItem::Text(_) => Some(pos.y),
+        _ => None,
+    }) {
+        // Text runs on a line are laid out in visual order. Extractors read
+        // them in the order of the content stream, so they are written in
+        // the order of their text in the source instead. Since each run is
+        // positioned on its own, this doesn't change how the line looks.
+        if baseline.is_some() && run.len() > 1 {
+            let keys: Option<Vec<_>> =
+                run.iter().map(|(_, item)| logical_key(item)).collect();
+            if let Some(keys) = keys {
+                let mut order: Vec<_> = (0..run.len()).collect();
+                order.sort_by_key(|&i| keys[i]);
+                for i in order {
+                    write_item(ctx, offset, &run[i]);
+                }
+                continue;
+            }
+        }
+
+        for item in run {
+            write_item(ctx, offset, item);
         }
     }
 }
 
+/// Where the text of a text run starts in its source file, if it stems from
+/// one.
+fn logical_key(item: &FrameItem) -> Option<(FileId, u64, u16)> {
+    let FrameItem::Text(text) = item else { return None };
+    let (span, offset) = text
+        .glyphs
+        .iter()
+        .map(|glyph| glyph.span)
+        .min_by_key(|&(span, offset)| (span.number(), offset))?;
+    Some((span.id()?, span.number(), offset))
+}
+
+/// Encode a frame item into the content stream.
+fn write_item(
+    ctx: &mut PageContext,
+    offset: Point,
+    &(pos, ref item): &(Point, FrameItem),
+) {
+    let pos = pos + offset;
+    let x = pos.x.to_f32();
+    let y = pos.y.to_f32();
+    match item {
+        FrameItem::Group(group) => write_group(ctx, pos, group),
+        FrameItem::Text(text) => write_text(ctx, pos, text),
+        FrameItem::Shape(shape, _) => write_shape(ctx, pos, shape),
+        FrameItem::Image(image, size, _) => write_image(ctx, x, y, image, *size),
+        FrameItem::Meta(meta, size) => match meta {
+            Meta::Link(dest) => write_link(ctx, pos, dest, *size),
+            Meta::Elem(_) => {}
+            Meta::Hide => {}
+        },
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Whether translated groups are folded into their items' positions. Only
+    /// turned off by tests to compare against the unfolded output.
+    static FOLD_TRANSLATIONS: std::cell::Cell<bool> = const { std::cell::Cell::new(true) };
+}
+
+/// Whether translated groups are folded into their items' positions.
+fn folds_translations() -> bool {
+    #[cfg(test)]
+    return FOLD_TRANSLATIONS.with(std::cell::Cell::get);
+    #[cfg(not(test))]
+    true
+}
+
 /// Encode a group into the content stream.
 fn write_group(ctx: &mut PageContext, pos: Point, group: &GroupItem) {
-    let translation = Transform::translate(pos.x, pos.y);
+    // Only the placements directly on the page were measured when looking
+    // for reused frames, so nested groups are always written as usual.
+    let on_page = std::mem::replace(&mut ctx.on_page, false);
+    let reused = (on_page && !ctx.parent.reused_frames.is_empty())
+        .then(|| hash128(&group.frame))
+        .filter(|hash| ctx.parent.reused_frames.contains_key(hash));
+
+    // Groups that neither clip nor are containers don't need their own
+    // graphics state if they are only translated. The translation is then
+    // folded into the positions of their items.
+    let Transform { sx, ky, kx, sy, tx, ty } = group.transform;
+    if folds_translations()
+        && reused.is_none()
+        && group.clip_path.is_none()
+        && !group.frame.kind().is_hard()
+        && sx.is_one()
+        && sy.is_one()
+        && kx.is_zero()
+        && ky.is_zero()
+    {
+        write_frame_at(ctx, pos + Point::new(tx, ty), &group.frame);
+        ctx.on_page = on_page;
+        return;
+    }
+
+    let transform = Transform::translate(pos.x, pos.y).pre_concat(group.transform);
 
     ctx.save_state();
 
     if group.frame.kind().is_hard() {
-        ctx.group_transform(
-            ctx.state
-                .transform
-                .post_concat(ctx.state.container_transform.invert().unwrap())
-                .pre_concat(translation)
-                .pre_concat(group.transform),
-        );
+        // Build the container transform from the item transform instead of
+        // going through the previous container, whose transform might not
+        // be invertible.
+        ctx.set_container_transform(ctx.state.transform.pre_concat(transform));
         ctx.size(group.frame.size());
     }
 
-    ctx.transform(translation.pre_concat(group.transform));
+    if !transform.is_identity() {
+        ctx.transform(transform);
+    }
+
     if let Some(clip_path) = &group.clip_path {
         write_path(ctx, 0.0, 0.0, clip_path);
         ctx.content.clip_nonzero();
         ctx.content.end_path();
     }
 
-    write_frame(ctx, &group.frame);
+    match reused {
+        Some(hash) => write_form(ctx, hash, &group.frame),
+        None => write_frame(ctx, &group.frame),
+    }
+
     ctx.restore_state();
+    ctx.on_page = on_page;
+}
+
+/// Encode a frame that is used on several pages by referring to its shared
+/// form XObject, which is created on first use.
+fn write_form(ctx: &mut PageContext, hash: u128, frame: &Frame) {
+    if !ctx.parent.form_map.contains_key(&hash) {
+        let ReusedFrame { bbox, scale } = ctx.parent.reused_frames[&hash];
+        let mut form_ctx = PageContext::new(ctx.parent, frame.size());
+        form_ctx.form_scale = scale;
+        form_ctx.stats = ctx.stats.is_some().then(PdfPageStats::default);
+        write_frame(&mut form_ctx, frame);
+
+        let form = EncodedForm {
+            uses_opacities: form_ctx.uses_opacities,
+            content: deflate_deferred(form_ctx.content.finish()),
+            bbox,
+            stats: form_ctx.stats,
+        };
+        ctx.parent.form_map.insert(hash, form);
+    }
+
+    let (index, _, form) = ctx.parent.form_map.get_full(&hash).unwrap();
+    ctx.uses_opacities |= form.uses_opacities;
+
+    // The form's items count on every page that shows it.
+    if let (Some(stats), Some(counts)) = (&mut ctx.stats, &form.stats) {
+        stats.glyph_runs += counts.glyph_runs;
+        stats.shapes += counts.shapes;
+        stats.images += counts.images;
+    }
+
+    let name = eco_format!("Fr{index}");
+    ctx.content.x_object(Name(name.as_bytes()));
+    ctx.resources
+        .insert(PageResource::new(ResourceKind::Form, name), index);
 }
 
 /// Encode a text run into the content stream.
 fn write_text(ctx: &mut PageContext, pos: Point, text: &TextItem) {
+    ctx.count(|stats| stats.glyph_runs += 1);
+
+    // Glyph ID 0 is the `.notdef` glyph that fonts use for characters they
+    // don't cover. It typically shows up as an empty box, which is easy to
+    // miss until the document is printed.
+    for glyph in text.glyphs.iter().filter(|glyph| glyph.id == 0) {
+        if let Some(c) = text.text[glyph.range()].chars().next() {
+            ctx.parent.missing_glyph(&text.font, c, glyph.span.0);
+        }
+    }
+
+    // Glyphs are always written in visual order. For right-to-left runs, this
+    // is the reverse of the logical order and the /ToUnicode map (which can
+    // only associate text with individual glyphs) makes viewers extract the
+    // characters in the wrong order. Hyphens inserted by hyphenation, on the
+    // other hand, have no text at all, but would be extracted like a typed
+    // hyphen. In both cases, we provide the logical text explicitly in an
+    // `/ActualText` span.
+    let reordered = text.glyphs.windows(2).any(|w| w[1].range.start < w[0].range.start);
+    // Hyphenation appends its hyphen after the last glyph, while languages
+    // that repeat hyphens at the start of the next line prepend one there.
+    let hyphenated = text.glyphs.last().is_some_and(is_inserted_hyphen);
+    let repeated = text.glyphs.first().is_some_and(is_inserted_hyphen);
+    // Outlined text has no font to extract text from and text whose stroke
+    // is painted below the fill shows its glyphs twice. They need the span,
+    // too. Viewers ignore spans nested into an `/ActualText` span, so there
+    // is a single one for the whole run.
+    let outlined = ctx.parent.options.text_as_paths;
+    let doubled = text.paint_order == PaintOrder::StrokeFill
+        && text
+            .stroke
+            .as_ref()
+            .is_some_and(|stroke| stroke.thickness.to_f32() > 0.0);
+    let wrapped = reordered || hyphenated || repeated || outlined || doubled;
+    if wrapped {
+        // The line breaks at the logical end of the text, also in
+        // right-to-left runs.
+        let mut logical = EcoString::from(text.text.as_str());
+        if hyphenated && ctx.parent.options.soft_hyphens && !logical.ends_with('\u{ad}') {
+            logical.push('\u{ad}');
+        }
+
+        let mut text_span =
+            ctx.content.begin_marked_content_with_properties(Name(b"Span"));
+        let mut actual_text = text_span.properties();
+        actual_text.pair(Name(b"ActualText"), TextStr(&logical));
+        actual_text.finish();
+        text_span.finish();
+    }
+
+    write_text_runs(ctx, pos, text);
+
+    if wrapped {
+        ctx.content.end_marked_content();
+    }
+}
+
+/// Whether a glyph is a hyphen that was inserted by hyphenation. Such glyphs
+/// have no text and no span. Other glyphs with empty text ranges are part of
+/// a cluster that is shaped into multiple glyphs.
+fn is_inserted_hyphen(glyph: &Glyph) -> bool {
+    glyph.range.is_empty() && glyph.span.0.is_detached()
+}
+
+/// Encode the text runs making up a text item into the content stream,
+/// splitting it into runs of normal and color glyphs.
+fn write_text_runs(ctx: &mut PageContext, pos: Point, text: &TextItem) {
     let ttf = text.font.ttf();
     let tables = ttf.tables();
 
@@ -841,12 +1571,9 @@ fn write_normal_text(ctx: &mut PageContext, pos: Point, text: TextItemView) {
     let y = pos.y.to_f32();
 
     *ctx.parent.languages.entry(text.item.lang).or_insert(0) += text.glyph_range.len();
-
-    let glyph_set = ctx.parent.glyph_sets.entry(text.item.font.clone()).or_default();
-    for g in text.glyphs() {
-        let t = text.text();
-        let segment = &t[g.range()];
-        glyph_set.entry(g.id).or_insert_with(|| segment.into());
+    if let Some(region) = text.item.region {
+        let key = (text.item.lang, region);
+        *ctx.parent.regions.entry(key).or_insert(0) += text.glyph_range.len();
     }
 
     let fill_transform = ctx.state.transforms(Size::zero(), pos);
@@ -860,57 +1587,219 @@ fn write_normal_text(ctx: &mut PageContext, pos: Point, text: TextItemView) {
         }
     });
 
+    // When the stroke should be painted below the fill, we show the glyphs
+    // twice: First only stroked and then only filled.
+    let passes: &[TextRenderingMode] = match (stroke, text.item.paint_order) {
+        (None, _) => &[TextRenderingMode::Fill],
+        (Some(_), PaintOrder::FillStroke) => &[TextRenderingMode::FillStroke],
+        (Some(_), PaintOrder::StrokeFill) => {
+            &[TextRenderingMode::Stroke, TextRenderingMode::Fill]
+        }
+    };
+
     if let Some(stroke) = stroke {
-        ctx.set_stroke(stroke, true, fill_transform);
-        ctx.set_text_rendering_mode(TextRenderingMode::FillStroke);
-    } else {
-        ctx.set_text_rendering_mode(TextRenderingMode::Fill);
+        // Strokes may also be relative to the text itself. Their box belongs
+        // to the whole text item, even if this is only a part of it.
+        let before = &text.item.glyphs[..text.glyph_range.start];
+        let start = before.iter().map(|g| g.x_advance).sum::<Em>().at(text.item.size);
+        let (offset, size) = text.item.bbox();
+        let origin = pos + offset - Point::with_x(start);
+        ctx.set_stroke(stroke, true, ctx.state.transforms(size, origin));
+    }
+
+    ctx.set_opacities(text.item.stroke.as_ref(), Some(&text.item.fill), true);
+
+    if ctx.parent.options.text_as_paths {
+        write_glyph_outlines(ctx, pos, &text, passes);
+        return;
+    }
+
+    let glyph_set = ctx.parent.glyph_sets.entry(text.item.font.clone()).or_default();
+    for g in text.glyphs() {
+        let t = text.text();
+        let segment = &t[g.range()];
+        // Glyphs inserted by hyphenation have no text of their own. They
+        // must not decide the text of the same glyph typed by the user.
+        let entry = glyph_set.entry(g.id).or_default();
+        if entry.is_empty() {
+            *entry = segment.into();
+        }
     }
 
     ctx.set_font(&text.item.font, text.item.size);
-    ctx.set_opacities(text.item.stroke.as_ref(), Some(&text.item.fill));
     ctx.content.begin_text();
 
-    // Position the text.
-    ctx.content.set_text_matrix([1.0, 0.0, 0.0, -1.0, x, y]);
+    // With multiple passes, the `/ActualText` span written by `write_text`
+    // keeps the glyphs from being extracted twice.
+    for &mode in passes {
+        ctx.set_text_rendering_mode(mode);
 
-    let mut positioned = ctx.content.show_positioned();
-    let mut items = positioned.items();
+        // Position the text.
+        ctx.content.set_text_matrix([1.0, 0.0, 0.0, -1.0, x, y]);
+        write_glyphs(ctx, &text);
+    }
+
+    ctx.content.end_text();
+}
+
+/// The maximum number of glyphs shown by a single text showing operator.
+const MAX_GLYPHS_PER_SHOW: usize = 4096;
+
+/// Show the glyphs of a text run with their kerning adjustments.
+fn write_glyphs(ctx: &mut PageContext, text: &TextItemView) {
+    let mut glyphs = text.glyphs().peekable();
     let mut adjustment = Em::zero();
     let mut encoded = vec![];
 
-    // Write the glyphs with kerning adjustments.
-    for glyph in text.glyphs() {
-        adjustment += glyph.x_offset;
+    // Very long runs are split into multiple show operators, as some viewers
+    // struggle with huge arrays. The text position carries over from one
+    // operator to the next, so this doesn't change the output visually.
+    while glyphs.peek().is_some() {
+        let mut positioned = ctx.content.show_positioned();
+        let mut items = positioned.items();
+        let mut count = 0;
+        let mut last_cluster = None;
+
+        // Write the glyphs with kerning adjustments. Chunks are only split
+        // at cluster boundaries. Glyph ranges saturate in very long runs, so
+        // a saturated range doesn't mark a cluster.
+        while let Some(glyph) = glyphs.next_if(|glyph| {
+            count < MAX_GLYPHS_PER_SHOW
+                || (last_cluster == Some(glyph.range.start)
+                    && glyph.range.start < u16::MAX)
+        }) {
+            count += 1;
+            last_cluster = Some(glyph.range.start);
+            adjustment += glyph.x_offset;
+
+            if !adjustment.is_zero() {
+                if !encoded.is_empty() {
+                    items.show(Str(&encoded));
+                    encoded.clear();
+                }
+
+                items.adjust(ctx.parent.rounding.round(-adjustment.to_font_units()));
+                adjustment = Em::zero();
+            }
+
+            let cid = crate::font::glyph_cid(&text.item.font, glyph.id);
+            encoded.push((cid >> 8) as u8);
+            encoded.push((cid & 0xff) as u8);
 
-        if !adjustment.is_zero() {
-            if !encoded.is_empty() {
-                items.show(Str(&encoded));
-                encoded.clear();
+            if let Some(advance) = text.item.font.advance(glyph.id) {
+                adjustment += glyph.x_advance - advance;
             }
 
-            items.adjust(-adjustment.to_font_units());
-            adjustment = Em::zero();
+            adjustment -= glyph.x_offset;
         }
 
-        let cid = crate::font::glyph_cid(&text.item.font, glyph.id);
-        encoded.push((cid >> 8) as u8);
-        encoded.push((cid & 0xff) as u8);
+        if !encoded.is_empty() {
+            items.show(Str(&encoded));
+            encoded.clear();
+        }
 
-        if let Some(advance) = text.item.font.advance(glyph.id) {
-            adjustment += glyph.x_advance - advance;
+        items.finish();
+        positioned.finish();
+    }
+}
+
+/// Draw the glyphs of a text run as paths instead of showing them with an
+/// embedded font.
+fn write_glyph_outlines(
+    ctx: &mut PageContext,
+    pos: Point,
+    text: &TextItemView,
+    passes: &[TextRenderingMode],
+) {
+    // Without a font, there is nothing to extract text from. The text stays
+    // searchable through the `/ActualText` span written by `write_text`.
+    let font = &text.item.font;
+    let size = text.item.size;
+    let scale = (size.to_pt() / font.units_per_em()) as f32;
+
+    for &mode in passes {
+        let mut builder = GlyphPathBuilder {
+            content: &mut ctx.content,
+            origin: (0.0, pos.y.to_f32()),
+            scale,
+            last: (0.0, 0.0),
+            empty: true,
+        };
+
+        let mut x = pos.x;
+        for glyph in text.glyphs() {
+            builder.origin.0 = (x + glyph.x_offset.at(size)).to_f32();
+            font.ttf().outline_glyph(GlyphId(glyph.id), &mut builder);
+            x += glyph.x_advance.at(size);
+        }
+
+        if builder.empty {
+            continue;
         }
 
-        adjustment -= glyph.x_offset;
+        match mode {
+            TextRenderingMode::Stroke => ctx.content.stroke(),
+            TextRenderingMode::FillStroke => ctx.content.fill_nonzero_and_stroke(),
+            _ => ctx.content.fill_nonzero(),
+        };
+    }
+}
+
+/// Writes glyph outlines, given in font units, as paths into a content stream.
+struct GlyphPathBuilder<'a> {
+    content: &'a mut RoundedContent,
+    /// Where the origin of the current glyph lies on the page.
+    origin: (f32, f32),
+    /// The scale from font units to points.
+    scale: f32,
+    /// The current point in font units.
+    last: (f32, f32),
+    /// Whether nothing was written yet.
+    empty: bool,
+}
+
+impl GlyphPathBuilder<'_> {
+    /// Map a point from font units into the page's coordinate system.
+    fn map(&self, x: f32, y: f32) -> (f32, f32) {
+        (self.origin.0 + x * self.scale, self.origin.1 - y * self.scale)
     }
+}
 
-    if !encoded.is_empty() {
-        items.show(Str(&encoded));
+impl OutlineBuilder for GlyphPathBuilder<'_> {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let (px, py) = self.map(x, y);
+        self.content.move_to(px, py);
+        self.last = (x, y);
+        self.empty = false;
     }
 
-    items.finish();
-    positioned.finish();
-    ctx.content.end_text();
+    fn line_to(&mut self, x: f32, y: f32) {
+        let (px, py) = self.map(x, y);
+        self.content.line_to(px, py);
+        self.last = (x, y);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        // PDF has no quadratic curves, so we elevate them to cubic ones.
+        let (x0, y0) = self.last;
+        let c1 = self.map(x0 + 2.0 / 3.0 * (x1 - x0), y0 + 2.0 / 3.0 * (y1 - y0));
+        let c2 = self.map(x + 2.0 / 3.0 * (x1 - x), y + 2.0 / 3.0 * (y1 - y));
+        let (px, py) = self.map(x, y);
+        self.content.cubic_to(c1.0, c1.1, c2.0, c2.1, px, py);
+        self.last = (x, y);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (p1x, p1y) = self.map(x1, y1);
+        let (p2x, p2y) = self.map(x2, y2);
+        let (px, py) = self.map(x, y);
+        self.content.cubic_to(p1x, p1y, p2x, p2y, px, py);
+        self.last = (x, y);
+    }
+
+    fn close(&mut self) {
+        self.content.close_path();
+    }
 }
 
 // Encodes a text run made only of color glyphs into the content stream
@@ -946,45 +1835,75 @@ fn write_color_glyphs(ctx: &mut PageContext, pos: Point, text: TextItemView) {
 
         ctx.content.show(Str(&[index]));
 
-        glyph_set
-            .entry(glyph.id)
-            .or_insert_with(|| text.text()[glyph.range()].into());
+        let entry = glyph_set.entry(glyph.id).or_default();
+        if entry.is_empty() {
+            *entry = text.text()[glyph.range()].into();
+        }
     }
     ctx.content.end_text();
 }
 
 /// Encode a geometrical shape into the content stream.
 fn write_shape(ctx: &mut PageContext, pos: Point, shape: &Shape) {
+    ctx.count(|stats| stats.shapes += 1);
+
     let x = pos.x.to_f32();
     let y = pos.y.to_f32();
 
+    // A line width of zero denotes the thinnest line the output device can
+    // draw in PDF. Since such hairlines print inconsistently, we only keep
+    // zero-thickness strokes when asked to.
+    let hairlines = ctx.parent.options.zero_thickness_hairlines;
     let stroke = shape.stroke.as_ref().and_then(|stroke| {
-        if stroke.thickness.to_f32() > 0.0 {
+        let thickness = stroke.thickness.to_f32();
+        if (thickness > 0.0 || (hairlines && thickness == 0.0))
+            && is_visible(&stroke.paint)
+        {
             Some(stroke)
         } else {
             None
         }
     });
 
-    if shape.fill.is_none() && stroke.is_none() {
+    // Shapes that can't leave any mark are skipped entirely.
+    let fill = shape.fill.as_ref().filter(|fill| is_visible(fill));
+    if fill.is_none() && stroke.is_none() {
         return;
     }
 
-    if let Some(fill) = &shape.fill {
+    if let Some(fill) = fill {
         ctx.set_fill(fill, false, ctx.state.transforms(shape.geometry.bbox_size(), pos));
     }
 
     if let Some(stroke) = stroke {
-        ctx.set_stroke(
-            stroke,
-            false,
-            ctx.state.transforms(shape.geometry.bbox_size(), pos),
-        );
+        // Lines and straight paths have no area, so their paint may be
+        // relative to the area that their stroke covers instead.
+        let (offset, size) = shape.geometry.stroke_paint_box(stroke.thickness);
+        let origin = pos + offset;
+        ctx.set_stroke(stroke, false, ctx.state.transforms(size, origin));
     }
 
-    ctx.set_opacities(stroke, shape.fill.as_ref());
+    ctx.set_opacities(stroke, fill, false);
+    write_geometry(ctx, x, y, &shape.geometry);
 
-    match shape.geometry {
+    match (fill, stroke) {
+        (None, None) => unreachable!(),
+        (Some(_), None) => ctx.content.fill_nonzero(),
+        (None, Some(_)) => ctx.content.stroke(),
+        // PDF can only fill and stroke in one go with the fill first, so we
+        // need to write the geometry twice for the opposite order.
+        (Some(_), Some(_)) if shape.paint_order == PaintOrder::StrokeFill => {
+            ctx.content.stroke();
+            write_geometry(ctx, x, y, &shape.geometry);
+            ctx.content.fill_nonzero()
+        }
+        (Some(_), Some(_)) => ctx.content.fill_nonzero_and_stroke(),
+    };
+}
+
+/// Encode a shape's geometry into the content stream.
+fn write_geometry(ctx: &mut PageContext, x: f32, y: f32, geometry: &Geometry) {
+    match *geometry {
         Geometry::Line(target) => {
             let dx = target.x.to_f32();
             let dy = target.y.to_f32();
@@ -995,58 +1914,144 @@ fn write_shape(ctx: &mut PageContext, pos: Point, shape: &Shape) {
             let w = size.x.to_f32();
             let h = size.y.to_f32();
             if w.abs() > f32::EPSILON && h.abs() > f32::EPSILON {
-                ctx.content.rect(x, y, w, h);
+                // Rectangles with negative sizes would be drawn in the
+                // opposite direction, which changes where dashes fall. We
+                // thus always draw from the minimum corner.
+                ctx.content.rect(x.min(x + w), y.min(y + h), w.abs(), h.abs());
             }
         }
         Geometry::Path(ref path) => {
             write_path(ctx, x, y, path);
         }
     }
-
-    match (&shape.fill, stroke) {
-        (None, None) => unreachable!(),
-        (Some(_), None) => ctx.content.fill_nonzero(),
-        (None, Some(_)) => ctx.content.stroke(),
-        (Some(_), Some(_)) => ctx.content.fill_nonzero_and_stroke(),
-    };
 }
 
 /// Encode a bezier path into the content stream.
 fn write_path(ctx: &mut PageContext, x: f32, y: f32, path: &Path) {
+    let px = |v: Abs| x + v.to_f32();
+    let py = |v: Abs| y + v.to_f32();
     for elem in &path.0 {
         match elem {
-            PathItem::MoveTo(p) => {
-                ctx.content.move_to(x + p.x.to_f32(), y + p.y.to_f32())
-            }
-            PathItem::LineTo(p) => {
-                ctx.content.line_to(x + p.x.to_f32(), y + p.y.to_f32())
-            }
+            PathItem::MoveTo(p) => ctx.content.move_to(px(p.x), py(p.y)),
+            PathItem::LineTo(p) => ctx.content.line_to(px(p.x), py(p.y)),
             PathItem::CubicTo(p1, p2, p3) => ctx.content.cubic_to(
-                x + p1.x.to_f32(),
-                y + p1.y.to_f32(),
-                x + p2.x.to_f32(),
-                y + p2.y.to_f32(),
-                x + p3.x.to_f32(),
-                y + p3.y.to_f32(),
+                px(p1.x),
+                py(p1.y),
+                px(p2.x),
+                py(p2.y),
+                px(p3.x),
+                py(p3.y),
             ),
             PathItem::ClosePath => ctx.content.close_path(),
         };
     }
 }
 
+/// Whether a paint can leave any mark. Gradients and patterns are always
+/// considered visible.
+fn is_visible(paint: &Paint) -> bool {
+    match paint {
+        Paint::Solid(color) => color.alpha().map_or(true, |alpha| alpha > 0.0),
+        Paint::Gradient(_) | Paint::Pattern(_) => true,
+    }
+}
+
+/// Whether a color is black, i.e. has full black ink in CMYK or no light in
+/// any other color space.
+fn is_black(color: Color) -> bool {
+    match color {
+        Color::Cmyk(cmyk) => cmyk.k >= 1.0,
+        _ => color.to_space(ColorSpace::Srgb).to_vec4()[..3]
+            .iter()
+            .all(|&v| v <= 0.0),
+    }
+}
+
 /// Encode a vector or raster image into the content stream.
 fn write_image(ctx: &mut PageContext, x: f32, y: f32, image: &Image, size: Size) {
+    ctx.count(|stats| stats.images += 1);
+
+    // The size of the image on the page, including the scale of the
+    // transforms it is placed in.
+    let ts = ctx.form_scale.pre_concat(ctx.state.transform);
+    let placed = (
+        size.x.to_pt() * ts.sx.get().hypot(ts.ky.get()),
+        size.y.to_pt() * ts.kx.get().hypot(ts.sy.get()),
+    );
+
+    let options = ctx.parent.options;
+    let settings = match image.kind() {
+        // If the image has more pixels than needed at its final size, we
+        // embed a downsampled version instead.
+        ImageKind::Raster(raster) => ImageSettings {
+            downsampled: options.max_image_dpi.and_then(|max_dpi| {
+                downsampled_size((raster.width(), raster.height()), placed, max_dpi)
+            }),
+            ..ImageSettings::default()
+        },
+        // Parts of an SVG that must be rasterized are rendered with enough
+        // pixels for the requested resolution at the placed size.
+        ImageKind::Svg(svg) => ImageSettings {
+            svg_raster_scale: options
+                .svg_raster_dpi
+                .map(|dpi| {
+                    let scale_x = placed.0 / svg.width();
+                    let scale_y = placed.1 / svg.height();
+                    // One inch has 72 points.
+                    scale_x.max(scale_y) * dpi / 72.0
+                })
+                .filter(|scale| scale.is_finite() && *scale > 0.0)
+                .or(options.svg_raster_scale)
+                .map(Scalar::new),
+            ..ImageSettings::default()
+        },
+    };
+
+    // Images with an alpha channel are drawn with a soft mask and SVGs may
+    // contain transparency of their own.
+    ctx.uses_opacities |= match image.kind() {
+        ImageKind::Raster(raster) => raster.dynamic().color().has_alpha(),
+        ImageKind::Svg(_) => true,
+    };
+
+    // The image is encoded once, with enough pixels for all its placements.
     let index = ctx.parent.image_map.insert(image.clone());
     ctx.parent
-        .image_deferred_map
+        .image_settings
         .entry(index)
-        .or_insert_with(|| deferred_image(image.clone()));
+        .and_modify(|merged| *merged = merged.merge(settings))
+        .or_insert(settings);
 
     let name = eco_format!("Im{index}");
     let w = size.x.to_f32();
     let h = size.y.to_f32();
+    let rotation = match image.kind() {
+        ImageKind::Raster(raster) => embedded_rotation(raster),
+        ImageKind::Svg(_) => None,
+    };
+
+    // An image mask paints with the fill color. It is set outside of the
+    // saved graphics state below to keep the tracked state in sync.
+    if let Some(fill) = image.fill() {
+        let paint = Paint::Solid(fill);
+        ctx.set_fill(&paint, false, ctx.state.transforms(size, Point::zero()));
+        ctx.set_opacities(None, Some(&paint), false);
+    }
+
+    // Map the unit square onto the image's rectangle. If the image data is
+    // still stored in its EXIF orientation, we rotate and flip it here so
+    // that the rectangle refers to the displayed orientation.
     ctx.content.save_state();
-    ctx.content.transform([w, 0.0, 0.0, -h, x, y + h]);
+    ctx.content.transform(match rotation {
+        Some(2) => [-w, 0.0, 0.0, -h, x + w, y + h],
+        Some(3) => [-w, 0.0, 0.0, h, x + w, y],
+        Some(4) => [w, 0.0, 0.0, h, x, y],
+        Some(5) => [0.0, h, -w, 0.0, x + w, y],
+        Some(6) => [0.0, h, w, 0.0, x, y],
+        Some(7) => [0.0, -h, w, 0.0, x, y + h],
+        Some(8) => [0.0, -h, -w, 0.0, x + w, y + h],
+        _ => [w, 0.0, 0.0, -h, x, y + h],
+    });
 
     if let Some(alt) = image.alt() {
         let mut image_span =
@@ -1097,6 +2102,26 @@ fn write_link(ctx: &mut PageContext, pos: Point, dest: &Destination, size: Size)
     ctx.links.push((dest.clone(), rect));
 }
 
+/// Convert a dash pattern into a PDF dash array and phase.
+///
+/// Returns `None` for patterns that PDF can't represent and that the raster
+/// exporter draws as solid lines: Those with negative lengths or without any
+/// non-zero length. Zero lengths are kept, so that dots with round or square
+/// caps remain visible.
+fn to_pdf_dash_pattern(pattern: &DashPattern<Abs, Abs>) -> Option<(Vec<f32>, f32)> {
+    let array: Vec<f32> = pattern.array.iter().map(|l| l.to_f32()).collect();
+    let period: f32 = array.iter().sum();
+    if array.iter().any(|&l| l < 0.0) || !(period > 0.0 && period.is_finite()) {
+        return None;
+    }
+
+    // PDF repeats an array with an odd number of elements, so that the
+    // period is twice its sum. The phase must not be negative.
+    let period = if array.len() % 2 == 1 { 2.0 * period } else { period };
+    let phase = pattern.phase.to_f32().rem_euclid(period);
+    Some((array, phase))
+}
+
 fn to_pdf_line_cap(cap: LineCap) -> LineCapStyle {
     match cap {
         LineCap::Butt => LineCapStyle::ButtCap,
@@ -1122,3 +2147,814 @@ fn to_pdf_numbering_style(style: PdfPageLabelStyle) -> NumberingStyle {
         PdfPageLabelStyle::UpperAlpha => NumberingStyle::UpperAlpha,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use typst::foundations::Bytes;
+    use typst::layout::{Abs, PageRanges, Point, Size};
+    use typst::visualize::Color;
+
+    use crate::tests::{
+        assert_renders_like, mismatch, pixel, render_thumbnail, Object, TestPdf,
+        TestWorld,
+    };
+    use crate::{
+        AttachmentIcon, LinkVisibility, PdfEmbeddedFile, PdfFileAttachment, PdfOptions,
+        PdfThumbnails, PdfTransition, TransitionStyle,
+    };
+
+    #[test]
+    fn test_container_transforms() {
+        // Gradients relative to their parent must follow the closest
+        // container, whether it is directly on the page, nested in another
+        // one or has an identity transform of its own.
+        let fill = "gradient.linear(red, blue, relative: \"parent\")";
+        let cell = format!("rect(width: 30pt, height: 20pt, fill: {fill})");
+        for body in [
+            format!("#box(width: 80pt, height: 40pt, {cell})"),
+            format!("#rotate(30deg, box(width: 80pt, height: 40pt, {cell}))"),
+            format!(
+                "#box(width: 90pt, height: 60pt, inset: 5pt, \
+                 scale(x: 80%, box(width: 60pt, height: 40pt, inset: 4pt, \
+                 rotate(-15deg, box(width: 50pt, height: 30pt, {cell})))))"
+            ),
+        ] {
+            let world = TestWorld::new(&format!(
+                "#set page(width: 120pt, height: 120pt, margin: 10pt)\n{body}"
+            ));
+            assert_renders_like(&world, &PdfOptions::default());
+        }
+
+        // A container below one that is scaled to nothing can't be derived
+        // from its parent, but is still written.
+        let world = TestWorld::new(&format!(
+            "#rotate(30deg, scale(x: 0%, box(width: 40pt, height: 40pt, box({cell}))))"
+        ));
+        assert_eq!(world.export(&PdfOptions::default()).pages().len(), 1);
+    }
+
+    #[test]
+    fn test_isolated_page_groups() {
+        let world = TestWorld::new(
+            "#set page(width: 100pt, height: 100pt)\n\
+             #rect(fill: blue)\n\
+             #pagebreak()\n\
+             #rect(fill: blue)\n\
+             #place(top + left, dx: 5pt, dy: 5pt, rect(fill: red.transparentize(50%)))\n\
+             #pagebreak()\n\
+             #image(\"square.svg\", width: 10pt)",
+        )
+        .with_file(
+            "square.svg",
+            Bytes::from_static(
+                b"<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"10\" height=\"10\">\
+                  <rect width=\"10\" height=\"10\" fill=\"red\"/></svg>",
+            ),
+        );
+        let pdf = world.export(&PdfOptions::default());
+
+        // Opaque pages stay without a group.
+        assert!(pdf.page(0).get("Group").is_none());
+
+        // Pages with translucent shapes or SVGs blend in an isolated group
+        // with the document's sRGB profile.
+        for i in [1, 2] {
+            let group = pdf.dict(pdf.page(i), "Group");
+            assert_eq!(group.name("S"), Some("Transparency"));
+            assert!(group.is_true("I"));
+            let space = pdf.get(group.get("CS").unwrap()).as_array().unwrap();
+            assert_eq!(space[0].as_name(), Some("ICCBased"));
+            assert_eq!(pdf.get(&space[1]).as_stream().unwrap().dict.int("N"), Some(3));
+        }
+
+        // Composited as a whole, the translucent page looks like it does in
+        // typst-render.
+        let document = world.compile();
+        let reference = typst_render::render(&document.pages[1].frame, 2.0, Color::WHITE);
+        assert!(mismatch(&pdf.render(1, 2.0), &reference, 64) < 0.01);
+    }
+
+    #[test]
+    fn test_skip_invisible_shapes() {
+        let shape = |paint: &str| {
+            TestWorld::new(&format!(
+                "#set page(width: 100pt, height: 100pt)\n\
+                 #rect(fill: {paint}, stroke: {paint})"
+            ))
+            .export(&PdfOptions::default())
+        };
+
+        // A fully transparent shape adds nothing to the empty page.
+        let empty = TestWorld::new("#set page(width: 100pt, height: 100pt)")
+            .export(&PdfOptions::default());
+        let invisible = shape("red.transparentize(100%)");
+        assert_eq!(invisible.page_content(0), empty.page_content(0));
+        assert!(invisible.named("Type", "ExtGState").is_empty());
+
+        // An almost transparent one is still painted.
+        let faint = shape("red.transparentize(99%)");
+        assert_eq!(faint.ops_named("B").len(), 1);
+        let states = faint.named("Type", "ExtGState");
+        assert_eq!(states.len(), 1);
+        let alpha = (states[0].f64("ca"), states[0].f64("CA"));
+        assert!(matches!(alpha, (Some(a), Some(b)) if a > 0.0 && a == b));
+    }
+
+    #[test]
+    fn test_dash_patterns() {
+        for thickness in [0.5, 1.0, 3.0] {
+            for (dash, cap, array, phase) in [
+                // Dots are as long as the stroke is thick.
+                ("\"dotted\"", "butt", vec![thickness, 2.0], 0.0),
+                ("\"loosely-dashed\"", "butt", vec![3.0, 6.0], 0.0),
+                ("\"dash-dotted\"", "square", vec![3.0, 2.0, thickness, 2.0], 0.0),
+                // Zero-length dashes stay, so that round caps draw dots.
+                ("(array: (0pt, 4pt), phase: 1pt)", "round", vec![0.0, 4.0], 1.0),
+                // An odd array is repeated, so that its period is twice its
+                // sum, and a negative phase is moved into the period.
+                (
+                    "(array: (3pt, 1pt, 1pt), phase: -2pt)",
+                    "butt",
+                    vec![3.0, 1.0, 1.0],
+                    8.0,
+                ),
+            ] {
+                let world = TestWorld::new(&format!(
+                    "#set page(width: 100pt, height: 20pt, margin: 10pt)\n\
+                     #line(length: 80pt, stroke: (thickness: {thickness}pt, \
+                     dash: {dash}, cap: \"{cap}\"))"
+                ));
+                let pdf = world.export(&PdfOptions::default());
+                let ops = pdf.ops_named("d");
+                assert_eq!(ops.len(), 1, "{dash}");
+                assert_eq!(ops[0].operands[0].as_numbers(), Some(array), "{dash}");
+                assert_eq!(ops[0].operands[1].as_f64(), Some(phase), "{dash}");
+                assert_renders_like(&world, &PdfOptions::default());
+            }
+        }
+    }
+
+    #[test]
+    fn test_negative_rects() {
+        let rect = |dx: f64, dy: f64, width: f64, height: f64| {
+            TestWorld::new(&format!(
+                "#set page(width: 100pt, height: 100pt, margin: 0pt)\n\
+                 #place(dx: {dx}pt, dy: {dy}pt, rect(width: {width}pt, \
+                 height: {height}pt, stroke: (thickness: 3pt, \
+                 dash: (array: (12pt, 4pt, 2pt, 4pt), phase: 5pt))))"
+            ))
+            .export(&PdfOptions::default())
+        };
+
+        // The same rectangle from 20pt to 80pt and from 30pt to 70pt, given
+        // by each of its corners. Its dashes run the same way whichever
+        // corner it starts at.
+        let expected = rect(20.0, 30.0, 60.0, 40.0);
+        let re = expected.ops_named("re");
+        assert_eq!(re.len(), 1);
+        assert_eq!(re[0].numbers(), [20.0, 30.0, 60.0, 40.0]);
+        let pixmap = expected.render(0, 2.0);
+        for (dx, dy, width, height) in [
+            (80.0, 30.0, -60.0, 40.0),
+            (20.0, 70.0, 60.0, -40.0),
+            (80.0, 70.0, -60.0, -40.0),
+        ] {
+            let pdf = rect(dx, dy, width, height);
+            assert_eq!(pdf.ops(), expected.ops(), "{width} {height}");
+            assert_eq!(mismatch(&pdf.render(0, 2.0), &pixmap, 0), 0.0);
+        }
+    }
+
+    #[test]
+    fn test_hairlines() {
+        let hairlines = PdfOptions {
+            zero_thickness_hairlines: true,
+            ..Default::default()
+        };
+        let rect = |thickness: &str, options: &PdfOptions| {
+            TestWorld::new(&format!(
+                "#set page(width: 100pt, height: 100pt)\n\
+                 #rect(stroke: {thickness})"
+            ))
+            .export(options)
+        };
+        let widths = |pdf: &TestPdf| {
+            pdf.ops_named("w")
+                .iter()
+                .map(|op| op.numbers()[0])
+                .collect::<Vec<_>>()
+        };
+
+        // When asked to, a rectangle with a zero-thickness stroke is stroked
+        // as a hairline.
+        let pdf = rect("0pt", &hairlines);
+        assert_eq!(widths(&pdf), [0.0]);
+        let content = String::from_utf8(pdf.page_content(0)).unwrap();
+        assert!(content.lines().any(|line| line == "0 w"));
+        assert_eq!(pdf.ops_named("S").len(), 1);
+
+        // By default, it is skipped.
+        let pdf = rect("0pt", &PdfOptions::default());
+        assert!(widths(&pdf).is_empty());
+        assert!(pdf.ops_named("S").is_empty());
+
+        // Negative thicknesses are always skipped, while positive ones are
+        // stroked either way.
+        for options in [PdfOptions::default(), hairlines] {
+            let pdf = rect("-1pt", &options);
+            assert!(widths(&pdf).is_empty());
+            assert!(pdf.ops_named("S").is_empty());
+            assert_eq!(widths(&rect("1pt", &options)), [1.0]);
+        }
+    }
+
+    #[test]
+    fn test_stroke_parameters() {
+        // The value of the single operator that sets a stroke parameter.
+        let parameter = |pdf: &TestPdf, operator| {
+            let ops = pdf.ops_named(operator);
+            assert_eq!(ops.len(), 1, "{operator}");
+            ops[0].numbers()[0]
+        };
+
+        let caps = [("butt", 0.0), ("round", 1.0), ("square", 2.0)];
+        let joins = [("miter", 0.0), ("round", 1.0), ("bevel", 2.0)];
+        // The chevron's tip needs a miter limit of about five, so that it is
+        // beveled by default and by the small limit, but not by the large one.
+        let limits = [("auto", 4.0), ("1.5", 1.5), ("10", 10.0)];
+
+        for (cap, cap_style) in caps {
+            for (join, join_style) in joins {
+                for (limit, miter_limit) in limits {
+                    let stroke = format!(
+                        "(thickness: 4pt, cap: \"{cap}\", join: \"{join}\", \
+                         miter-limit: {limit})"
+                    );
+
+                    // An open chevron, whose ends show the caps.
+                    let world = TestWorld::new(&format!(
+                        "#set page(width: 100pt, height: 40pt, margin: 10pt)\n\
+                         #path(stroke: {stroke}, (0pt, 0pt), (50pt, 10pt), (0pt, 20pt))"
+                    ));
+                    let pdf = world.export(&PdfOptions::default());
+                    assert_eq!(parameter(&pdf, "J"), cap_style);
+                    assert_eq!(parameter(&pdf, "j"), join_style);
+                    assert_eq!(parameter(&pdf, "M"), miter_limit);
+                    assert_renders_like(&world, &PdfOptions::default());
+
+                    // Only a miter join within the limit has a spike that
+                    // reaches five points beyond the tip.
+                    let spiked = join == "miter" && miter_limit > 5.1;
+                    let [r, ..] = pixel(&pdf.render(0, 1.0), 65, 20);
+                    assert_eq!(r < 128, spiked, "{stroke}");
+
+                    // Stroked text gets the same parameters.
+                    let world = TestWorld::new(&format!(
+                        "#set page(width: auto, height: auto, margin: 10pt)\n\
+                         #text(size: 40pt, stroke: {stroke})[VAW]"
+                    ));
+                    let pdf = world.export(&PdfOptions::default());
+                    assert_eq!(parameter(&pdf, "J"), cap_style);
+                    assert_eq!(parameter(&pdf, "j"), join_style);
+                    assert_eq!(parameter(&pdf, "M"), miter_limit);
+                    assert_renders_like(&world, &PdfOptions::default());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_text_as_paths() {
+        let world = TestWorld::new("#set page(width: auto, height: auto)\nHello");
+        let embedded = world.export(&PdfOptions::default());
+        let paths =
+            world.export(&PdfOptions { text_as_paths: true, ..Default::default() });
+
+        assert!(!embedded.named("Type", "FontDescriptor").is_empty());
+        assert!(!embedded.ops_named("TJ").is_empty());
+
+        // No font may be embedded and no text be shown. Instead, the glyphs
+        // are filled once as a whole and remain extractable.
+        assert!(paths.named("Type", "FontDescriptor").is_empty());
+        assert!(paths.named("Type", "Font").is_empty());
+        assert!(paths.ops_named("Tj").is_empty() && paths.ops_named("TJ").is_empty());
+        assert_eq!(paths.ops_named("f").len(), 1);
+        assert_eq!(actual_texts(&paths), ["Hello"]);
+
+        // The page keeps its size.
+        assert_eq!(embedded.page(0).get("MediaBox"), paths.page(0).get("MediaBox"));
+    }
+
+    #[test]
+    fn test_thumbnails() {
+        let world = TestWorld::new("#set page(paper: \"a4\")\nA\n#pagebreak()\nB");
+        assert_eq!(world.export(&PdfOptions::default()).count("Thumb"), 0);
+
+        let thumbnails = Some(PdfThumbnails {
+            max_size: 64,
+            quality: 50,
+            render: render_thumbnail,
+        });
+        let pdf = world.export(&PdfOptions { thumbnails, ..Default::default() });
+
+        // One JPEG per page that fits the A4 page into 64 pixels.
+        let thumbs = pdf.values("Thumb");
+        assert_eq!(thumbs.len(), 2);
+        for thumb in thumbs {
+            let thumb = &thumb.as_stream().unwrap().dict;
+            assert_eq!((thumb.int("Width"), thumb.int("Height")), (Some(45), Some(64)));
+            assert_eq!(thumb.name("Filter"), Some("DCTDecode"));
+        }
+    }
+
+    #[test]
+    fn test_drop_outside_annotations() {
+        let world = TestWorld::new(
+            "#set page(width: 100pt, height: 100pt)\n\
+             #place(dx: -80pt, link(\"https://typst.app\")[A])\n\
+             #link(\"https://typst.app\")[B]",
+        );
+        let attachment = |x: f64| PdfFileAttachment {
+            page: 0,
+            pos: Point::new(Abs::pt(x), Abs::pt(10.0)),
+            size: Size::splat(Abs::pt(20.0)),
+            icon: AttachmentIcon::default(),
+            file: PdfEmbeddedFile {
+                name: "data.txt".into(),
+                data: Bytes::from_static(b"data"),
+                description: None,
+                mime_type: None,
+                modified: None,
+                relationship: None,
+            },
+        };
+        let file_attachments = vec![attachment(-50.0), attachment(10.0)];
+
+        let unclipped = world.export(&PdfOptions {
+            file_attachments: file_attachments.clone(),
+            ..Default::default()
+        });
+        assert_eq!(unclipped.named("Subtype", "Link").len(), 2);
+        assert_eq!(unclipped.named("Subtype", "FileAttachment").len(), 2);
+        assert!(unclipped.warnings.is_empty());
+
+        // Only the annotations that are on the page survive.
+        let clipped = world.export(&PdfOptions {
+            clip_to_page: true,
+            file_attachments,
+            ..Default::default()
+        });
+        assert_eq!(clipped.named("Subtype", "Link").len(), 1);
+        assert_eq!(clipped.named("Subtype", "FileAttachment").len(), 1);
+        assert_eq!(clipped.warnings.len(), 1);
+        assert!(clipped.warnings[0].message.contains("dropped 2 annotations"));
+    }
+
+    #[test]
+    fn test_clip_to_page() {
+        let world = TestWorld::new(
+            "#set page(width: 100pt, height: 100pt, margin: 0pt)\n\
+             #place(dx: -50pt, rect(width: 100pt, height: 100pt, fill: red))",
+        );
+        let unclipped = world.export(&PdfOptions::default());
+        assert!(unclipped.ops_named("W").is_empty());
+
+        // The page's rect is made the clip path before anything is drawn.
+        let clipped =
+            world.export(&PdfOptions { clip_to_page: true, ..Default::default() });
+        let ops = clipped.page_ops(0);
+        let start = ops.iter().position(|op| op.operator == "re").unwrap();
+        let operators: Vec<_> =
+            ops[start..start + 3].iter().map(|op| op.operator.as_str()).collect();
+        assert_eq!(operators, ["re", "W", "n"]);
+        assert_eq!(ops[start].numbers(), [0.0, 0.0, 100.0, 100.0]);
+        assert!(ops[..start]
+            .iter()
+            .all(|op| op.operator == "cm" || op.operator == "q"));
+
+        // Only the half of the rect that is on the page remains visible.
+        let pixmap = clipped.render(0, 1.0);
+        assert_eq!(pixel(&pixmap, 25, 50), [255, 65, 54, 255]);
+        assert_eq!(pixel(&pixmap, 75, 50), [255, 255, 255, 255]);
+        assert_eq!(mismatch(&pixmap, &unclipped.render(0, 1.0), 0), 0.0);
+    }
+
+    #[test]
+    fn test_fold_translations() {
+        let world = TestWorld::new(
+            "#set page(width: 300pt, height: auto)\n\
+             #set par(justify: true)\n\
+             = Heading\n\
+             #for i in range(20) [\n\
+               Text $x_#i^2 + sqrt(y)$ and #box(circle(radius: 3pt)) more.\n\
+               - Item #i\n\
+             ]\n\
+             #table(columns: 3, ..range(9).map(str))\n\
+             #rotate(20deg, [Rotated])\n\
+             #box(clip: true, width: 20pt, lorem(5))",
+        );
+        let folded = world.export(&PdfOptions::default());
+        super::FOLD_TRANSLATIONS.with(|fold| fold.set(false));
+        let unfolded = world.export(&PdfOptions::default());
+        super::FOLD_TRANSLATIONS.with(|fold| fold.set(true));
+
+        // Folding only removes graphics states that merely translate.
+        assert!(folded.ops_named("q").len() < unfolded.ops_named("q").len());
+
+        // Math, boxes and list markers each end up in a group of their own,
+        // so the page's content shrinks by about 7%. The table's grid gets
+        // longer coordinates instead, but that costs less.
+        let (a, b) = (folded.content_size(), unfolded.content_size());
+        assert!(a * 100 < b * 95, "{a} is not 5% less than {b}");
+
+        // The result looks the same.
+        assert!(mismatch(&folded.render(0, 2.0), &unfolded.render(0, 2.0), 8) < 1e-4);
+        assert_renders_like(&world, &PdfOptions::default());
+    }
+
+    #[test]
+    fn test_reused_frames() {
+        let header = "#set page(width: 100pt, height: 100pt, header: {\n\
+             for i in range(8) { place(dx: i * 10pt, circle(radius: 2pt)) }\n\
+             })\n";
+
+        // The header is written once and drawn on each page.
+        let world = TestWorld::new(&format!("{header}A #pagebreak() B #pagebreak() C"));
+        let pdf = world.export(&PdfOptions::default());
+        assert_eq!(pdf.forms().len(), 1);
+        for i in 0..3 {
+            assert_eq!(
+                pdf.page_ops(i).iter().filter(|op| op.operator == "Do").count(),
+                1
+            );
+        }
+
+        // A frame that is only placed once is not shared.
+        let world = TestWorld::new(&format!("{header}A"));
+        let pdf = world.export(&PdfOptions::default());
+        assert_eq!(pdf.forms().len(), 0);
+    }
+
+    #[test]
+    fn test_scaled_reused_frames() {
+        // A logo with an image that is placed at its natural size on the
+        // first page and at twice the size on the second one.
+        let logo = image::RgbImage::from_fn(100, 50, |x, y| {
+            image::Rgb([(x * 2) as u8, (y * 4) as u8, 128])
+        });
+        let mut png = std::io::Cursor::new(vec![]);
+        logo.write_to(&mut png, image::ImageFormat::Png).unwrap();
+        let world = TestWorld::new(
+            "#let logo = block(width: 20pt, height: 10pt, {\n\
+               image(\"/logo.png\", width: 20pt)\n\
+               for i in range(6) { place(dx: i * 3pt, circle(radius: 1pt)) }\n\
+             })\n\
+             #set page(width: 60pt, height: 40pt, margin: 0pt)\n\
+             #page(background: logo)[]\n\
+             #page(background: scale(200%, origin: top + left, logo))[]",
+        )
+        .with_file("/logo.png", png.into_inner());
+
+        // The logo is written once, with an image that is sharp at the
+        // larger placement: Forty points at 72 DPI are forty pixels.
+        let options = PdfOptions { max_image_dpi: Some(72.0), ..Default::default() };
+        let pdf = world.export(&options);
+        assert_eq!(pdf.forms().len(), 1);
+        let images = pdf.subtype("Image");
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].dict.int("Width"), Some(40));
+
+        // The form's own content is not scaled, so both pages look right.
+        let document = world.compile();
+        for i in 0..2 {
+            let reference =
+                typst_render::render(&document.pages[i].frame, 2.0, Color::WHITE);
+            assert!(mismatch(&pdf.render(i, 2.0), &reference, 64) < 0.01, "page {i}");
+        }
+    }
+
+    #[test]
+    fn test_page_transitions() {
+        let world = TestWorld::new("A #pagebreak() B");
+        let second = NonZeroUsize::new(2);
+        let transition = PdfTransition {
+            style: TransitionStyle::Wipe,
+            duration: 0.5,
+            advance: Some(3.0),
+        };
+        let page_transitions = vec![(PageRanges::new(vec![second..=second]), transition)];
+        let pdf = world.export(&PdfOptions { page_transitions, ..Default::default() });
+
+        // Only the second page gets a transition.
+        assert!(!pdf.page(0).contains("Trans") && !pdf.page(0).contains("Dur"));
+        let trans = pdf.dict(pdf.page(1), "Trans");
+        assert_eq!(trans.name("S"), Some("Wipe"));
+        assert_eq!(trans.f64("D"), Some(0.5));
+        assert_eq!(pdf.page(1).f64("Dur"), Some(3.0));
+    }
+
+    #[test]
+    fn test_file_attachment_round_trip() {
+        let world = TestWorld::new("Chart");
+        let data = b"x,y\n1,2\n";
+        let attachment = PdfFileAttachment {
+            page: 0,
+            pos: Point::splat(Abs::pt(10.0)),
+            size: Size::splat(Abs::pt(20.0)),
+            icon: AttachmentIcon::Paperclip,
+            file: PdfEmbeddedFile {
+                name: "chart.csv".into(),
+                data: Bytes::from_static(data),
+                description: Some("The chart's data".into()),
+                mime_type: Some("text/csv".into()),
+                modified: None,
+                relationship: None,
+            },
+        };
+        let file_attachments = vec![attachment];
+        let pdf = world.export(&PdfOptions { file_attachments, ..Default::default() });
+
+        let annotations = pdf.named("Subtype", "FileAttachment");
+        assert_eq!(annotations.len(), 1);
+        let annotation = annotations[0];
+        assert_eq!(annotation.name("Name"), Some("Paperclip"));
+        let contents = annotation.get("Contents").and_then(Object::as_text);
+        assert_eq!(contents.as_deref(), Some("The chart's data"));
+
+        // Follow the annotation to the embedded file stream.
+        let spec = pdf.dict(annotation, "FS");
+        assert_eq!(spec.get("F").and_then(Object::as_text).as_deref(), Some("chart.csv"));
+        let file = pdf.get(pdf.dict(spec, "EF").get("F").unwrap()).as_stream().unwrap();
+        assert_eq!(file.dict.name("Type"), Some("EmbeddedFile"));
+        assert_eq!(file.dict.name("Subtype"), Some("text/csv"));
+        assert_eq!(file.data, data);
+    }
+
+    #[test]
+    fn test_fragment_links() {
+        let world = TestWorld::new(
+            "= Intro <intro>\n\
+             #link(\"#intro\")[A]\n\
+             #link(\"https://typst.app/doc#intro\")[B]\n\
+             #link(\"https://typst.app/other#intro\")[C]",
+        );
+
+        // Without a document URL, only the bare fragment jumps to the heading.
+        let pdf = world.export(&PdfOptions::default());
+        assert_eq!(
+            uris(&pdf),
+            ["https://typst.app/doc#intro", "https://typst.app/other#intro"]
+        );
+        assert_eq!(jumps(&pdf, "intro"), 1);
+        assert!(pdf.warnings.is_empty());
+
+        // With it, links to a bare fragment and to the document with a
+        // fragment jump to the heading.
+        let options = PdfOptions {
+            document_url: Some("https://typst.app/doc"),
+            ..Default::default()
+        };
+        let pdf = world.export(&options);
+        assert_eq!(uris(&pdf), ["https://typst.app/other#intro"]);
+        assert_eq!(jumps(&pdf, "intro"), 2);
+        assert!(pdf.warnings.is_empty());
+
+        // Fragments without a matching heading stay external and warn.
+        let world = TestWorld::new("#link(\"#missing\")[A]");
+        let pdf = world.export(&options);
+        assert_eq!(uris(&pdf), ["#missing"]);
+        assert_eq!(pdf.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_coordinate_precision() {
+        let world = TestWorld::new(
+            "#set par(justify: true)\n\
+             #lorem(200)\n\
+             #rotate(30deg, rect(width: 20.123456pt, height: 10.654321pt))\n\
+             #circle(radius: 7.777777pt)",
+        );
+        let precise = world.export(&PdfOptions::default());
+        let rounded = world.export(&PdfOptions {
+            coordinate_precision: Some(4),
+            ..Default::default()
+        });
+        assert!(rounded.page_content(0).len() < precise.page_content(0).len());
+
+        // By default, coordinates keep all of their digits.
+        let ops = precise.page_ops(0);
+        assert!(ops.iter().flat_map(|op| &op.operands).any(|operand| {
+            operand.as_f64().is_some_and(|v| (v * 1e4).fract().abs() > 1e-3)
+        }));
+
+        // The streams only differ in the digits that were rounded off, which
+        // changes the anti-aliasing of a few pixels at most.
+        assert_eq!(ops.len(), rounded.page_ops(0).len());
+        for (a, b) in ops.iter().zip(&rounded.page_ops(0)) {
+            assert_eq!(a.operator, b.operator);
+            assert_close(
+                &Object::Array(a.operands.clone()),
+                &Object::Array(b.operands.clone()),
+            );
+        }
+        assert!(mismatch(&precise.render(0, 2.0), &rounded.render(0, 2.0), 8) < 1e-4);
+    }
+
+    /// Assert that two objects are equal except for rounded numbers.
+    fn assert_close(a: &Object, b: &Object) {
+        match (a, b) {
+            (Object::Array(a), Object::Array(b)) => {
+                assert_eq!(a.len(), b.len());
+                a.iter().zip(b).for_each(|(a, b)| assert_close(a, b));
+            }
+            _ => match (a.as_f64(), b.as_f64()) {
+                (Some(a), Some(b)) => {
+                    assert!((a - b).abs() <= 1e-4 + a.abs() * 1e-6, "{a} != {b}")
+                }
+                _ => assert_eq!(a, b),
+            },
+        }
+    }
+
+    #[test]
+    fn test_dead_link_warnings() {
+        let world = TestWorld::new(
+            "[Start] <start>\n\
+             #link(<end>)[A] #link(<end>)[B] #link(<start>)[C]\n\
+             #pagebreak()\n\
+             #link(<end>)[D]\n\
+             #pagebreak()\n\
+             [End] <end>",
+        );
+        let pdf = world.export(&PdfOptions::default());
+        assert!(pdf.warnings.is_empty());
+
+        // Links to pages outside of the ranges are counted per page, but
+        // links to exported pages still work.
+        let first = NonZeroUsize::new(1);
+        let second = NonZeroUsize::new(2);
+        let page_ranges = Some(PageRanges::new(vec![first..=second]));
+        let pdf = world.export(&PdfOptions { page_ranges, ..Default::default() });
+        let messages: Vec<_> =
+            pdf.warnings.iter().map(|warning| warning.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "2 links on page 1 point to pages that are not exported",
+                "1 link on page 2 points to pages that are not exported",
+            ]
+        );
+        assert!(pdf.warnings[0].hints[0].contains("do nothing when clicked"));
+        assert_eq!(pdf.named("Subtype", "Link").len(), 4);
+    }
+
+    #[test]
+    fn test_link_visibility() {
+        let world = TestWorld::new("#link(\"https://typst.app\")[A]");
+        for (link_visibility, flags) in [
+            (LinkVisibility::Visible, 4),
+            (LinkVisibility::NoView, 36),
+            (LinkVisibility::Hidden, 2),
+        ] {
+            let pdf = world.export(&PdfOptions { link_visibility, ..Default::default() });
+            let links = pdf.named("Subtype", "Link");
+            assert_eq!(links.len(), 1);
+            assert_eq!(links[0].int("F"), Some(flags));
+        }
+    }
+
+    #[test]
+    fn test_hyphenated_text_extraction() {
+        let world = TestWorld::new(
+            "#set page(width: 50pt, height: auto, margin: 5pt)\n\
+             #set text(lang: \"en\", hyphenate: true)\n\
+             Incomprehensibilities so well-known",
+        );
+
+        // The hyphenated lines extract as parts of the word, without the
+        // inserted hyphens and without characters of the next line. The
+        // typed hyphen still extracts.
+        let pdf = world.export(&PdfOptions::default());
+        assert_eq!(pdf.text(0), "Incom\nprehen\nsibilities\nso well-\nknown");
+
+        // With soft hyphens, they mark where the word was broken.
+        let options = PdfOptions { soft_hyphens: true, ..Default::default() };
+        let pdf = world.export(&options);
+        assert_eq!(pdf.text(0), "Incom\u{ad}\nprehen\u{ad}\nsibilities\nso well-\nknown");
+    }
+
+    #[test]
+    fn test_long_glyph_runs() {
+        // A single run of 100,000 glyphs, whose glyph ranges saturate after
+        // the first 65,535 bytes.
+        let world = TestWorld::new(
+            "#set page(width: auto, height: auto)\n\
+             #set text(size: 2pt)\n\
+             #(\"AV\" * 50000)",
+        );
+        let pdf = world.export(&PdfOptions::default());
+
+        // It is shown in chunks of at most 4096 glyphs, which hold all of
+        // them and extract to the full text.
+        let chunks: Vec<usize> = pdf
+            .ops_named("TJ")
+            .iter()
+            .map(|op| {
+                let items = op.operands[0].as_array().unwrap();
+                items.iter().filter_map(Object::as_bytes).map(|s| s.len() / 2).sum()
+            })
+            .collect();
+        assert_eq!(chunks.len(), 25);
+        assert!(chunks.iter().all(|&n| n <= super::MAX_GLYPHS_PER_SHOW));
+        assert_eq!(chunks.iter().sum::<usize>(), 100_000);
+        assert_eq!(pdf.text(0), "AV".repeat(50000));
+
+        // Each pair of glyphs is kerned, also across the chunk boundaries, so
+        // the run keeps its width.
+        let kerned: usize = pdf
+            .ops_named("TJ")
+            .iter()
+            .map(|op| {
+                op.operands[0]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .filter(|item| item.as_f64().is_some())
+                    .count()
+            })
+            .sum();
+        assert_eq!(kerned, 99_999);
+    }
+
+    #[test]
+    fn test_mixed_direction_text_extraction() {
+        // Right-to-left runs are shown in visual order, but extract in the
+        // order in which they were written, both within a run and between the
+        // runs of a line.
+        for (lang, text) in [
+            ("en", "Hello שלום עולם World"),
+            ("he", "שלום Hello World עולם"),
+            ("he", "מספר 123 ועוד 45.6"),
+        ] {
+            let world = TestWorld::new(&format!(
+                "#set page(width: auto, height: auto)\n\
+                 #set text(lang: \"{lang}\")\n\
+                 {text}"
+            ));
+            let pdf = world.export(&PdfOptions::default());
+            assert_eq!(pdf.text(0), text);
+            assert_renders_like(&world, &PdfOptions::default());
+        }
+    }
+
+    #[test]
+    fn test_actual_text_spans_do_not_nest() {
+        // Hyphenated lines of outlined text whose stroke is painted below the
+        // fill need an `/ActualText` span for each reason.
+        let world = TestWorld::new(
+            "#set page(width: 50pt, height: auto, margin: 5pt)\n\
+             #set text(lang: \"en\", hyphenate: true, stroke: 0.5pt)\n\
+             #set text(paint-order: \"stroke-fill\")\n\
+             Incomprehensibilities",
+        );
+
+        for text_as_paths in [false, true] {
+            let pdf = world.export(&PdfOptions { text_as_paths, ..Default::default() });
+            let mut depth = 0;
+            for op in pdf.page_ops(0) {
+                match op.operator.as_str() {
+                    "BDC" | "BMC" => depth += 1,
+                    "EMC" => depth -= 1,
+                    _ => {}
+                }
+                assert!(depth <= 1);
+            }
+            assert_eq!(pdf.text(0).replace('\n', ""), "Incomprehensibilities");
+        }
+    }
+
+    /// The URIs that links point to.
+    fn uris(pdf: &TestPdf) -> Vec<String> {
+        pdf.values("URI").iter().filter_map(|uri| uri.as_text()).collect()
+    }
+
+    /// How many links jump to the named destination.
+    fn jumps(pdf: &TestPdf, name: &str) -> usize {
+        pdf.named("S", "GoTo")
+            .iter()
+            .filter(|action| {
+                action.get("D").and_then(Object::as_text).as_deref() == Some(name)
+            })
+            .count()
+    }
+
+    /// The `/ActualText` strings of the marked-content sequences.
+    fn actual_texts(pdf: &TestPdf) -> Vec<String> {
+        pdf.ops_named("BDC")
+            .iter()
+            .filter_map(|op| op.operands[1].as_dict()?.get("ActualText")?.as_text())
+            .collect()
+    }
+}
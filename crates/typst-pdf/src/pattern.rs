@@ -1,6 +1,6 @@
 use ecow::eco_format;
 use pdf_writer::types::{ColorSpaceOperand, PaintType, TilingType};
-use pdf_writer::{Filter, Finish, Name, Rect};
+use pdf_writer::{Content, Filter, Finish, Name, Rect};
 use typst::layout::{Abs, Ratio, Transform};
 use typst::utils::Numeric;
 use typst::visualize::{Pattern, RelativeTo};
@@ -12,31 +12,43 @@ use crate::{transform_to_array, PdfContext};
 /// Writes the actual patterns (tiling patterns) to the PDF.
 /// This is performed once after writing all pages.
 pub(crate) fn write_patterns(ctx: &mut PdfContext) {
-    for PdfPattern { transform, pattern, content, resources } in ctx.pattern_map.items() {
+    // Tiles can use other patterns, so all IDs are allocated up front.
+    for _ in ctx.pattern_map.items() {
         let tiling = ctx.alloc.bump();
         ctx.pattern_refs.push(tiling);
+    }
 
-        let mut tiling_pattern = ctx.pdf.tiling_pattern(tiling, content);
-        tiling_pattern
-            .tiling_type(TilingType::ConstantSpacing)
-            .paint_type(PaintType::Colored)
-            .bbox(Rect::new(
-                0.0,
-                0.0,
-                pattern.size().x.to_pt() as _,
-                pattern.size().y.to_pt() as _,
-            ))
-            .x_step((pattern.size().x + pattern.spacing().x).to_pt() as _)
-            .y_step((pattern.size().y + pattern.spacing().y).to_pt() as _);
+    // Patterns that only differ in their transform share their tile, so each
+    // tile's content is written once as a form XObject.
+    let mut tile_refs = vec![];
+    for (pattern, PdfPatternTile { content, resources, .. }) in &ctx.pattern_tiles {
+        let tile = ctx.alloc.bump();
+        tile_refs.push(tile);
 
-        let mut resources_map = tiling_pattern.resources();
+        let mut form = ctx.pdf.form_xobject(tile, content);
+        form.bbox(Rect::new(
+            0.0,
+            0.0,
+            pattern.size().x.to_pt() as _,
+            pattern.size().y.to_pt() as _,
+        ));
 
-        resources_map.x_objects().pairs(
-            resources
-                .iter()
-                .filter(|(res, _)| res.is_x_object())
-                .map(|(res, ref_)| (res.name(), ctx.image_refs[*ref_])),
-        );
+        let mut resources_map = form.resources();
+
+        resources_map
+            .x_objects()
+            .pairs(
+                resources
+                    .iter()
+                    .filter(|(res, _)| res.is_x_object())
+                    .map(|(res, ref_)| (res.name(), ctx.image_refs[*ref_])),
+            )
+            .pairs(
+                resources
+                    .iter()
+                    .filter(|(res, _)| res.is_form())
+                    .map(|(res, ref_)| (res.name(), ctx.form_refs[*ref_])),
+            );
 
         resources_map.fonts().pairs(
             resources
@@ -71,13 +83,40 @@ pub(crate) fn write_patterns(ctx: &mut PdfContext) {
         );
 
         resources_map.finish();
+        form.filter(Filter::FlateDecode);
+    }
+
+    let mut content = Content::new();
+    content.x_object(Name(b"Tile"));
+    let content = content.finish();
+
+    for (PdfPattern { transform, pattern }, &tiling) in
+        ctx.pattern_map.items().zip(&ctx.pattern_refs)
+    {
+        let index = ctx.pattern_tiles.get_index_of(pattern).unwrap();
+        let mut tiling_pattern = ctx.pdf.tiling_pattern(tiling, &content);
         tiling_pattern
-            .matrix(transform_to_array(
-                transform
-                    .pre_concat(Transform::scale(Ratio::one(), -Ratio::one()))
-                    .post_concat(Transform::translate(Abs::zero(), pattern.spacing().y)),
+            .tiling_type(TilingType::ConstantSpacing)
+            .paint_type(PaintType::Colored)
+            .bbox(Rect::new(
+                0.0,
+                0.0,
+                pattern.size().x.to_pt() as _,
+                pattern.size().y.to_pt() as _,
             ))
-            .filter(Filter::FlateDecode);
+            .x_step((pattern.size().x + pattern.spacing().x).to_pt() as _)
+            .y_step((pattern.size().y + pattern.spacing().y).to_pt() as _);
+
+        tiling_pattern
+            .resources()
+            .x_objects()
+            .pair(Name(b"Tile"), tile_refs[index]);
+
+        tiling_pattern.matrix(transform_to_array(
+            transform
+                .pre_concat(Transform::scale(Ratio::one(), -Ratio::one()))
+                .post_concat(Transform::translate(Abs::zero(), pattern.spacing().y)),
+        ));
     }
 }
 
@@ -88,10 +127,16 @@ pub struct PdfPattern {
     pub transform: Transform,
     /// The pattern to paint.
     pub pattern: Pattern,
-    /// The rendered pattern.
+}
+
+/// The rendered content of a pattern's tile.
+pub struct PdfPatternTile {
+    /// The rendered tile.
     pub content: Vec<u8>,
-    /// The resources used by the pattern.
+    /// The resources used by the tile.
     pub resources: Vec<(PageResource, usize)>,
+    /// Whether the tile uses opacities.
+    pub uses_opacities: bool,
 }
 
 /// Registers a pattern with the PDF.
@@ -115,18 +160,24 @@ fn register_pattern(
         RelativeTo::Parent => transforms.container_transform,
     };
 
-    // Render the body.
-    let content = construct_page(ctx.parent, pattern.frame());
-
-    let mut pdf_pattern = PdfPattern {
-        transform,
-        pattern: pattern.clone(),
-        content: content.content.wait().clone(),
-        resources: content.resources.into_iter().collect(),
-    };
+    // Render the body once per pattern.
+    if !ctx.parent.pattern_tiles.contains_key(pattern) {
+        let content = construct_page(ctx.parent, pattern.frame(), false);
+        let mut tile = PdfPatternTile {
+            content: content.content.wait().clone(),
+            resources: content.resources.into_iter().collect(),
+            uses_opacities: content.uses_opacities,
+        };
+
+        tile.resources.sort();
+        ctx.parent.pattern_tiles.insert(pattern.clone(), tile);
+    }
 
-    pdf_pattern.resources.sort();
+    if ctx.parent.pattern_tiles[pattern].uses_opacities {
+        ctx.uses_opacities = true;
+    }
 
+    let pdf_pattern = PdfPattern { transform, pattern: pattern.clone() };
     ctx.parent.pattern_map.insert(pdf_pattern)
 }
 
@@ -162,3 +213,37 @@ impl PaintEncode for Pattern {
             .insert(PageResource::new(ResourceKind::Pattern, id), index);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{assert_renders_like, TestWorld};
+    use crate::PdfOptions;
+
+    #[test]
+    fn test_shared_pattern_tiles() {
+        let world = TestWorld::new(
+            "#set page(width: 100pt, height: 150pt)\n\
+             #let dots = pattern(size: (10pt, 10pt), circle(radius: 3pt, fill: blue))\n\
+             #rect(width: 40pt, height: 25pt, fill: dots)\n\
+             #rect(width: 40pt, height: 25pt, fill: dots)\n\
+             #rotate(20deg, rect(width: 40pt, height: 20pt, fill: dots))",
+        );
+        let pdf = world.export(&PdfOptions::default());
+        assert_eq!(pdf.pages().len(), 1);
+
+        // Each use starts the pattern at its own rect, so it needs its own
+        // matrix. But all of them draw the same tile.
+        let tilings: Vec<_> = pdf
+            .streams()
+            .into_iter()
+            .filter(|stream| stream.dict.int("PatternType") == Some(1))
+            .collect();
+        assert_eq!(tilings.len(), 3);
+        assert_eq!(pdf.forms().len(), 1);
+        for tiling in tilings {
+            assert_eq!(tiling.data, b"/Tile Do");
+        }
+
+        assert_renders_like(&world, &PdfOptions::default());
+    }
+}
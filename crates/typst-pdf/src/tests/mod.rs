@@ -0,0 +1,741 @@
+//! Shared helpers for the exporter's tests.
+//!
+//! An exported document is parsed into its objects, so that tests can make
+//! assertions about the structure of the file instead of searching its
+//! bytes. Pages can also be extracted as text and rasterized, which is what
+//! a PDF reader would show.
+
+mod parse;
+mod render;
+
+use std::collections::HashMap;
+
+use ecow::EcoVec;
+use once_cell::sync::Lazy;
+use tiny_skia as sk;
+use typst::diag::{FileError, FileResult, SourceDiagnostic};
+use typst::eval::Tracer;
+use typst::foundations::{Bytes, Datetime};
+use typst::layout::{Frame, PageRanges};
+use typst::model::Document;
+use typst::syntax::{FileId, Source};
+use typst::text::{Font, FontBook};
+use typst::utils::LazyHash;
+use typst::visualize::Color;
+use typst::{Library, World};
+
+use self::parse::{decode_utf16, parse_content};
+pub use self::parse::{Dict, File, Object, Op, Stream};
+use crate::{
+    AssociatedFileRelationship, PdfEmbeddedFile, PdfOptions, PdfThumbnails,
+    PdfTransition, PdfVersion, TransitionStyle,
+};
+
+/// A world for PDF export testing.
+pub struct TestWorld {
+    pub main: Source,
+    files: HashMap<String, Bytes>,
+    base: &'static TestBase,
+}
+
+impl TestWorld {
+    /// Create a new world for a single test.
+    ///
+    /// This is cheap because the shared base for all test runs is lazily
+    /// initialized just once.
+    pub fn new(text: &str) -> Self {
+        static BASE: Lazy<TestBase> = Lazy::new(TestBase::default);
+        let main = Source::detached(text);
+        Self { main, files: HashMap::new(), base: &*BASE }
+    }
+
+    /// Make a file available at the given absolute path, in addition to
+    /// the development assets below `/assets`.
+    pub fn with_file(mut self, path: &str, data: impl Into<Bytes>) -> Self {
+        self.files.insert(path.trim_start_matches('/').into(), data.into());
+        self
+    }
+
+    /// Compile the main source, panicking on errors.
+    pub fn compile(&self) -> Document {
+        typst::compile(self, &mut Tracer::new()).expect("failed to compile")
+    }
+
+    /// Compile the main source and export it, panicking on errors.
+    pub fn export(&self, options: &PdfOptions) -> TestPdf {
+        let document = self.compile();
+        let mut tracer = Tracer::new();
+        let bytes = crate::pdf_with_options(&document, options, &mut tracer)
+            .expect("failed to export");
+        TestPdf::new(bytes, tracer.warnings())
+    }
+}
+
+impl World for TestWorld {
+    fn library(&self) -> &LazyHash<Library> {
+        &self.base.library
+    }
+
+    fn book(&self) -> &LazyHash<FontBook> {
+        &self.base.book
+    }
+
+    fn main(&self) -> Source {
+        self.main.clone()
+    }
+
+    fn source(&self, id: FileId) -> FileResult<Source> {
+        if id == self.main.id() {
+            Ok(self.main.clone())
+        } else {
+            Err(FileError::NotFound(id.vpath().as_rootless_path().into()))
+        }
+    }
+
+    fn file(&self, id: FileId) -> FileResult<Bytes> {
+        let path = id.vpath().as_rootless_path();
+        let key = path.to_string_lossy();
+        if let Some(data) = self.files.get(key.as_ref()) {
+            return Ok(data.clone());
+        }
+
+        key.strip_prefix("assets/")
+            .and_then(typst_dev_assets::get)
+            .map(Bytes::from_static)
+            .ok_or_else(|| FileError::NotFound(path.into()))
+    }
+
+    fn font(&self, index: usize) -> Option<Font> {
+        Some(self.base.fonts[index].clone())
+    }
+
+    fn today(&self, _: Option<i64>) -> Option<Datetime> {
+        None
+    }
+}
+
+/// Shared foundation of all test worlds.
+struct TestBase {
+    library: LazyHash<Library>,
+    book: LazyHash<FontBook>,
+    fonts: Vec<Font>,
+}
+
+impl Default for TestBase {
+    fn default() -> Self {
+        let fonts: Vec<_> = typst_assets::fonts()
+            .chain(typst_dev_assets::fonts())
+            .flat_map(|data| Font::iter(Bytes::from_static(data)))
+            .collect();
+
+        Self {
+            library: LazyHash::new(Library::default()),
+            book: LazyHash::new(FontBook::from_fonts(&fonts)),
+            fonts,
+        }
+    }
+}
+
+/// An exported PDF, parsed so that tests can inspect it.
+pub struct TestPdf {
+    /// The raw bytes of the file.
+    pub bytes: Vec<u8>,
+    /// The warnings that arose during export.
+    pub warnings: EcoVec<SourceDiagnostic>,
+    /// The parsed file.
+    pub file: File,
+}
+
+impl TestPdf {
+    /// Parse an exported file, panicking if it is malformed.
+    pub fn new(bytes: Vec<u8>, warnings: EcoVec<SourceDiagnostic>) -> Self {
+        let file = File::parse(&bytes);
+        Self { bytes, warnings, file }
+    }
+
+    /// Resolve an object if it is a reference.
+    pub fn get<'a>(&'a self, object: &'a Object) -> &'a Object {
+        self.file.get(object)
+    }
+
+    /// Resolve a dictionary entry that must be a dictionary or stream.
+    pub fn dict<'a>(&'a self, dict: &'a Dict, key: &str) -> &'a Dict {
+        let value = dict.get(key).unwrap_or_else(|| panic!("missing /{key}"));
+        self.get(value)
+            .as_dict()
+            .unwrap_or_else(|| panic!("/{key} is not a dict"))
+    }
+
+    /// The document catalog.
+    pub fn catalog(&self) -> &Dict {
+        self.dict(&self.file.trailer, "Root")
+    }
+
+    /// The page dictionaries, in order.
+    pub fn pages(&self) -> Vec<&Dict> {
+        let mut pages = vec![];
+        let mut stack = vec![self.dict(self.catalog(), "Pages")];
+        while let Some(node) = stack.pop() {
+            if node.name("Type") == Some("Page") {
+                pages.push(node);
+                continue;
+            }
+            let kids = self.get(node.get("Kids").expect("page tree node without kids"));
+            for kid in kids.as_array().unwrap().iter().rev() {
+                stack.push(self.get(kid).as_dict().unwrap());
+            }
+        }
+        pages
+    }
+
+    /// The page with the given index.
+    pub fn page(&self, index: usize) -> &Dict {
+        self.pages()[index]
+    }
+
+    /// All objects in the file, including those nested in arrays and
+    /// dictionaries, in the order of their objects' numbers.
+    pub fn all(&self) -> Vec<&Object> {
+        fn collect<'a>(object: &'a Object, all: &mut Vec<&'a Object>) {
+            all.push(object);
+            match object {
+                Object::Array(items) => items.iter().for_each(|item| collect(item, all)),
+                Object::Dict(dict) | Object::Stream(Stream { dict, .. }) => {
+                    dict.0.iter().for_each(|(_, value)| collect(value, all));
+                }
+                _ => {}
+            }
+        }
+
+        let mut all = vec![];
+        self.file
+            .objects
+            .values()
+            .for_each(|object| collect(object, &mut all));
+        all
+    }
+
+    /// All dictionaries in the file, including those of streams and those
+    /// nested in other objects.
+    pub fn dicts(&self) -> Vec<&Dict> {
+        self.all().into_iter().filter_map(Object::as_dict).collect()
+    }
+
+    /// The dictionaries in which the given key has the given name as its
+    /// value, e.g. `("Subtype", "Link")`.
+    pub fn named(&self, key: &str, name: &str) -> Vec<&Dict> {
+        self.dicts()
+            .into_iter()
+            .filter(|dict| dict.name(key) == Some(name))
+            .collect()
+    }
+
+    /// The values of all dictionary entries with the given key, resolved if
+    /// they are references.
+    pub fn values(&self, key: &str) -> Vec<&Object> {
+        self.dicts()
+            .into_iter()
+            .filter_map(|dict| dict.get(key))
+            .map(|value| self.get(value))
+            .collect()
+    }
+
+    /// How many dictionaries in the file have the given key.
+    pub fn count(&self, key: &str) -> usize {
+        self.values(key).len()
+    }
+
+    /// All streams in the file.
+    pub fn streams(&self) -> Vec<&Stream> {
+        self.file.objects.values().filter_map(Object::as_stream).collect()
+    }
+
+    /// The decoded content of a page, with its streams joined.
+    pub fn page_content(&self, index: usize) -> Vec<u8> {
+        let page = self.page(index);
+        let contents = self.get(page.get("Contents").expect("page without contents"));
+        let mut data = vec![];
+        for content in contents.as_array().unwrap_or(std::slice::from_ref(contents)) {
+            data.extend_from_slice(&self.get(content).as_stream().unwrap().data);
+            data.push(b'\n');
+        }
+        data
+    }
+
+    /// The operations of a page, without those of the forms it uses.
+    pub fn page_ops(&self, index: usize) -> Vec<Op> {
+        parse_content(&self.page_content(index))
+    }
+
+    /// The streams whose `/Subtype` has the given name, e.g. `Image`.
+    pub fn subtype(&self, name: &str) -> Vec<&Stream> {
+        self.streams()
+            .into_iter()
+            .filter(|stream| stream.dict.name("Subtype") == Some(name))
+            .collect()
+    }
+
+    /// The form XObjects in the file.
+    pub fn forms(&self) -> Vec<&Stream> {
+        self.subtype("Form")
+    }
+
+    /// The operations of all pages and form XObjects, in that order.
+    pub fn ops(&self) -> Vec<Op> {
+        let mut ops: Vec<Op> =
+            (0..self.pages().len()).flat_map(|i| self.page_ops(i)).collect();
+        for form in self.forms() {
+            ops.extend(parse_content(&form.data));
+        }
+        ops
+    }
+
+    /// The total size of the decoded content of the pages and forms.
+    pub fn content_size(&self) -> usize {
+        let pages: usize =
+            (0..self.pages().len()).map(|i| self.page_content(i).len()).sum();
+        pages + self.forms().iter().map(|form| form.data.len()).sum::<usize>()
+    }
+
+    /// The operations of all pages and forms with the given operator.
+    pub fn ops_named(&self, operator: &str) -> Vec<Op> {
+        self.ops().into_iter().filter(|op| op.operator == operator).collect()
+    }
+
+    /// Extract the text of a page the way a text extractor does.
+    ///
+    /// The glyphs are mapped to text with their font's `/ToUnicode` map,
+    /// unless they are in an `/ActualText` span. Like most extractors, only
+    /// the outermost of nested spans is taken into account. A line break is
+    /// inserted when the baseline changes.
+    pub fn text(&self, index: usize) -> String {
+        let page = self.page(index);
+        let resources =
+            page.get("Resources").map(|r| self.get(r)).and_then(Object::as_dict);
+        let mut extractor = TextExtractor {
+            pdf: self,
+            text: String::new(),
+            spans: vec![],
+            font: None,
+            baseline: None,
+            y: 0.0,
+        };
+        extractor.run(&self.page_ops(index), resources);
+        extractor.text
+    }
+
+    /// Rasterize a page with the given number of pixels per point.
+    pub fn render(&self, index: usize, pixel_per_pt: f32) -> sk::Pixmap {
+        render::render(&self.file, self.page(index), pixel_per_pt)
+    }
+}
+
+/// Extracts the text of a content stream.
+struct TextExtractor<'a> {
+    pdf: &'a TestPdf,
+    text: String,
+    /// For each open marked-content sequence, its actual text, if it is an
+    /// outermost `/ActualText` span.
+    spans: Vec<Option<String>>,
+    /// The character map of the current font.
+    font: Option<HashMap<u16, String>>,
+    /// The baseline of the last shown glyphs.
+    baseline: Option<f64>,
+    /// The baseline set by the text positioning operators.
+    y: f64,
+}
+
+impl TextExtractor<'_> {
+    fn run(&mut self, ops: &[Op], resources: Option<&Dict>) {
+        let pdf = self.pdf;
+        let resource = |category: &str, name: &Object| {
+            let dict = pdf.dict(resources.expect("no resources"), category);
+            pdf.get(dict.get(name.as_name().unwrap()).expect("missing resource"))
+        };
+
+        for op in ops {
+            match op.operator.as_str() {
+                "BDC" => {
+                    let properties = match &op.operands[1] {
+                        Object::Dict(dict) => Some(dict),
+                        name => resource("Properties", name).as_dict(),
+                    };
+                    let outermost = self.spans.iter().all(Option::is_none);
+                    let actual = properties
+                        .and_then(|dict| dict.get("ActualText"))
+                        .and_then(Object::as_text)
+                        .filter(|_| outermost);
+                    self.spans.push(actual);
+                }
+                "BMC" => self.spans.push(None),
+                "EMC" => {
+                    if let Some(text) = self.spans.pop().expect("unbalanced EMC") {
+                        self.text.push_str(&text);
+                    }
+                }
+                "Tf" => {
+                    let font = resource("Font", &op.operands[0]).as_dict().unwrap();
+                    self.font = Some(self.cmap(font));
+                }
+                "BT" => self.y = 0.0,
+                "Td" | "TD" => self.y += op.numbers()[1],
+                "Tm" => self.y = op.numbers()[5],
+                "Tj" | "'" | "\"" => self.show(op.operands.last().unwrap()),
+                "TJ" => {
+                    for item in op.operands[0].as_array().unwrap() {
+                        if let Object::Str(_) = item {
+                            self.show(item);
+                        }
+                    }
+                }
+                "Do" => {
+                    let xobject =
+                        resource("XObject", &op.operands[0]).as_stream().unwrap();
+                    if xobject.dict.name("Subtype") == Some("Form") {
+                        let inner = xobject
+                            .dict
+                            .get("Resources")
+                            .map(|r| pdf.get(r))
+                            .and_then(Object::as_dict);
+                        self.run(&parse_content(&xobject.data), inner);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Map the glyphs of a string to text.
+    fn show(&mut self, string: &Object) {
+        if self.baseline.is_some_and(|baseline| (baseline - self.y).abs() > 1e-3) {
+            self.text.push('\n');
+        }
+        self.baseline = Some(self.y);
+
+        if self.spans.iter().any(Option::is_some) {
+            return;
+        }
+
+        let cmap = self.font.as_ref().expect("text without font");
+        for code in string.as_bytes().unwrap().chunks_exact(2) {
+            let code = u16::from_be_bytes([code[0], code[1]]);
+            if let Some(text) = cmap.get(&code) {
+                self.text.push_str(text);
+            }
+        }
+    }
+
+    /// Parse the `/ToUnicode` character map of a font.
+    fn cmap(&self, font: &Dict) -> HashMap<u16, String> {
+        let mut map = HashMap::new();
+        let Some(stream) = font.get("ToUnicode") else { return map };
+        let stream = self.pdf.get(stream).as_stream().unwrap();
+        let code = |object: &Object| {
+            let bytes = object.as_bytes().unwrap();
+            u16::from_be_bytes([bytes[0], bytes[1]])
+        };
+
+        for op in parse_content(&stream.data) {
+            match op.operator.as_str() {
+                "endbfchar" => {
+                    for pair in op.operands.chunks_exact(2) {
+                        map.insert(
+                            code(&pair[0]),
+                            decode_utf16(pair[1].as_bytes().unwrap()),
+                        );
+                    }
+                }
+                "endbfrange" => {
+                    for triple in op.operands.chunks_exact(3) {
+                        let (first, last) = (code(&triple[0]), code(&triple[1]));
+                        let start = triple[2].as_bytes().unwrap();
+                        let start = u16::from_be_bytes([start[0], start[1]]);
+                        for (i, c) in (first..=last).enumerate() {
+                            let unit = start + i as u16;
+                            map.insert(c, String::from_utf16_lossy(&[unit]));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        map
+    }
+}
+
+/// Render a thumbnail with the PNG exporter's renderer.
+pub fn render_thumbnail(frame: &Frame, pixel_per_pt: f32) -> (u32, u32, Vec<u8>) {
+    let pixmap = typst_render::render(frame, pixel_per_pt, Color::WHITE);
+    let rgb = pixmap
+        .data()
+        .chunks_exact(4)
+        .flat_map(|p| [p[0], p[1], p[2]])
+        .collect();
+    (pixmap.width(), pixmap.height(), rgb)
+}
+
+/// Rasterize the first page of a document the way the PNG exporter does, on
+/// a white background.
+pub fn render_reference(document: &Document, pixel_per_pt: f32) -> sk::Pixmap {
+    typst_render::render(&document.pages[0].frame, pixel_per_pt, Color::WHITE)
+}
+
+/// The share of pixels whose channels differ by more than the tolerance.
+pub fn mismatch(a: &sk::Pixmap, b: &sk::Pixmap, tolerance: u8) -> f64 {
+    assert_eq!((a.width(), a.height()), (b.width(), b.height()), "sizes differ");
+    let differing = a
+        .pixels()
+        .iter()
+        .zip(b.pixels())
+        .filter(|(p, q)| {
+            let (p, q) = (p.demultiply(), q.demultiply());
+            [
+                (p.red(), q.red()),
+                (p.green(), q.green()),
+                (p.blue(), q.blue()),
+                (p.alpha(), q.alpha()),
+            ]
+            .iter()
+            .any(|&(x, y)| x.abs_diff(y) > tolerance)
+        })
+        .count();
+    differing as f64 / a.pixels().len() as f64
+}
+
+/// Assert that a page of the exported PDF looks like the document rendered
+/// by the PNG exporter.
+///
+/// Anti-aliasing differs between the two rasterizers, so a few pixels along
+/// edges are allowed to be off.
+#[track_caller]
+pub fn assert_renders_like(world: &TestWorld, options: &PdfOptions) {
+    let document = world.compile();
+    let pdf = world.export(options);
+    let ours = pdf.render(0, 2.0);
+    let reference = render_reference(&document, 2.0);
+    let share = mismatch(&ours, &reference, 64);
+    assert!(share < 0.01, "{:.2}% of the pixels differ", share * 100.0);
+}
+
+/// The pixel at the given position, demultiplied.
+pub fn pixel(pixmap: &sk::Pixmap, x: u32, y: u32) -> [u8; 4] {
+    let p = pixmap.pixel(x, y).unwrap().demultiply();
+    [p.red(), p.green(), p.blue(), p.alpha()]
+}
+
+#[test]
+fn test_check_options() {
+    let document = TestWorld::new("Hello").compile();
+    let ok = |options: PdfOptions| {
+        crate::pdf_with_options(&document, &options, &mut Tracer::new()).is_ok()
+    };
+
+    for value in [0.0, -1.0, f64::NAN, f64::INFINITY].map(Some) {
+        assert!(!ok(PdfOptions { max_image_dpi: value, ..Default::default() }));
+        assert!(!ok(PdfOptions { svg_raster_scale: value, ..Default::default() }));
+        assert!(!ok(PdfOptions { svg_raster_dpi: value, ..Default::default() }));
+    }
+
+    assert!(ok(PdfOptions { max_image_dpi: Some(150.0), ..Default::default() }));
+    assert!(ok(PdfOptions { svg_raster_scale: Some(0.5), ..Default::default() }));
+    assert!(ok(PdfOptions { svg_raster_dpi: Some(300.0), ..Default::default() }));
+
+    for max_size in [0, PdfThumbnails::MAX_SIZE + 1, u32::MAX] {
+        let thumbnails =
+            Some(PdfThumbnails { max_size, quality: 75, render: render_thumbnail });
+        assert!(!ok(PdfOptions { thumbnails, ..Default::default() }));
+    }
+
+    // Only RGB profiles can be the working space, which is checked before
+    // anything is exported.
+    for profile in [&[][..], typst_assets::icc::S_GREY_V4] {
+        let working_rgb_profile = Some(Bytes::from(profile));
+        assert!(!ok(PdfOptions { working_rgb_profile, ..Default::default() }));
+    }
+
+    let working_rgb_profile = Some(Bytes::from_static(typst_assets::icc::S_RGB_V4));
+    assert!(ok(PdfOptions { working_rgb_profile, ..Default::default() }));
+
+    let file = PdfEmbeddedFile {
+        name: "data.txt".into(),
+        data: Bytes::from_static(b"data"),
+        description: None,
+        mime_type: None,
+        modified: None,
+        relationship: None,
+    };
+    for (limit, fits) in [(8, true), (7, false)] {
+        let embedded_files = vec![file.clone(), file.clone()];
+        let embedded_files_limit = Some(limit);
+        let options = PdfOptions {
+            embedded_files,
+            embedded_files_limit,
+            ..Default::default()
+        };
+        assert_eq!(ok(options), fits);
+    }
+
+    for places in [PdfOptions::MAX_COORDINATE_PRECISION + 1, u8::MAX] {
+        let coordinate_precision = Some(places);
+        assert!(!ok(PdfOptions { coordinate_precision, ..Default::default() }));
+    }
+
+    for places in [0, PdfOptions::MAX_COORDINATE_PRECISION] {
+        let coordinate_precision = Some(places);
+        assert!(ok(PdfOptions { coordinate_precision, ..Default::default() }));
+    }
+
+    let all = || PageRanges::new(vec![None..=None]);
+    let transition = |duration, advance| PdfTransition {
+        style: TransitionStyle::Wipe,
+        duration,
+        advance,
+    };
+    for value in [-1.0, f32::NAN, f32::INFINITY] {
+        for transition in [transition(value, None), transition(1.0, Some(value))] {
+            let page_transitions = vec![(all(), transition)];
+            assert!(!ok(PdfOptions { page_transitions, ..Default::default() }));
+        }
+    }
+
+    let page_transitions = vec![(all(), transition(0.0, Some(0.0)))];
+    assert!(ok(PdfOptions { page_transitions, ..Default::default() }));
+}
+
+#[test]
+fn test_stats() {
+    // The header is shared between the pages, but its circles count on
+    // each of them.
+    let document = TestWorld::new(
+        "#set page(width: 100pt, height: 100pt, header: {\n\
+         for i in range(8) { place(dx: i * 10pt, circle(radius: 2pt)) }\n\
+         })\n\
+         A #rect(width: 10pt, height: 10pt) #pagebreak() B",
+    )
+    .compile();
+    let (bytes, stats) =
+        crate::pdf_with_stats(&document, &PdfOptions::default(), &mut Tracer::new())
+            .expect("failed to export");
+
+    let counts: Vec<_> = stats
+        .pages
+        .iter()
+        .map(|page| (page.index, page.glyph_runs, page.shapes, page.images))
+        .collect();
+    assert_eq!(counts, [(0, 1, 9, 0), (1, 1, 8, 0)]);
+    assert_eq!(stats.fonts, 1);
+    assert!(stats.font_bytes > 0);
+    assert_eq!((stats.images, stats.image_bytes), (0, 0));
+    assert!(stats.content_bytes > 0);
+    assert_eq!(stats.size, bytes.len());
+    assert!(stats.font_bytes + stats.content_bytes < stats.size);
+}
+
+#[test]
+fn test_text_extraction() {
+    let world = TestWorld::new(
+        "#set page(width: 100pt, height: auto)\n\
+         Hello World\n\n\
+         #box(rect[Inside])",
+    );
+    let pdf = world.export(&PdfOptions::default());
+    assert_eq!(pdf.text(0), "Hello World\nInside");
+}
+
+#[test]
+fn test_rendering() {
+    for source in [
+        "#set page(width: 120pt, height: 80pt, fill: luma(230))\n\
+         #rect(width: 50pt, height: 20pt, fill: red, stroke: 2pt + blue)\n\
+         #place(dx: 60pt, dy: 10pt, circle(radius: 15pt, fill: green.transparentize(50%)))\n\
+         #line(length: 100pt, stroke: (paint: purple, thickness: 3pt, dash: \"dashed\"))",
+        "#set page(width: 120pt, height: 80pt)\n\
+         #rect(width: 100%, height: 30pt, fill: gradient.linear(red, blue))\n\
+         #rect(width: 100%, height: 30pt, fill: gradient.radial(yellow, green))",
+        "#set page(width: 120pt, height: 80pt)\n\
+         #set text(size: 20pt, fill: blue)\n\
+         Hello #text(red)[World]",
+        "#set page(width: 120pt, height: 80pt)\n\
+         #let pat = pattern(size: (10pt, 10pt), square(size: 5pt, fill: red))\n\
+         #rect(width: 100%, height: 100%, fill: pat)",
+        "#set page(width: 120pt, height: 80pt)\n\
+         #rotate(20deg, rect(width: 40pt, height: 20pt, fill: teal))\n\
+         #scale(x: -100%, block(clip: true, width: 30pt, height: 10pt, circle(radius: 20pt, fill: orange)))",
+    ] {
+        assert_renders_like(&TestWorld::new(source), &PdfOptions::default());
+    }
+}
+
+#[test]
+fn test_pdf_version() {
+    let world = TestWorld::new(
+        "#set document(title: \"Report\", author: \"Jane\", keywords: (\"a\", \"b\"))\n\
+         #set document(date: datetime(year: 2024, month: 3, day: 1))\n\
+         = Intro\n\
+         Hello #link(\"https://typst.app\")[World]\n\
+         #pagebreak()\n\
+         #rect(fill: red.transparentize(50%))",
+    );
+    let file = PdfEmbeddedFile {
+        name: "data.csv".into(),
+        data: Bytes::from_static(b"a,b"),
+        description: None,
+        mime_type: None,
+        modified: None,
+        relationship: Some(AssociatedFileRelationship::Data),
+    };
+    let options = |version| PdfOptions {
+        version,
+        embedded_files: vec![file.clone()],
+        ..Default::default()
+    };
+    let legacy = world.export(&options(PdfVersion::Pdf17));
+    let modern = world.export(&options(PdfVersion::Pdf20));
+
+    // The header and the metadata declare the version.
+    assert_eq!(
+        (legacy.file.version.as_str(), modern.file.version.as_str()),
+        ("1.7", "2.0")
+    );
+    let xmp = |pdf: &TestPdf| {
+        let metadata = pdf.get(pdf.catalog().get("Metadata").unwrap());
+        String::from_utf8(metadata.as_stream().unwrap().data.clone()).unwrap()
+    };
+    assert!(xmp(&legacy).contains("<pdf:PDFVersion>1.7</pdf:PDFVersion>"));
+    assert!(xmp(&modern).contains("<pdf:PDFVersion>2.0</pdf:PDFVersion>"));
+
+    // The document information dictionary only keeps the dates in 2.0, the
+    // rest is deprecated in favor of the XMP metadata, which has it all.
+    let keys = |pdf: &TestPdf| {
+        let info = pdf.dict(&pdf.file.trailer, "Info");
+        ["Title", "Author", "Keywords", "Creator", "CreationDate", "ModDate"]
+            .into_iter()
+            .filter(|key| info.contains(key))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        keys(&legacy),
+        ["Title", "Author", "Keywords", "Creator", "CreationDate", "ModDate"]
+    );
+    assert_eq!(keys(&modern), ["CreationDate", "ModDate"]);
+    assert!(xmp(&modern).contains("Report") && xmp(&modern).contains("Jane"));
+
+    // Both declare the associated file through the catalog, which is the
+    // 2.0 way, and neither is encrypted.
+    for pdf in [&legacy, &modern] {
+        assert_eq!(
+            pdf.catalog()
+                .get("AF")
+                .and_then(|af| pdf.get(af).as_array())
+                .map(<[_]>::len),
+            Some(1)
+        );
+        assert!(!pdf.file.trailer.contains("Encrypt"));
+    }
+
+    // Everything else stays the same.
+    assert_eq!(legacy.ops(), modern.ops());
+    for i in 0..2 {
+        assert_eq!(legacy.page(i), modern.page(i));
+    }
+}
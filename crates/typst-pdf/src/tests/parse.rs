@@ -0,0 +1,588 @@
+//! Parsing of exported PDF files and content streams.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug, Formatter};
+
+/// A PDF object.
+#[derive(Clone, PartialEq)]
+pub enum Object {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Real(f64),
+    Name(String),
+    Str(Vec<u8>),
+    Array(Vec<Object>),
+    Dict(Dict),
+    Stream(Stream),
+    Ref(i32),
+}
+
+impl Object {
+    /// The value if this is an integer.
+    pub fn as_int(&self) -> Option<i64> {
+        match *self {
+            Self::Int(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The value if this is an integer or real number.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Self::Int(value) => Some(value as f64),
+            Self::Real(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The name, without its leading slash, if this is a name.
+    pub fn as_name(&self) -> Option<&str> {
+        match self {
+            Self::Name(name) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// The raw bytes if this is a string.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Self::Str(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// The decoded text if this is a text string.
+    pub fn as_text(&self) -> Option<String> {
+        self.as_bytes().map(decode_text)
+    }
+
+    /// The items if this is an array.
+    pub fn as_array(&self) -> Option<&[Object]> {
+        match self {
+            Self::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// The numbers if this is an array of numbers.
+    pub fn as_numbers(&self) -> Option<Vec<f64>> {
+        self.as_array()?.iter().map(Object::as_f64).collect()
+    }
+
+    /// The dictionary if this is a dictionary or a stream.
+    pub fn as_dict(&self) -> Option<&Dict> {
+        match self {
+            Self::Dict(dict) => Some(dict),
+            Self::Stream(stream) => Some(&stream.dict),
+            _ => None,
+        }
+    }
+
+    /// The stream if this is a stream.
+    pub fn as_stream(&self) -> Option<&Stream> {
+        match self {
+            Self::Stream(stream) => Some(stream),
+            _ => None,
+        }
+    }
+
+    /// The object number if this is an indirect reference.
+    pub fn as_ref(&self) -> Option<i32> {
+        match *self {
+            Self::Ref(id) => Some(id),
+            _ => None,
+        }
+    }
+}
+
+impl Debug for Object {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Null => f.write_str("null"),
+            Self::Bool(value) => write!(f, "{value}"),
+            Self::Int(value) => write!(f, "{value}"),
+            Self::Real(value) => write!(f, "{value}"),
+            Self::Name(name) => write!(f, "/{name}"),
+            Self::Str(bytes) => write!(f, "({})", String::from_utf8_lossy(bytes)),
+            Self::Array(items) => f.debug_list().entries(items).finish(),
+            Self::Dict(dict) => dict.fmt(f),
+            Self::Stream(stream) => {
+                write!(f, "{:?} stream({} bytes)", stream.dict, stream.data.len())
+            }
+            Self::Ref(id) => write!(f, "{id} 0 R"),
+        }
+    }
+}
+
+/// A PDF dictionary, with its entries in the order they were written.
+#[derive(Default, Clone, PartialEq)]
+pub struct Dict(pub Vec<(String, Object)>);
+
+impl Dict {
+    /// The value of the entry with the given key, e.g. `Type`.
+    pub fn get(&self, key: &str) -> Option<&Object> {
+        self.0.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    /// Whether the dictionary has an entry with the given key.
+    pub fn contains(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// The name that the entry with the given key holds, e.g. the `Subtype`.
+    pub fn name(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(Object::as_name)
+    }
+
+    /// The integer that the entry with the given key holds.
+    pub fn int(&self, key: &str) -> Option<i64> {
+        self.get(key).and_then(Object::as_int)
+    }
+
+    /// The number that the entry with the given key holds.
+    pub fn f64(&self, key: &str) -> Option<f64> {
+        self.get(key).and_then(Object::as_f64)
+    }
+
+    /// Whether the entry with the given key is the boolean `true`.
+    pub fn is_true(&self, key: &str) -> bool {
+        self.get(key) == Some(&Object::Bool(true))
+    }
+}
+
+impl Debug for Dict {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_map().entries(self.0.iter().map(|(k, v)| (k, v))).finish()
+    }
+}
+
+/// A PDF stream.
+#[derive(Clone, PartialEq)]
+pub struct Stream {
+    /// The stream's dictionary.
+    pub dict: Dict,
+    /// The stream's data as written.
+    pub raw: Vec<u8>,
+    /// The stream's data, decompressed if it uses the Flate filter.
+    pub data: Vec<u8>,
+}
+
+/// A whole PDF file, with its objects looked up through the cross-reference
+/// table.
+pub struct File {
+    /// The file's version, e.g. `1.7`.
+    pub version: String,
+    /// The file's trailer dictionary.
+    pub trailer: Dict,
+    /// The file's indirect objects by their object number.
+    pub objects: BTreeMap<i32, Object>,
+}
+
+impl File {
+    /// Parse a PDF file, panicking if it is malformed.
+    pub fn parse(bytes: &[u8]) -> Self {
+        let header = bytes.strip_prefix(b"%PDF-").expect("missing header");
+        let end = header.iter().position(|b| b.is_ascii_whitespace()).unwrap();
+        let version = String::from_utf8(header[..end].to_vec()).unwrap();
+
+        let tail = bytes.len().saturating_sub(64);
+        let start = find_last(&bytes[tail..], b"startxref").expect("missing startxref");
+        let mut p = Parser::new(bytes, tail + start + b"startxref".len());
+        let xref = p.object().as_int().expect("invalid startxref") as usize;
+
+        let mut p = Parser::new(bytes, xref);
+        p.expect_keyword("xref");
+        let mut offsets = vec![];
+        let mut entries = 0;
+        while let Some(first) = p.peek_int() {
+            p.object();
+            let count = p.object().as_int().expect("invalid xref subsection");
+            entries += count;
+            for id in first..first + count {
+                let offset = p.object().as_int().expect("invalid xref entry");
+                let generation = p.object().as_int().expect("invalid xref entry");
+                match p.keyword().as_str() {
+                    "n" => offsets.push((id as i32, offset as usize, generation)),
+                    "f" => {}
+                    other => panic!("invalid xref entry type {other}"),
+                }
+            }
+        }
+
+        p.expect_keyword("trailer");
+        let Object::Dict(trailer) = p.object() else { panic!("invalid trailer") };
+        let size = trailer.int("Size").expect("trailer without size");
+        assert_eq!(size, entries, "xref doesn't match /Size");
+
+        let mut objects = BTreeMap::new();
+        for (id, offset, generation) in offsets {
+            let mut p = Parser::new(bytes, offset);
+            assert_eq!(p.object(), Object::Int(id as i64), "xref offset is off");
+            assert_eq!(p.object(), Object::Int(generation));
+            p.expect_keyword("obj");
+            let object = p.indirect_object();
+            p.expect_keyword("endobj");
+            objects.insert(id, object);
+        }
+
+        Self { version, trailer, objects }
+    }
+
+    /// Resolve an object if it is a reference.
+    pub fn get<'a>(&'a self, object: &'a Object) -> &'a Object {
+        match object {
+            Object::Ref(id) => self
+                .objects
+                .get(id)
+                .unwrap_or_else(|| panic!("dangling reference {id} 0 R")),
+            _ => object,
+        }
+    }
+}
+
+/// An operation in a content stream.
+#[derive(Clone, PartialEq)]
+pub struct Op {
+    /// The operator, e.g. `cm`.
+    pub operator: String,
+    /// The operands before the operator.
+    pub operands: Vec<Object>,
+}
+
+impl Op {
+    /// The operands as numbers, panicking if one isn't.
+    pub fn numbers(&self) -> Vec<f64> {
+        self.operands
+            .iter()
+            .map(|operand| operand.as_f64().expect("operand is not a number"))
+            .collect()
+    }
+}
+
+impl Debug for Op {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for operand in &self.operands {
+            write!(f, "{operand:?} ")?;
+        }
+        f.write_str(&self.operator)
+    }
+}
+
+/// Parse a content stream into its operations.
+pub fn parse_content(data: &[u8]) -> Vec<Op> {
+    let mut p = Parser::new(data, 0);
+    let mut ops = vec![];
+    let mut operands = vec![];
+    loop {
+        p.skip_whitespace();
+        if p.done() {
+            break;
+        }
+
+        if p.at_keyword() {
+            let operator = p.keyword();
+            assert_ne!(operator, "BI", "inline images are not supported");
+            ops.push(Op { operator, operands: std::mem::take(&mut operands) });
+        } else {
+            operands.push(p.object());
+        }
+    }
+
+    assert!(operands.is_empty(), "operands without an operator");
+    ops
+}
+
+/// Decode a text string, which is either UTF-16BE with a byte order mark or
+/// (for ASCII-only text) PDFDocEncoding.
+pub fn decode_text(bytes: &[u8]) -> String {
+    match bytes.strip_prefix(&[0xFE, 0xFF]) {
+        Some(utf16) => decode_utf16(utf16),
+        None => bytes.iter().map(|&b| b as char).collect(),
+    }
+}
+
+/// Decode UTF-16BE bytes.
+pub fn decode_utf16(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes
+        .chunks(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16(&units).expect("invalid UTF-16")
+}
+
+/// A parser for PDF objects.
+struct Parser<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Self { data, pos }
+    }
+
+    fn done(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    fn eat(&mut self) -> u8 {
+        let byte = self.peek().expect("unexpected end of data");
+        self.pos += 1;
+        byte
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(byte) = self.peek() {
+            if byte == b'%' {
+                while !matches!(self.peek(), None | Some(b'\n' | b'\r')) {
+                    self.pos += 1;
+                }
+            } else if is_whitespace(byte) {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Whether a keyword (including an operator) comes next.
+    fn at_keyword(&self) -> bool {
+        match self.peek() {
+            Some(byte) if byte.is_ascii_alphabetic() || byte == b'\'' || byte == b'"' => {
+                let word = self.word_at(self.pos);
+                !matches!(word, b"true" | b"false" | b"null")
+            }
+            _ => false,
+        }
+    }
+
+    fn word_at(&self, pos: usize) -> &'a [u8] {
+        let len = self.data[pos..]
+            .iter()
+            .position(|&b| is_whitespace(b) || is_delimiter(b))
+            .unwrap_or(self.data.len() - pos);
+        &self.data[pos..(pos + len.max(1)).min(self.data.len())]
+    }
+
+    fn keyword(&mut self) -> String {
+        self.skip_whitespace();
+        let word = self.word_at(self.pos);
+        self.pos += word.len();
+        String::from_utf8(word.to_vec()).unwrap()
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) {
+        let found = self.keyword();
+        assert_eq!(found, keyword, "expected keyword at {}", self.pos);
+    }
+
+    fn peek_int(&mut self) -> Option<i64> {
+        self.skip_whitespace();
+        let word = self.word_at(self.pos);
+        std::str::from_utf8(word).ok()?.parse().ok()
+    }
+
+    /// Parse the object of an indirect object, which may be a stream.
+    fn indirect_object(&mut self) -> Object {
+        let object = self.object();
+        self.skip_whitespace();
+        if self.word_at(self.pos) != b"stream" {
+            return object;
+        }
+
+        self.pos += b"stream".len();
+        if self.peek() == Some(b'\r') {
+            self.pos += 1;
+        }
+        assert_eq!(self.eat(), b'\n', "stream keyword without newline");
+
+        let Object::Dict(dict) = object else { panic!("stream without dictionary") };
+        let len = dict.int("Length").expect("stream without direct length") as usize;
+        let raw = self.data[self.pos..self.pos + len].to_vec();
+        self.pos += len;
+        self.expect_keyword("endstream");
+
+        let data = match dict.get("Filter") {
+            Some(Object::Name(name)) if name == "FlateDecode" => {
+                miniz_oxide::inflate::decompress_to_vec_zlib(&raw)
+                    .expect("invalid flate stream")
+            }
+            _ => raw.clone(),
+        };
+
+        Object::Stream(Stream { dict, raw, data })
+    }
+
+    fn object(&mut self) -> Object {
+        self.skip_whitespace();
+        match self.peek().expect("unexpected end of data") {
+            b'/' => {
+                self.pos += 1;
+                Object::Name(self.name())
+            }
+            b'(' => Object::Str(self.literal_string()),
+            b'<' if self.data.get(self.pos + 1) == Some(&b'<') => {
+                self.pos += 2;
+                let mut entries = vec![];
+                loop {
+                    self.skip_whitespace();
+                    if self.data[self.pos..].starts_with(b">>") {
+                        self.pos += 2;
+                        break;
+                    }
+                    let Object::Name(key) = self.object() else {
+                        panic!("dictionary key is not a name at {}", self.pos);
+                    };
+                    let value = self.object();
+                    entries.push((key, value));
+                }
+                Object::Dict(Dict(entries))
+            }
+            b'<' => Object::Str(self.hex_string()),
+            b'[' => {
+                self.pos += 1;
+                let mut items = vec![];
+                loop {
+                    self.skip_whitespace();
+                    if self.peek() == Some(b']') {
+                        self.pos += 1;
+                        break;
+                    }
+                    items.push(self.object());
+                }
+                Object::Array(items)
+            }
+            b'+' | b'-' | b'.' | b'0'..=b'9' => self.number(),
+            _ => match self.keyword().as_str() {
+                "true" => Object::Bool(true),
+                "false" => Object::Bool(false),
+                "null" => Object::Null,
+                other => panic!("unexpected keyword {other} at {}", self.pos),
+            },
+        }
+    }
+
+    fn number(&mut self) -> Object {
+        let word = std::str::from_utf8(self.word_at(self.pos)).unwrap();
+        self.pos += word.len();
+        if word.contains('.') {
+            return Object::Real(word.parse().expect("invalid real number"));
+        }
+
+        let value: i64 = word.parse().expect("invalid integer");
+
+        // An integer may start an indirect reference `id 0 R`.
+        let before = self.pos;
+        if value > 0 && self.peek_int() == Some(0) {
+            self.pos += self.word_at(self.pos).len();
+            self.skip_whitespace();
+            if self.word_at(self.pos) == b"R" {
+                self.pos += 1;
+                return Object::Ref(value as i32);
+            }
+        }
+        self.pos = before;
+        Object::Int(value)
+    }
+
+    fn name(&mut self) -> String {
+        let mut name = vec![];
+        while let Some(byte) = self.peek() {
+            if is_whitespace(byte) || is_delimiter(byte) {
+                break;
+            }
+            self.pos += 1;
+            if byte == b'#' {
+                let hex =
+                    std::str::from_utf8(&self.data[self.pos..self.pos + 2]).unwrap();
+                name.push(u8::from_str_radix(hex, 16).expect("invalid name escape"));
+                self.pos += 2;
+            } else {
+                name.push(byte);
+            }
+        }
+        String::from_utf8(name).expect("name is not UTF-8")
+    }
+
+    fn literal_string(&mut self) -> Vec<u8> {
+        self.pos += 1;
+        let mut bytes = vec![];
+        let mut depth = 0;
+        loop {
+            match self.eat() {
+                b'(' => {
+                    depth += 1;
+                    bytes.push(b'(');
+                }
+                b')' if depth == 0 => break,
+                b')' => {
+                    depth -= 1;
+                    bytes.push(b')');
+                }
+                b'\\' => match self.eat() {
+                    b'n' => bytes.push(b'\n'),
+                    b'r' => bytes.push(b'\r'),
+                    b't' => bytes.push(b'\t'),
+                    b'b' => bytes.push(0x08),
+                    b'f' => bytes.push(0x0C),
+                    b'\r' => {
+                        if self.peek() == Some(b'\n') {
+                            self.pos += 1;
+                        }
+                    }
+                    b'\n' => {}
+                    digit @ b'0'..=b'7' => {
+                        let mut value = u32::from(digit - b'0');
+                        for _ in 0..2 {
+                            match self.peek() {
+                                Some(digit @ b'0'..=b'7') => {
+                                    value = value * 8 + u32::from(digit - b'0');
+                                    self.pos += 1;
+                                }
+                                _ => break,
+                            }
+                        }
+                        bytes.push(value as u8);
+                    }
+                    other => bytes.push(other),
+                },
+                other => bytes.push(other),
+            }
+        }
+        bytes
+    }
+
+    fn hex_string(&mut self) -> Vec<u8> {
+        self.pos += 1;
+        let mut digits = vec![];
+        loop {
+            match self.eat() {
+                b'>' => break,
+                byte if is_whitespace(byte) => {}
+                byte => digits
+                    .push((byte as char).to_digit(16).expect("invalid hex string") as u8),
+            }
+        }
+        if digits.len() % 2 == 1 {
+            digits.push(0);
+        }
+        digits.chunks(2).map(|pair| pair[0] << 4 | pair[1]).collect()
+    }
+}
+
+fn is_whitespace(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\n' | b'\r' | 0x0C | 0x00)
+}
+
+fn is_delimiter(byte: u8) -> bool {
+    matches!(byte, b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%')
+}
+
+fn find_last(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).rposition(|window| window == needle)
+}
@@ -0,0 +1,1428 @@
+//! A rasterizer for exported PDF pages.
+//!
+//! It understands just the subset of PDF that the exporter writes and panics
+//! on anything else, so that a test can't pass because some content was
+//! silently skipped. Colors in ICC-based color spaces are converted to sRGB
+//! with their embedded profile.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use tiny_skia as sk;
+use ttf_parser::{GlyphId, OutlineBuilder};
+
+use super::parse::{parse_content, Dict, File, Object, Op};
+
+/// Render a page of a PDF file with the given number of pixels per point.
+///
+/// The page is drawn onto a white background.
+pub fn render(file: &File, page: &Dict, pixel_per_pt: f32) -> sk::Pixmap {
+    let media = file.get(page.get("MediaBox").expect("page without media box"));
+    let [x0, y0, x1, y1] = rect(media);
+    let width = (pixel_per_pt * (x1 - x0)).round().max(1.0) as u32;
+    let height = (pixel_per_pt * (y1 - y0)).round().max(1.0) as u32;
+
+    let mut canvas = sk::Pixmap::new(width, height).unwrap();
+    canvas.fill(sk::Color::WHITE);
+
+    let ctm = sk::Transform::from_row(
+        pixel_per_pt,
+        0.0,
+        0.0,
+        -pixel_per_pt,
+        -x0 * pixel_per_pt,
+        y1 * pixel_per_pt,
+    );
+
+    let renderer = Renderer {
+        file,
+        fonts: RefCell::default(),
+        icc: RefCell::default(),
+    };
+    let resources = page.get("Resources").map(|r| file.get(r)).and_then(Object::as_dict);
+    let mut data = vec![];
+    let contents = file.get(page.get("Contents").expect("page without contents"));
+    for content in contents.as_array().unwrap_or(std::slice::from_ref(contents)) {
+        data.extend_from_slice(&file.get(content).as_stream().unwrap().data);
+        data.push(b'\n');
+    }
+
+    // An isolated page group is composited onto the page as a whole.
+    let ops = parse_content(&data);
+    let group = page.get("Group").map(|g| file.get(g)).and_then(Object::as_dict);
+    if group.is_some_and(|group| group.is_true("I")) {
+        let mut layer = sk::Pixmap::new(width, height).unwrap();
+        renderer.run(&mut layer, &ops, resources, State::new(ctm));
+        canvas.draw_pixmap(
+            0,
+            0,
+            layer.as_ref(),
+            &sk::PixmapPaint::default(),
+            sk::Transform::identity(),
+            None,
+        );
+    } else {
+        renderer.run(&mut canvas, &ops, resources, State::new(ctm));
+    }
+    canvas
+}
+
+/// Renders content streams onto a canvas.
+struct Renderer<'a> {
+    file: &'a File,
+    /// The fonts by the object number of their dictionary.
+    fonts: RefCell<HashMap<i32, Rc<Font>>>,
+    /// The transforms from ICC profiles to sRGB by the object number of the
+    /// profile stream.
+    icc: RefCell<HashMap<i32, Rc<qcms::Transform>>>,
+}
+
+/// The graphics state.
+#[derive(Clone)]
+struct State {
+    ctm: sk::Transform,
+    /// The initial transform of the current content stream, which patterns
+    /// are relative to.
+    base: sk::Transform,
+    fill: Paint,
+    stroke: Paint,
+    fill_space: Object,
+    stroke_space: Object,
+    fill_alpha: f32,
+    stroke_alpha: f32,
+    blend_mode: sk::BlendMode,
+    line: sk::Stroke,
+    clip: Option<Rc<sk::Mask>>,
+    text: TextState,
+}
+
+impl State {
+    fn new(ctm: sk::Transform) -> Self {
+        let gray = Object::Name("DeviceGray".into());
+        Self {
+            ctm,
+            base: ctm,
+            fill: Paint::Color(sk::Color::BLACK),
+            stroke: Paint::Color(sk::Color::BLACK),
+            fill_space: gray.clone(),
+            stroke_space: gray,
+            fill_alpha: 1.0,
+            stroke_alpha: 1.0,
+            blend_mode: sk::BlendMode::SourceOver,
+            line: sk::Stroke {
+                width: 1.0,
+                miter_limit: 10.0,
+                ..Default::default()
+            },
+            clip: None,
+            text: TextState::default(),
+        }
+    }
+}
+
+/// A fill or stroke paint.
+#[derive(Clone)]
+enum Paint {
+    Color(sk::Color),
+    /// A pattern, given by its object.
+    Pattern(Object),
+}
+
+/// The text state.
+#[derive(Clone)]
+struct TextState {
+    font: Option<Rc<Font>>,
+    size: f32,
+    matrix: sk::Transform,
+    line_matrix: sk::Transform,
+    char_spacing: f32,
+    word_spacing: f32,
+    horizontal_scaling: f32,
+    leading: f32,
+    rise: f32,
+    mode: i64,
+}
+
+impl Default for TextState {
+    fn default() -> Self {
+        Self {
+            font: None,
+            size: 0.0,
+            matrix: sk::Transform::identity(),
+            line_matrix: sk::Transform::identity(),
+            char_spacing: 0.0,
+            word_spacing: 0.0,
+            horizontal_scaling: 1.0,
+            leading: 0.0,
+            rise: 0.0,
+            mode: 0,
+        }
+    }
+}
+
+/// An embedded CID font.
+struct Font {
+    /// The advance widths in thousandths of an em by CID.
+    widths: HashMap<u16, f32>,
+    /// The outlines of the glyphs in em units, by CID.
+    outlines: HashMap<u16, Option<sk::Path>>,
+}
+
+impl<'a> Renderer<'a> {
+    /// Run the operations of a content stream.
+    fn run(
+        &self,
+        canvas: &mut sk::Pixmap,
+        ops: &[Op],
+        resources: Option<&'a Dict>,
+        initial: State,
+    ) {
+        let mut stack = vec![];
+        let mut state = initial;
+        let mut path = sk::PathBuilder::new();
+        let mut current = (0.0, 0.0);
+        let mut clip = None;
+
+        for op in ops {
+            let n = |i: usize| {
+                op.operands[i].as_f64().expect("operand is not a number") as f32
+            };
+            match op.operator.as_str() {
+                "q" => stack.push(state.clone()),
+                "Q" => state = stack.pop().expect("unbalanced Q"),
+                "cm" => state.ctm = state.ctm.pre_concat(matrix(&op.operands)),
+
+                "w" => state.line.width = n(0),
+                "J" => {
+                    state.line.line_cap = match n(0) as i64 {
+                        0 => sk::LineCap::Butt,
+                        1 => sk::LineCap::Round,
+                        2 => sk::LineCap::Square,
+                        other => panic!("invalid line cap {other}"),
+                    }
+                }
+                "j" => {
+                    state.line.line_join = match n(0) as i64 {
+                        0 => sk::LineJoin::Miter,
+                        1 => sk::LineJoin::Round,
+                        2 => sk::LineJoin::Bevel,
+                        other => panic!("invalid line join {other}"),
+                    }
+                }
+                "M" => state.line.miter_limit = n(0),
+                "d" => {
+                    let mut array: Vec<f32> = op.operands[0]
+                        .as_numbers()
+                        .expect("invalid dash array")
+                        .into_iter()
+                        .map(|v| v as f32)
+                        .collect();
+                    if array.len() % 2 == 1 {
+                        array.extend(array.clone());
+                    }
+                    state.line.dash = if array.is_empty() {
+                        None
+                    } else {
+                        Some(sk::StrokeDash::new(array, n(1)).expect("invalid dash"))
+                    };
+                }
+                "gs" => self.set_ext_g_state(&mut state, resources, &op.operands[0]),
+                "i" | "ri" => {}
+
+                "m" => {
+                    path.move_to(n(0), n(1));
+                    current = (n(0), n(1));
+                }
+                "l" => {
+                    path.line_to(n(0), n(1));
+                    current = (n(0), n(1));
+                }
+                "c" => {
+                    path.cubic_to(n(0), n(1), n(2), n(3), n(4), n(5));
+                    current = (n(4), n(5));
+                }
+                "v" => {
+                    path.cubic_to(current.0, current.1, n(0), n(1), n(2), n(3));
+                    current = (n(2), n(3));
+                }
+                "y" => {
+                    path.cubic_to(n(0), n(1), n(2), n(3), n(2), n(3));
+                    current = (n(2), n(3));
+                }
+                "h" => path.close(),
+                "re" => {
+                    let (x, y, w, h) = (n(0), n(1), n(2), n(3));
+                    path.move_to(x, y);
+                    path.line_to(x + w, y);
+                    path.line_to(x + w, y + h);
+                    path.line_to(x, y + h);
+                    path.close();
+                    current = (x, y);
+                }
+                "W" => clip = Some(sk::FillRule::Winding),
+                "W*" => clip = Some(sk::FillRule::EvenOdd),
+
+                "f" | "F" | "f*" | "B" | "B*" | "b" | "b*" | "S" | "s" | "n" => {
+                    let operator = op.operator.as_str();
+                    if matches!(operator, "b" | "b*" | "s") {
+                        path.close();
+                    }
+                    let built = std::mem::take(&mut path).finish();
+                    if let Some(built) = &built {
+                        let rule = if operator.ends_with('*') {
+                            sk::FillRule::EvenOdd
+                        } else {
+                            sk::FillRule::Winding
+                        };
+                        if matches!(operator, "f" | "F" | "f*" | "B" | "B*" | "b" | "b*")
+                        {
+                            self.fill(canvas, built, rule, &state);
+                        }
+                        if matches!(operator, "B" | "B*" | "b" | "b*" | "S" | "s") {
+                            self.stroke(canvas, built, &state);
+                        }
+                    }
+                    if let Some(rule) = clip.take() {
+                        let empty = sk::PathBuilder::from_rect(
+                            sk::Rect::from_xywh(0.0, 0.0, 0.0, 0.0).unwrap(),
+                        );
+                        let built = built.unwrap_or(empty);
+                        let ctm = state.ctm;
+                        clip_to(canvas, &mut state, &built, rule, ctm);
+                    }
+                }
+
+                "g" | "G" | "rg" | "RG" | "k" | "K" => {
+                    let stroke = op.operator.chars().all(|c| c.is_ascii_uppercase());
+                    let space = match op.operator.to_ascii_lowercase().as_str() {
+                        "g" => "DeviceGray",
+                        "rg" => "DeviceRGB",
+                        _ => "DeviceCMYK",
+                    };
+                    let space = Object::Name(space.into());
+                    let color = self.color(&space, &op.numbers());
+                    if stroke {
+                        (state.stroke_space, state.stroke) = (space, Paint::Color(color));
+                    } else {
+                        (state.fill_space, state.fill) = (space, Paint::Color(color));
+                    }
+                }
+                "cs" | "CS" => {
+                    let space = self.color_space(resources, &op.operands[0]);
+                    let components = components(self.file, &space);
+                    let initial = if name_of(&space) == Some("Pattern") {
+                        Paint::Color(sk::Color::BLACK)
+                    } else {
+                        Paint::Color(self.color(&space, &vec![0.0; components]))
+                    };
+                    if op.operator == "CS" {
+                        (state.stroke_space, state.stroke) = (space, initial);
+                    } else {
+                        (state.fill_space, state.fill) = (space, initial);
+                    }
+                }
+                "sc" | "scn" | "SC" | "SCN" => {
+                    let stroke = op.operator.starts_with('S');
+                    let space =
+                        if stroke { &state.stroke_space } else { &state.fill_space };
+                    let paint = match op.operands.last() {
+                        Some(Object::Name(name)) => {
+                            let pattern = self.resource(resources, "Pattern", name);
+                            Paint::Pattern(pattern.clone())
+                        }
+                        _ => Paint::Color(self.color(space, &op.numbers())),
+                    };
+                    if stroke {
+                        state.stroke = paint;
+                    } else {
+                        state.fill = paint;
+                    }
+                }
+
+                "BT" => {
+                    state.text.matrix = sk::Transform::identity();
+                    state.text.line_matrix = sk::Transform::identity();
+                }
+                "ET" => {}
+                "Tf" => {
+                    let Object::Name(name) = &op.operands[0] else {
+                        panic!("invalid font")
+                    };
+                    let font = self.resource(resources, "Font", name);
+                    state.text.font = Some(self.font(font));
+                    state.text.size = n(1);
+                }
+                "Tc" => state.text.char_spacing = n(0),
+                "Tw" => state.text.word_spacing = n(0),
+                "Tz" => state.text.horizontal_scaling = n(0) / 100.0,
+                "TL" => state.text.leading = n(0),
+                "Ts" => state.text.rise = n(0),
+                "Tr" => state.text.mode = n(0) as i64,
+                "Td" | "TD" => {
+                    if op.operator == "TD" {
+                        state.text.leading = -n(1);
+                    }
+                    let moved = state.text.line_matrix.pre_translate(n(0), n(1));
+                    state.text.line_matrix = moved;
+                    state.text.matrix = moved;
+                }
+                "Tm" => {
+                    state.text.line_matrix = matrix(&op.operands);
+                    state.text.matrix = state.text.line_matrix;
+                }
+                "T*" => {
+                    let moved =
+                        state.text.line_matrix.pre_translate(0.0, -state.text.leading);
+                    state.text.line_matrix = moved;
+                    state.text.matrix = moved;
+                }
+                "Tj" => self.show(canvas, &mut state, &op.operands[0]),
+                "TJ" => {
+                    for item in op.operands[0].as_array().expect("invalid TJ array") {
+                        match item {
+                            Object::Str(_) => self.show(canvas, &mut state, item),
+                            _ => {
+                                let adjustment = item.as_f64().expect("invalid TJ item");
+                                let text = &mut state.text;
+                                let tx = -adjustment as f32 / 1000.0
+                                    * text.size
+                                    * text.horizontal_scaling;
+                                text.matrix = text.matrix.pre_translate(tx, 0.0);
+                            }
+                        }
+                    }
+                }
+
+                "Do" => {
+                    let Object::Name(name) = &op.operands[0] else {
+                        panic!("invalid Do")
+                    };
+                    let xobject = self.resource(resources, "XObject", name);
+                    self.draw_xobject(canvas, &state, xobject);
+                }
+
+                "BMC" | "BDC" | "EMC" | "MP" | "DP" | "d0" | "d1" => {}
+                other => panic!("unsupported operator {other}"),
+            }
+        }
+    }
+
+    /// Look up a resource of the given category, e.g. `XObject`.
+    fn resource(
+        &self,
+        resources: Option<&'a Dict>,
+        category: &str,
+        name: &str,
+    ) -> &'a Object {
+        let file = self.file;
+        resources
+            .and_then(|resources| resources.get(category))
+            .and_then(|dict| file.get(dict).as_dict())
+            .and_then(|dict| dict.get(name))
+            .map(|object| file.get(object))
+            .unwrap_or_else(|| panic!("missing {category} resource {name}"))
+    }
+
+    /// Resolve a color space operand.
+    fn color_space(&self, resources: Option<&'a Dict>, operand: &Object) -> Object {
+        match operand {
+            Object::Name(name)
+                if matches!(
+                    name.as_str(),
+                    "DeviceGray" | "DeviceRGB" | "DeviceCMYK" | "Pattern"
+                ) =>
+            {
+                operand.clone()
+            }
+            Object::Name(name) => self.resource(resources, "ColorSpace", name).clone(),
+            _ => panic!("invalid color space operand"),
+        }
+    }
+
+    /// Apply a graphics state parameter dictionary.
+    fn set_ext_g_state(
+        &self,
+        state: &mut State,
+        resources: Option<&'a Dict>,
+        name: &Object,
+    ) {
+        let Object::Name(name) = name else { panic!("invalid gs operand") };
+        let dict = self.resource(resources, "ExtGState", name).as_dict().unwrap();
+        for (key, value) in &dict.0 {
+            let value = self.file.get(value);
+            match key.as_str() {
+                "CA" => state.stroke_alpha = value.as_f64().unwrap() as f32,
+                "ca" => state.fill_alpha = value.as_f64().unwrap() as f32,
+                "BM" => state.blend_mode = blend_mode(value.as_name().unwrap()),
+                "SMask" => {
+                    assert_eq!(value.as_name(), Some("None"), "unsupported soft mask")
+                }
+                "Type" | "OP" | "op" | "OPM" | "SA" | "AIS" | "TK" => {}
+                other => panic!("unsupported graphics state parameter {other}"),
+            }
+        }
+    }
+
+    /// Fill a path in user space.
+    fn fill(
+        &self,
+        canvas: &mut sk::Pixmap,
+        path: &sk::Path,
+        rule: sk::FillRule,
+        state: &State,
+    ) {
+        let paint = self.paint(canvas, &state.fill, state.fill_alpha, path, state, None);
+        if let Some(paint) = paint {
+            canvas.fill_path(path, &paint, rule, state.ctm, state.clip.as_deref());
+        }
+    }
+
+    /// Stroke a path in user space.
+    fn stroke(&self, canvas: &mut sk::Pixmap, path: &sk::Path, state: &State) {
+        let paint = self.paint(
+            canvas,
+            &state.stroke,
+            state.stroke_alpha,
+            path,
+            state,
+            Some(&state.line),
+        );
+        if let Some(paint) = paint {
+            canvas.stroke_path(
+                path,
+                &paint,
+                &state.line,
+                state.ctm,
+                state.clip.as_deref(),
+            );
+        }
+    }
+
+    /// Create a tiny-skia paint. Patterns are rendered into a layer that
+    /// covers the path's bounds.
+    fn paint(
+        &self,
+        canvas: &sk::Pixmap,
+        paint: &Paint,
+        alpha: f32,
+        path: &sk::Path,
+        state: &State,
+        stroke: Option<&sk::Stroke>,
+    ) -> Option<sk::Paint<'static>> {
+        let mut sk_paint = sk::Paint {
+            anti_alias: true,
+            blend_mode: state.blend_mode,
+            ..Default::default()
+        };
+
+        match paint {
+            Paint::Color(color) => {
+                let mut color = *color;
+                color.set_alpha(alpha);
+                sk_paint.set_color(color);
+            }
+            Paint::Pattern(pattern) => {
+                let outline = match stroke {
+                    Some(stroke) => path.stroke(stroke, 1.0)?,
+                    None => path.clone(),
+                };
+                let bounds = outline.transform(state.ctm)?.bounds();
+                let layer = self.pattern(canvas, pattern, state, bounds);
+                // The shader borrows the layer, so we give it a static
+                // lifetime. Tests render few enough patterns that leaking
+                // them doesn't matter.
+                let layer: &'static sk::Pixmap = Box::leak(Box::new(layer));
+                sk_paint.shader = sk::Pattern::new(
+                    layer.as_ref(),
+                    sk::SpreadMode::Pad,
+                    sk::FilterQuality::Nearest,
+                    alpha,
+                    state.ctm.invert()?,
+                );
+            }
+        }
+
+        Some(sk_paint)
+    }
+
+    /// Render a pattern into a layer of the canvas' size, within the given
+    /// device space bounds.
+    fn pattern(
+        &self,
+        canvas: &sk::Pixmap,
+        pattern: &Object,
+        state: &State,
+        bounds: sk::Rect,
+    ) -> sk::Pixmap {
+        let dict = pattern.as_dict().expect("invalid pattern");
+        let transform = state.base.pre_concat(
+            dict.get("Matrix").map_or(sk::Transform::identity(), |m| {
+                matrix(self.file.get(m).as_array().unwrap())
+            }),
+        );
+
+        let mut layer = sk::Pixmap::new(canvas.width(), canvas.height()).unwrap();
+        match dict.int("PatternType") {
+            Some(1) => self.tiling_pattern(&mut layer, pattern, transform, bounds),
+            Some(2) => {
+                let shading = self.file.get(dict.get("Shading").unwrap());
+                self.shading(&mut layer, shading, transform, bounds);
+            }
+            other => panic!("invalid pattern type {other:?}"),
+        }
+        layer
+    }
+
+    /// Render the cells of a tiling pattern that intersect the bounds.
+    fn tiling_pattern(
+        &self,
+        layer: &mut sk::Pixmap,
+        pattern: &'a Object,
+        transform: sk::Transform,
+        bounds: sk::Rect,
+    ) {
+        let dict = pattern.as_dict().unwrap();
+        let stream = pattern.as_stream().expect("tiling pattern is not a stream");
+        assert_eq!(dict.int("PaintType"), Some(1), "uncolored tiling pattern");
+        let bbox = rect(self.file.get(dict.get("BBox").unwrap()));
+        let x_step = dict.f64("XStep").unwrap() as f32;
+        let y_step = dict.f64("YStep").unwrap() as f32;
+        let resources = dict
+            .get("Resources")
+            .map(|r| self.file.get(r))
+            .and_then(Object::as_dict);
+        let ops = parse_content(&stream.data);
+
+        // Find the cells that cover the bounds in pattern space.
+        let inverse = transform.invert().expect("singular pattern matrix");
+        let corners = [
+            (bounds.left(), bounds.top()),
+            (bounds.right(), bounds.top()),
+            (bounds.left(), bounds.bottom()),
+            (bounds.right(), bounds.bottom()),
+        ]
+        .map(|(x, y)| {
+            let mut p = sk::Point::from_xy(x, y);
+            inverse.map_point(&mut p);
+            p
+        });
+        let min_x = corners.iter().map(|p| p.x).fold(f32::INFINITY, f32::min);
+        let max_x = corners.iter().map(|p| p.x).fold(f32::NEG_INFINITY, f32::max);
+        let min_y = corners.iter().map(|p| p.y).fold(f32::INFINITY, f32::min);
+        let max_y = corners.iter().map(|p| p.y).fold(f32::NEG_INFINITY, f32::max);
+        let columns = ((min_x - bbox[2]) / x_step).floor() as i32
+            ..=((max_x - bbox[0]) / x_step).ceil() as i32;
+        let rows = ((min_y - bbox[3]) / y_step).floor() as i32
+            ..=((max_y - bbox[1]) / y_step).ceil() as i32;
+        let cells = columns.clone().count() * rows.clone().count();
+        assert!(cells <= 100_000, "too many pattern cells");
+
+        for i in columns {
+            for j in rows.clone() {
+                let cell = transform.pre_translate(i as f32 * x_step, j as f32 * y_step);
+                let mut state = State::new(cell);
+                let bbox =
+                    sk::Rect::from_ltrb(bbox[0], bbox[1], bbox[2], bbox[3]).unwrap();
+                let bbox = sk::PathBuilder::from_rect(bbox);
+                clip_to(layer, &mut state, &bbox, sk::FillRule::Winding, cell);
+                self.run(layer, &ops, resources, state);
+            }
+        }
+    }
+
+    /// Render an axial or radial shading within the bounds.
+    fn shading(
+        &self,
+        layer: &mut sk::Pixmap,
+        shading: &Object,
+        transform: sk::Transform,
+        bounds: sk::Rect,
+    ) {
+        let dict = shading.as_dict().unwrap();
+        let kind = dict.int("ShadingType").unwrap();
+        assert!(matches!(kind, 2 | 3), "unsupported shading type {kind}");
+        let coords = self.file.get(dict.get("Coords").unwrap()).as_numbers().unwrap();
+        let domain = dict
+            .get("Domain")
+            .map_or(vec![0.0, 1.0], |d| self.file.get(d).as_numbers().unwrap());
+        let extend = dict.get("Extend").map_or([false; 2], |e| {
+            let items = self.file.get(e).as_array().unwrap();
+            [items[0] == Object::Bool(true), items[1] == Object::Bool(true)]
+        });
+        let space = self.file.get(dict.get("ColorSpace").unwrap()).clone();
+        let function = self.file.get(dict.get("Function").unwrap());
+        let inverse = transform.invert().expect("singular shading matrix");
+
+        // The pixels to compute and the shading parameter at each of them.
+        let x_range = bounds.left().floor().max(0.0) as u32
+            ..(bounds.right().ceil() as u32).min(layer.width());
+        let y_range = bounds.top().floor().max(0.0) as u32
+            ..(bounds.bottom().ceil() as u32).min(layer.height());
+        let mut pixels = vec![];
+        let mut samples = vec![];
+        for y in y_range {
+            for x in x_range.clone() {
+                let mut p = sk::Point::from_xy(x as f32 + 0.5, y as f32 + 0.5);
+                inverse.map_point(&mut p);
+                let (px, py) = (f64::from(p.x), f64::from(p.y));
+                let s = if kind == 2 {
+                    axial_parameter(&coords, px, py)
+                } else {
+                    radial_parameter(&coords, px, py)
+                };
+                let Some(s) = s else { continue };
+                let s = if s < 0.0 && extend[0] {
+                    0.0
+                } else if s > 1.0 && extend[1] {
+                    1.0
+                } else if !(0.0..=1.0).contains(&s) {
+                    continue;
+                } else {
+                    s
+                };
+                let t = domain[0] + s * (domain[1] - domain[0]);
+                pixels.push((x, y));
+                samples.extend(self.function(function, &[t]));
+            }
+        }
+
+        let rgb = self.colors_to_rgb(&space, &samples);
+        let width = layer.width();
+        let data = layer.data_mut();
+        for ((x, y), rgb) in pixels.into_iter().zip(rgb.chunks_exact(3)) {
+            let i = 4 * (y * width + x) as usize;
+            data[i..i + 4].copy_from_slice(&[rgb[0], rgb[1], rgb[2], 255]);
+        }
+    }
+
+    /// Evaluate a function.
+    fn function(&self, function: &Object, input: &[f64]) -> Vec<f64> {
+        let dict = function.as_dict().expect("invalid function");
+        let output = match dict.int("FunctionType") {
+            Some(2) => {
+                let c0 = dict.get("C0").map_or(vec![0.0], |c| c.as_numbers().unwrap());
+                let c1 = dict.get("C1").map_or(vec![1.0], |c| c.as_numbers().unwrap());
+                let n = dict.f64("N").unwrap();
+                let x = input[0].powf(n);
+                c0.iter().zip(&c1).map(|(a, b)| a + x * (b - a)).collect()
+            }
+            Some(3) => {
+                let domain = dict.get("Domain").unwrap().as_numbers().unwrap();
+                let functions = self.file.get(dict.get("Functions").unwrap());
+                let functions = functions.as_array().unwrap();
+                let bounds =
+                    self.file.get(dict.get("Bounds").unwrap()).as_numbers().unwrap();
+                let encode =
+                    self.file.get(dict.get("Encode").unwrap()).as_numbers().unwrap();
+                let x = input[0].clamp(domain[0], domain[1]);
+                let k = bounds.iter().take_while(|&&bound| x >= bound).count();
+                let low = if k == 0 { domain[0] } else { bounds[k - 1] };
+                let high = if k == bounds.len() { domain[1] } else { bounds[k] };
+                let t = if high > low { (x - low) / (high - low) } else { 0.0 };
+                let x = encode[2 * k] + t * (encode[2 * k + 1] - encode[2 * k]);
+                self.function(self.file.get(&functions[k]), &[x])
+            }
+            Some(4) => {
+                let stream =
+                    function.as_stream().expect("PostScript function without stream");
+                postscript(&stream.data, input)
+            }
+            other => panic!("unsupported function type {other:?}"),
+        };
+
+        match dict.get("Range").and_then(Object::as_numbers) {
+            Some(range) => output
+                .iter()
+                .enumerate()
+                .map(|(i, v)| v.clamp(range[2 * i], range[2 * i + 1]))
+                .collect(),
+            None => output,
+        }
+    }
+
+    /// Convert a color to sRGB.
+    fn color(&self, space: &Object, components: &[f64]) -> sk::Color {
+        let rgb = self.colors_to_rgb(space, components);
+        sk::Color::from_rgba8(rgb[0], rgb[1], rgb[2], 255)
+    }
+
+    /// Convert colors with consecutive components to 8-bit sRGB.
+    fn colors_to_rgb(&self, space: &Object, components: &[f64]) -> Vec<u8> {
+        let to_u8 = |v: f64| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+        match space {
+            Object::Name(name) => match name.as_str() {
+                "DeviceGray" => components.iter().flat_map(|&v| [to_u8(v); 3]).collect(),
+                "DeviceRGB" => components.iter().map(|&v| to_u8(v)).collect(),
+                "DeviceCMYK" => components
+                    .chunks_exact(4)
+                    .flat_map(|cmyk| {
+                        let k = 1.0 - cmyk[3];
+                        [0, 1, 2].map(|i| to_u8((1.0 - cmyk[i]) * k))
+                    })
+                    .collect(),
+                other => panic!("unsupported color space {other}"),
+            },
+            Object::Array(items) => match items[0].as_name().unwrap() {
+                "ICCBased" => {
+                    let profile = items[1].as_ref().expect("ICC profile is not indirect");
+                    let stream = self.file.get(&items[1]).as_stream().unwrap();
+                    let n = stream.dict.int("N").unwrap();
+                    let input: Vec<u8> = components.iter().map(|&v| to_u8(v)).collect();
+                    match n {
+                        1 | 3 => {
+                            let transform = self.icc_transform(profile, n == 1);
+                            let mut output = vec![0; input.len() / n as usize * 3];
+                            transform.convert(&input, &mut output);
+                            output
+                        }
+                        4 => self.colors_to_rgb(
+                            &Object::Name("DeviceCMYK".into()),
+                            components,
+                        ),
+                        _ => panic!("invalid ICC profile component count {n}"),
+                    }
+                }
+                "CalRGB" => {
+                    let dict = items[1].as_dict().unwrap();
+                    let m = dict.get("Matrix").unwrap().as_numbers().unwrap();
+                    let gamma = dict
+                        .get("Gamma")
+                        .map_or(vec![1.0; 3], |g| g.as_numbers().unwrap());
+                    components
+                        .chunks_exact(3)
+                        .flat_map(|abc| {
+                            let abc = [0, 1, 2].map(|i| abc[i].max(0.0).powf(gamma[i]));
+                            let xyz = [0, 1, 2].map(|j| {
+                                (0..3).map(|i| m[3 * i + j] * abc[i]).sum::<f64>()
+                            });
+                            xyz_to_srgb(xyz).map(to_u8)
+                        })
+                        .collect()
+                }
+                "DeviceN" | "Separation" => {
+                    let n = match items[0].as_name() {
+                        Some("DeviceN") => {
+                            self.file.get(&items[1]).as_array().unwrap().len()
+                        }
+                        _ => 1,
+                    };
+                    let alternate = self.file.get(&items[2]).clone();
+                    let tint = self.file.get(&items[3]);
+                    let converted: Vec<f64> = components
+                        .chunks_exact(n)
+                        .flat_map(|color| self.function(tint, color))
+                        .collect();
+                    self.colors_to_rgb(&alternate, &converted)
+                }
+                other => panic!("unsupported color space {other}"),
+            },
+            _ => panic!("invalid color space"),
+        }
+    }
+
+    /// The transform from the ICC profile with the given object number to
+    /// sRGB.
+    fn icc_transform(&self, profile: i32, gray: bool) -> Rc<qcms::Transform> {
+        self.icc
+            .borrow_mut()
+            .entry(profile)
+            .or_insert_with(|| {
+                let reference = Object::Ref(profile);
+                let data = &self.file.get(&reference).as_stream().unwrap().data;
+                let input =
+                    qcms::Profile::new_from_slice(data, false).expect("invalid profile");
+                let mut output = qcms::Profile::new_sRGB();
+                output.precache_output_transform();
+                let ty = if gray { qcms::DataType::Gray8 } else { qcms::DataType::RGB8 };
+                let transform = qcms::Transform::new_to(
+                    &input,
+                    &output,
+                    ty,
+                    qcms::DataType::RGB8,
+                    qcms::Intent::Perceptual,
+                )
+                .expect("failed to create color transform");
+                Rc::new(transform)
+            })
+            .clone()
+    }
+
+    /// Load a font.
+    fn font(&self, font: &Object) -> Rc<Font> {
+        let id = self
+            .file
+            .objects
+            .iter()
+            .find(|(_, object)| std::ptr::eq(*object, font))
+            .map(|(&id, _)| id)
+            .expect("font is not an indirect object");
+        self.fonts
+            .borrow_mut()
+            .entry(id)
+            .or_insert_with(|| Rc::new(self.load_font(font)))
+            .clone()
+    }
+
+    /// Load a Type 0 font with its descendant CID font.
+    fn load_font(&self, font: &Object) -> Font {
+        let file = self.file;
+        let dict = font.as_dict().unwrap();
+        assert_eq!(dict.name("Subtype"), Some("Type0"), "unsupported font type");
+        assert_eq!(dict.name("Encoding"), Some("Identity-H"));
+        let descendants =
+            file.get(dict.get("DescendantFonts").unwrap()).as_array().unwrap();
+        let cid = file.get(&descendants[0]).as_dict().unwrap();
+
+        // Read the widths, which are given as `c [w1 w2 ...]` or
+        // `first last w`.
+        let mut widths = HashMap::new();
+        let array = file.get(cid.get("W").unwrap()).as_array().unwrap();
+        let mut i = 0;
+        while i < array.len() {
+            let first = array[i].as_int().unwrap() as u16;
+            if let Some(list) = array[i + 1].as_numbers() {
+                for (k, w) in list.into_iter().enumerate() {
+                    widths.insert(first + k as u16, w as f32);
+                }
+                i += 2;
+            } else {
+                let last = array[i + 1].as_int().unwrap() as u16;
+                let w = array[i + 2].as_f64().unwrap() as f32;
+                for c in first..=last {
+                    widths.insert(c, w);
+                }
+                i += 3;
+            }
+        }
+
+        let descriptor = file.get(cid.get("FontDescriptor").unwrap()).as_dict().unwrap();
+        let mut outlines = HashMap::new();
+        if let Some(program) = descriptor.get("FontFile2") {
+            // A TrueType font whose glyph IDs are the CIDs.
+            assert_eq!(cid.name("CIDToGIDMap"), Some("Identity"));
+            let data = &file.get(program).as_stream().unwrap().data;
+            let face = ttf_parser::Face::parse(data, 0).expect("invalid TrueType font");
+            let scale = 1.0 / f32::from(face.units_per_em());
+            for gid in 0..face.number_of_glyphs() {
+                let mut builder = PathBuilder::new(scale);
+                face.outline_glyph(GlyphId(gid), &mut builder);
+                outlines.insert(gid, builder.0.finish());
+            }
+        } else {
+            // A bare CFF font, whose charset maps glyph IDs to CIDs if it is
+            // CID-keyed.
+            let program = descriptor.get("FontFile3").expect("font without program");
+            let stream = file.get(program).as_stream().unwrap();
+            assert_eq!(stream.dict.name("Subtype"), Some("CIDFontType0C"));
+            let table =
+                ttf_parser::cff::Table::parse(&stream.data).expect("invalid CFF font");
+            let scale = table.matrix().sx;
+            for gid in 0..table.number_of_glyphs() {
+                let cid = table.glyph_cid(GlyphId(gid)).unwrap_or(gid);
+                let mut builder = PathBuilder::new(scale);
+                let _ = table.outline(GlyphId(gid), &mut builder);
+                outlines.insert(cid, builder.0.finish());
+            }
+        }
+
+        Font { widths, outlines }
+    }
+
+    /// Show a string of glyphs.
+    fn show(&self, canvas: &mut sk::Pixmap, state: &mut State, string: &Object) {
+        let bytes = string.as_bytes().expect("invalid text string");
+        let font = state.text.font.clone().expect("text without font");
+        for code in bytes.chunks_exact(2) {
+            let cid = u16::from_be_bytes([code[0], code[1]]);
+            let text = &state.text;
+            let glyph_space = sk::Transform::from_row(
+                text.size * text.horizontal_scaling,
+                0.0,
+                0.0,
+                text.size,
+                0.0,
+                text.rise,
+            );
+            let to_user = text.matrix.pre_concat(glyph_space);
+
+            let outline = font.outlines.get(&cid).expect("glyph is not in the font");
+            if let Some(path) =
+                outline.as_ref().and_then(|p| p.clone().transform(to_user))
+            {
+                match text.mode {
+                    0 => self.fill(canvas, &path, sk::FillRule::Winding, state),
+                    1 => self.stroke(canvas, &path, state),
+                    2 => {
+                        self.fill(canvas, &path, sk::FillRule::Winding, state);
+                        self.stroke(canvas, &path, state);
+                    }
+                    3 => {}
+                    mode => panic!("unsupported text rendering mode {mode}"),
+                }
+            }
+
+            let width = font.widths.get(&cid).copied().unwrap_or(0.0);
+            let text = &mut state.text;
+            let tx = (width / 1000.0 * text.size + text.char_spacing)
+                * text.horizontal_scaling;
+            text.matrix = text.matrix.pre_translate(tx, 0.0);
+        }
+    }
+
+    /// Draw an image or form XObject.
+    fn draw_xobject(&self, canvas: &mut sk::Pixmap, state: &State, xobject: &'a Object) {
+        let stream = xobject.as_stream().expect("XObject is not a stream");
+        let dict = &stream.dict;
+        match dict.name("Subtype") {
+            Some("Image") => self.draw_image(canvas, state, xobject),
+            Some("Form") => {
+                let ctm = state.ctm.pre_concat(
+                    dict.get("Matrix").map_or(sk::Transform::identity(), |m| {
+                        matrix(self.file.get(m).as_array().unwrap())
+                    }),
+                );
+                let resources = dict
+                    .get("Resources")
+                    .map(|r| self.file.get(r))
+                    .and_then(Object::as_dict);
+                let ops = parse_content(&stream.data);
+                let bbox = rect(self.file.get(dict.get("BBox").unwrap()));
+                let bbox =
+                    sk::Rect::from_ltrb(bbox[0], bbox[1], bbox[2], bbox[3]).unwrap();
+                let bbox = sk::PathBuilder::from_rect(bbox);
+
+                if dict.contains("Group") {
+                    // A transparency group is composited as a whole.
+                    let mut layer =
+                        sk::Pixmap::new(canvas.width(), canvas.height()).unwrap();
+                    let mut inner = State::new(ctm);
+                    clip_to(&layer, &mut inner, &bbox, sk::FillRule::Winding, ctm);
+                    self.run(&mut layer, &ops, resources, inner);
+                    let paint = sk::PixmapPaint {
+                        opacity: state.fill_alpha,
+                        blend_mode: state.blend_mode,
+                        quality: sk::FilterQuality::Nearest,
+                    };
+                    canvas.draw_pixmap(
+                        0,
+                        0,
+                        layer.as_ref(),
+                        &paint,
+                        sk::Transform::identity(),
+                        state.clip.as_deref(),
+                    );
+                } else {
+                    let mut inner = state.clone();
+                    inner.ctm = ctm;
+                    inner.base = ctm;
+                    clip_to(canvas, &mut inner, &bbox, sk::FillRule::Winding, ctm);
+                    self.run(canvas, &ops, resources, inner);
+                }
+            }
+            other => panic!("unsupported XObject subtype {other:?}"),
+        }
+    }
+
+    /// Draw an image XObject into the unit square.
+    fn draw_image(&self, canvas: &mut sk::Pixmap, state: &State, image: &Object) {
+        let dict = image.as_dict().unwrap();
+        let pixmap = self.decode_image(image, Some(state));
+        let (w, h) = (pixmap.width() as f32, pixmap.height() as f32);
+        let transform = state.ctm.pre_concat(sk::Transform::from_row(
+            1.0 / w,
+            0.0,
+            0.0,
+            -1.0 / h,
+            0.0,
+            1.0,
+        ));
+        let quality = if dict.is_true("Interpolate") {
+            sk::FilterQuality::Bicubic
+        } else {
+            sk::FilterQuality::Nearest
+        };
+        let paint = sk::PixmapPaint {
+            opacity: state.fill_alpha,
+            blend_mode: state.blend_mode,
+            quality,
+        };
+        canvas.draw_pixmap(
+            0,
+            0,
+            pixmap.as_ref(),
+            &paint,
+            transform,
+            state.clip.as_deref(),
+        );
+    }
+
+    /// Decode an image XObject, including its soft mask, into a pixmap.
+    ///
+    /// Stencil masks are painted with the fill color of the state.
+    fn decode_image(&self, image: &Object, state: Option<&State>) -> sk::Pixmap {
+        let file = self.file;
+        let stream = image.as_stream().unwrap();
+        let dict = &stream.dict;
+        let width = dict.int("Width").unwrap() as u32;
+        let height = dict.int("Height").unwrap() as u32;
+        let count = (width * height) as usize;
+        let bits = dict.int("BitsPerComponent").unwrap_or(1) as usize;
+        let stencil = dict.is_true("ImageMask");
+        let space = if stencil {
+            Object::Name("DeviceGray".into())
+        } else {
+            file.get(dict.get("ColorSpace").expect("image without color space"))
+                .clone()
+        };
+        let n = components(file, &space);
+
+        // Decode the samples into the range [0, 1].
+        let samples: Vec<f64> = match dict.name("Filter") {
+            Some("DCTDecode") => {
+                assert_eq!(bits, 8);
+                let mut decoder = jpeg_decoder::Decoder::new(stream.data.as_slice());
+                let pixels = decoder.decode().expect("invalid JPEG");
+                let info = decoder.info().unwrap();
+                assert_eq!(
+                    (u32::from(info.width), u32::from(info.height)),
+                    (width, height)
+                );
+                match info.pixel_format {
+                    jpeg_decoder::PixelFormat::L8 => assert_eq!(n, 1),
+                    jpeg_decoder::PixelFormat::RGB24 => assert_eq!(n, 3),
+                    // The decoder inverts CMYK data like Adobe applications
+                    // write it, but PDF readers take the data as it is.
+                    jpeg_decoder::PixelFormat::CMYK32 => {
+                        assert_eq!(n, 4);
+                        return self.finish_image(
+                            image,
+                            pixels.iter().map(|&v| f64::from(255 - v) / 255.0).collect(),
+                            &space,
+                            n,
+                            state,
+                        );
+                    }
+                    other => panic!("unsupported JPEG pixel format {other:?}"),
+                }
+                pixels.iter().map(|&v| f64::from(v) / 255.0).collect()
+            }
+            None | Some("FlateDecode") => {
+                let row_bits = width as usize * n * bits;
+                let row_bytes = row_bits.div_ceil(8);
+                assert_eq!(stream.data.len(), row_bytes * height as usize, "image size");
+                let max = ((1u32 << bits) - 1) as f64;
+                stream
+                    .data
+                    .chunks_exact(row_bytes)
+                    .flat_map(|row| {
+                        (0..width as usize * n).map(move |i| match bits {
+                            1 => f64::from(row[i / 8] >> (7 - i % 8) & 1),
+                            8 => f64::from(row[i]) / max,
+                            16 => {
+                                f64::from(u16::from_be_bytes([
+                                    row[2 * i],
+                                    row[2 * i + 1],
+                                ])) / max
+                            }
+                            _ => panic!("unsupported bit depth {bits}"),
+                        })
+                    })
+                    .collect()
+            }
+            other => panic!("unsupported image filter {other:?}"),
+        };
+        assert_eq!(samples.len(), count * n);
+        self.finish_image(image, samples, &space, n, state)
+    }
+
+    /// Turn decoded image samples into a pixmap, applying the decode array
+    /// and the soft mask.
+    fn finish_image(
+        &self,
+        image: &Object,
+        mut samples: Vec<f64>,
+        space: &Object,
+        n: usize,
+        state: Option<&State>,
+    ) -> sk::Pixmap {
+        let dict = image.as_dict().unwrap();
+        let width = dict.int("Width").unwrap() as u32;
+        let height = dict.int("Height").unwrap() as u32;
+        if let Some(decode) = dict.get("Decode").and_then(Object::as_numbers) {
+            for (i, sample) in samples.iter_mut().enumerate() {
+                let (min, max) = (decode[2 * (i % n)], decode[2 * (i % n) + 1]);
+                *sample = min + *sample * (max - min);
+            }
+        }
+
+        let mut pixmap = sk::Pixmap::new(width, height).unwrap();
+        if dict.is_true("ImageMask") {
+            // Samples of zero are painted with the fill color.
+            let state = state.expect("stencil mask without state");
+            let Paint::Color(color) = state.fill else {
+                panic!("stencil mask with pattern")
+            };
+            let color = color.to_color_u8();
+            for (pixel, &sample) in pixmap.pixels_mut().iter_mut().zip(&samples) {
+                if sample == 0.0 {
+                    *pixel = color.premultiply();
+                }
+            }
+            return pixmap;
+        }
+
+        let rgb = self.colors_to_rgb(space, &samples);
+        let alpha = match dict.get("SMask") {
+            Some(mask) => {
+                let mask = self.file.get(mask);
+                let mask_dict = mask.as_dict().unwrap();
+                assert_eq!(mask_dict.int("Width"), Some(i64::from(width)));
+                assert_eq!(mask_dict.int("Height"), Some(i64::from(height)));
+                let decoded = self.decode_image(mask, None);
+                decoded.pixels().iter().map(|p| p.demultiply().red()).collect()
+            }
+            None => vec![255; (width * height) as usize],
+        };
+
+        for ((pixel, rgb), &a) in
+            pixmap.pixels_mut().iter_mut().zip(rgb.chunks_exact(3)).zip(&alpha)
+        {
+            *pixel = sk::ColorU8::from_rgba(rgb[0], rgb[1], rgb[2], a).premultiply();
+        }
+        pixmap
+    }
+}
+
+/// Intersect the state's clip with a path.
+fn clip_to(
+    canvas: &sk::Pixmap,
+    state: &mut State,
+    path: &sk::Path,
+    rule: sk::FillRule,
+    transform: sk::Transform,
+) {
+    let mask = match &state.clip {
+        Some(clip) => {
+            let mut mask = (**clip).clone();
+            mask.intersect_path(path, rule, true, transform);
+            mask
+        }
+        None => {
+            let mut mask = sk::Mask::new(canvas.width(), canvas.height()).unwrap();
+            mask.fill_path(path, rule, true, transform);
+            mask
+        }
+    };
+    state.clip = Some(Rc::new(mask));
+}
+
+/// Builds tiny-skia paths from glyph outlines, scaling them to em units.
+struct PathBuilder(sk::PathBuilder, f32);
+
+impl PathBuilder {
+    fn new(scale: f32) -> Self {
+        Self(sk::PathBuilder::new(), scale)
+    }
+}
+
+impl OutlineBuilder for PathBuilder {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.0.move_to(x * self.1, y * self.1);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.0.line_to(x * self.1, y * self.1);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let s = self.1;
+        self.0.quad_to(x1 * s, y1 * s, x * s, y * s);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let s = self.1;
+        self.0.cubic_to(x1 * s, y1 * s, x2 * s, y2 * s, x * s, y * s);
+    }
+
+    fn close(&mut self) {
+        self.0.close();
+    }
+}
+
+/// The number of components of a color space.
+fn components(file: &File, space: &Object) -> usize {
+    match space {
+        Object::Name(name) => match name.as_str() {
+            "DeviceGray" | "Pattern" => 1,
+            "DeviceRGB" => 3,
+            "DeviceCMYK" => 4,
+            other => panic!("unsupported color space {other}"),
+        },
+        Object::Array(items) => match items[0].as_name().unwrap() {
+            "ICCBased" => {
+                let stream = file.get(&items[1]).as_stream().unwrap();
+                stream.dict.int("N").unwrap() as usize
+            }
+            "CalRGB" => 3,
+            "DeviceN" => file.get(&items[1]).as_array().unwrap().len(),
+            "Separation" => 1,
+            other => panic!("unsupported color space {other}"),
+        },
+        _ => panic!("invalid color space"),
+    }
+}
+
+/// The name of a color space given by name.
+fn name_of(space: &Object) -> Option<&str> {
+    space.as_name()
+}
+
+/// Convert CIE XYZ with a D65 white point to sRGB.
+fn xyz_to_srgb([x, y, z]: [f64; 3]) -> [f64; 3] {
+    let linear = [
+        3.2404542 * x - 1.5371385 * y - 0.4985314 * z,
+        -0.9692660 * x + 1.8760108 * y + 0.0415560 * z,
+        0.0556434 * x - 0.2040259 * y + 1.0572252 * z,
+    ];
+    linear.map(
+        |c| {
+            if c <= 0.0031308 {
+                12.92 * c
+            } else {
+                1.055 * c.powf(1.0 / 2.4) - 0.055
+            }
+        },
+    )
+}
+
+/// The parameter of an axial shading at a point.
+fn axial_parameter(coords: &[f64], x: f64, y: f64) -> Option<f64> {
+    let (dx, dy) = (coords[2] - coords[0], coords[3] - coords[1]);
+    let len = dx * dx + dy * dy;
+    (len > 0.0).then(|| ((x - coords[0]) * dx + (y - coords[1]) * dy) / len)
+}
+
+/// The largest parameter of a radial shading whose circle passes through a
+/// point and has a non-negative radius.
+fn radial_parameter(coords: &[f64], x: f64, y: f64) -> Option<f64> {
+    let (x0, y0, r0, x1, y1, r1) =
+        (coords[0], coords[1], coords[2], coords[3], coords[4], coords[5]);
+    let (cdx, cdy, dr) = (x1 - x0, y1 - y0, r1 - r0);
+    let (pdx, pdy) = (x - x0, y - y0);
+    let a = cdx * cdx + cdy * cdy - dr * dr;
+    let b = pdx * cdx + pdy * cdy + r0 * dr;
+    let c = pdx * pdx + pdy * pdy - r0 * r0;
+    let valid = |t: f64| r0 + t * dr >= 0.0;
+    if a.abs() < 1e-12 {
+        let t = c / (2.0 * b);
+        return (b != 0.0 && valid(t)).then_some(t);
+    }
+
+    let discriminant = b * b - a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let root = discriminant.sqrt();
+    let (t1, t2) = ((b + root) / a, (b - root) / a);
+    let (high, low) = if t1 > t2 { (t1, t2) } else { (t2, t1) };
+    [high, low].into_iter().find(|&t| valid(t))
+}
+
+/// Evaluate a PostScript calculator function.
+fn postscript(program: &[u8], input: &[f64]) -> Vec<f64> {
+    let text = std::str::from_utf8(program).expect("invalid PostScript");
+    let body = text.trim().strip_prefix('{').and_then(|t| t.strip_suffix('}'));
+    let mut stack = input.to_vec();
+    for token in body.expect("unbraced PostScript").split_whitespace() {
+        if let Ok(value) = token.parse::<f64>() {
+            stack.push(value);
+            continue;
+        }
+
+        let mut pop = || stack.pop().expect("PostScript stack underflow");
+        match token {
+            "add" | "sub" | "mul" | "div" => {
+                let (b, a) = (pop(), pop());
+                stack.push(match token {
+                    "add" => a + b,
+                    "sub" => a - b,
+                    "mul" => a * b,
+                    _ => a / b,
+                });
+            }
+            "neg" => {
+                let a = pop();
+                stack.push(-a);
+            }
+            "abs" => {
+                let a = pop();
+                stack.push(a.abs());
+            }
+            "pop" => {
+                pop();
+            }
+            "exch" => {
+                let (b, a) = (pop(), pop());
+                stack.extend([b, a]);
+            }
+            "dup" => {
+                let a = *stack.last().expect("PostScript stack underflow");
+                stack.push(a);
+            }
+            "index" => {
+                let n = pop() as usize;
+                stack.push(stack[stack.len() - 1 - n]);
+            }
+            "roll" => {
+                let (j, n) = (pop() as i64, pop() as usize);
+                let start = stack.len() - n;
+                let shift = j.rem_euclid(n as i64) as usize;
+                stack[start..].rotate_right(shift);
+            }
+            other => panic!("unsupported PostScript operator {other}"),
+        }
+    }
+    stack
+}
+
+/// The blend mode with the given PDF name.
+fn blend_mode(name: &str) -> sk::BlendMode {
+    match name {
+        "Normal" | "Compatible" => sk::BlendMode::SourceOver,
+        "Multiply" => sk::BlendMode::Multiply,
+        "Screen" => sk::BlendMode::Screen,
+        "Overlay" => sk::BlendMode::Overlay,
+        "Darken" => sk::BlendMode::Darken,
+        "Lighten" => sk::BlendMode::Lighten,
+        "ColorDodge" => sk::BlendMode::ColorDodge,
+        "ColorBurn" => sk::BlendMode::ColorBurn,
+        "HardLight" => sk::BlendMode::HardLight,
+        "SoftLight" => sk::BlendMode::SoftLight,
+        "Difference" => sk::BlendMode::Difference,
+        "Exclusion" => sk::BlendMode::Exclusion,
+        "Hue" => sk::BlendMode::Hue,
+        "Saturation" => sk::BlendMode::Saturation,
+        "Color" => sk::BlendMode::Color,
+        "Luminosity" => sk::BlendMode::Luminosity,
+        other => panic!("invalid blend mode {other}"),
+    }
+}
+
+/// A matrix given by six operands or array items.
+fn matrix(items: &[Object]) -> sk::Transform {
+    let m: Vec<f32> = items
+        .iter()
+        .map(|v| v.as_f64().expect("invalid matrix") as f32)
+        .collect();
+    sk::Transform::from_row(m[0], m[1], m[2], m[3], m[4], m[5])
+}
+
+/// A rectangle given by an array, normalized so that the first corner is the
+/// lower left one.
+fn rect(object: &Object) -> [f32; 4] {
+    let v = object.as_numbers().expect("invalid rectangle");
+    let (x0, x1) = (v[0].min(v[2]) as f32, v[0].max(v[2]) as f32);
+    let (y0, y1) = (v[1].min(v[3]) as f32, v[1].max(v[3]) as f32);
+    [x0, y0, x1, y1]
+}
@@ -3,8 +3,9 @@ use std::sync::Arc;
 use image::imageops::FilterType;
 use image::{GenericImageView, Rgba};
 use tiny_skia as sk;
+use typst::foundations::Smart;
 use typst::layout::Size;
-use typst::visualize::{Image, ImageKind};
+use typst::visualize::{Image, ImageKind, ImageScaling};
 
 use crate::{AbsExt, State};
 
@@ -62,12 +63,31 @@ fn scaled_texture(image: &Image, w: u32, h: u32) -> Option<Arc<sk::Pixmap>> {
     match image.kind() {
         ImageKind::Raster(raster) => {
             let downscale = w < raster.width();
-            let filter =
-                if downscale { FilterType::Lanczos3 } else { FilterType::CatmullRom };
+            let filter = match image.scaling() {
+                Smart::Custom(ImageScaling::Pixelated) => FilterType::Nearest,
+                _ if downscale => FilterType::Lanczos3,
+                _ => FilterType::CatmullRom,
+            };
             let buf = raster.dynamic().resize(w, h, filter);
+            let fill = image.fill().map(crate::paint::to_sk_color_u8);
             for ((_, _, src), dest) in buf.pixels().zip(pixmap.pixels_mut()) {
                 let Rgba([r, g, b, a]) = src;
-                *dest = sk::ColorU8::from_rgba(r, g, b, a).premultiply();
+                *dest = match fill {
+                    // Black pixels are painted with the fill and white ones
+                    // are transparent.
+                    Some(fill) => {
+                        let coverage = u16::from(255 - r) * u16::from(fill.alpha());
+                        let alpha = (coverage / 255) as u8;
+                        sk::ColorU8::from_rgba(
+                            fill.red(),
+                            fill.green(),
+                            fill.blue(),
+                            alpha,
+                        )
+                    }
+                    None => sk::ColorU8::from_rgba(r, g, b, a),
+                }
+                .premultiply();
             }
         }
         // Safety: We do not keep any references to tree nodes beyond the scope
@@ -1,14 +1,14 @@
 use tiny_skia as sk;
 use typst::layout::{Abs, Axes, Point, Ratio, Size};
 use typst::visualize::{
-    DashPattern, FixedStroke, Geometry, LineCap, LineJoin, Path, PathItem, Shape,
+    DashPattern, FixedStroke, Geometry, LineCap, LineJoin, PaintOrder, Path, PathItem,
+    Shape,
 };
 
 use crate::{paint, AbsExt, State};
 
 /// Render a geometrical shape into the canvas.
 pub fn render_shape(canvas: &mut sk::Pixmap, state: State, shape: &Shape) -> Option<()> {
-    let ts = state.transform;
     let path = match shape.geometry {
         Geometry::Line(target) => {
             let mut builder = sk::PathBuilder::new();
@@ -35,6 +35,23 @@ pub fn render_shape(canvas: &mut sk::Pixmap, state: State, shape: &Shape) -> Opt
         Geometry::Path(ref path) => convert_path(path)?,
     };
 
+    match shape.paint_order {
+        PaintOrder::FillStroke => {
+            render_fill(canvas, state, shape, &path);
+            render_stroke(canvas, state, shape, &path);
+        }
+        PaintOrder::StrokeFill => {
+            render_stroke(canvas, state, shape, &path);
+            render_fill(canvas, state, shape, &path);
+        }
+    }
+
+    Some(())
+}
+
+/// Fill a shape's path.
+fn render_fill(canvas: &mut sk::Pixmap, state: State, shape: &Shape, path: &sk::Path) {
+    let ts = state.transform;
     if let Some(fill) = &shape.fill {
         let mut pixmap = None;
         let mut paint: sk::Paint = paint::to_sk_paint(
@@ -52,9 +69,13 @@ pub fn render_shape(canvas: &mut sk::Pixmap, state: State, shape: &Shape) -> Opt
         }
 
         let rule = sk::FillRule::default();
-        canvas.fill_path(&path, &paint, rule, ts, state.mask);
+        canvas.fill_path(path, &paint, rule, ts, state.mask);
     }
+}
 
+/// Stroke a shape's path.
+fn render_stroke(canvas: &mut sk::Pixmap, state: State, shape: &Shape, path: &sk::Path) {
+    let ts = state.transform;
     if let Some(FixedStroke { paint, thickness, cap, join, dash, miter_limit }) =
         &shape.stroke
     {
@@ -64,32 +85,33 @@ pub fn render_shape(canvas: &mut sk::Pixmap, state: State, shape: &Shape) -> Opt
         if width > 0.0 {
             let dash = dash.as_ref().and_then(to_sk_dash_pattern);
 
+            // Lines and straight paths have no area, so their paint is
+            // relative to the area that their stroke covers. Otherwise, the
+            // gradient is sampled in the bounding box grown by the stroke.
             let bbox = shape.geometry.bbox_size();
-            let offset_bbox = (!matches!(shape.geometry, Geometry::Line(..)))
-                .then(|| offset_bounding_box(bbox, *thickness))
-                .unwrap_or(bbox);
-
-            let fill_transform =
-                (!matches!(shape.geometry, Geometry::Line(..))).then(|| {
-                    sk::Transform::from_translate(
-                        -thickness.to_f32(),
-                        -thickness.to_f32(),
-                    )
-                });
-
-            let gradient_map =
-                (!matches!(shape.geometry, Geometry::Line(..))).then(|| {
-                    (
-                        Point::new(
-                            -*thickness * state.pixel_per_pt as f64,
-                            -*thickness * state.pixel_per_pt as f64,
-                        ),
-                        Axes::new(
-                            Ratio::new(offset_bbox.x / bbox.x),
-                            Ratio::new(offset_bbox.y / bbox.y),
-                        ),
-                    )
-                });
+            let (offset, paint_box) = shape.geometry.stroke_paint_box(*thickness);
+            let has_area = shape.geometry.has_area();
+            let offset_bbox =
+                if has_area { offset_bounding_box(bbox, *thickness) } else { paint_box };
+
+            let fill_transform = Some(if has_area {
+                sk::Transform::from_translate(-thickness.to_f32(), -thickness.to_f32())
+            } else {
+                sk::Transform::from_translate(offset.x.to_f32(), offset.y.to_f32())
+            });
+
+            let gradient_map = has_area.then(|| {
+                (
+                    Point::new(
+                        -*thickness * state.pixel_per_pt as f64,
+                        -*thickness * state.pixel_per_pt as f64,
+                    ),
+                    Axes::new(
+                        Ratio::new(offset_bbox.x / bbox.x),
+                        Ratio::new(offset_bbox.y / bbox.y),
+                    ),
+                )
+            });
 
             let mut pixmap = None;
             let paint = paint::to_sk_paint(
@@ -108,11 +130,9 @@ pub fn render_shape(canvas: &mut sk::Pixmap, state: State, shape: &Shape) -> Opt
                 dash,
                 miter_limit: miter_limit.get() as f32,
             };
-            canvas.stroke_path(&path, &paint, &stroke, ts, state.mask);
+            canvas.stroke_path(path, &paint, &stroke, ts, state.mask);
         }
     }
-
-    Some(())
 }
 
 /// Convert a Typst path into a tiny-skia path.
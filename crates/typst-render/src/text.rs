@@ -6,7 +6,7 @@ use ttf_parser::{GlyphId, OutlineBuilder};
 use typst::layout::{Abs, Axes, Point, Size};
 use typst::text::color::{frame_for_glyph, is_color_glyph};
 use typst::text::{Font, TextItem};
-use typst::visualize::{FixedStroke, Paint};
+use typst::visualize::{FixedStroke, Paint, PaintOrder};
 
 use crate::paint::{self, GradientSampler, PaintSampler, PatternSampler};
 use crate::{shape, AbsExt, State};
@@ -31,7 +31,7 @@ pub fn render_text(canvas: &mut sk::Pixmap, state: State, text: &TextItem) {
         } else {
             let state =
                 state.pre_translate(Point::new(Abs::raw(offset as _), Abs::raw(0.0)));
-            render_outline_glyph(canvas, state, text, id);
+            render_outline_glyph(canvas, state, text, id, offset);
         }
 
         x += glyph.x_advance.at(text.size).to_f32();
@@ -39,11 +39,14 @@ pub fn render_text(canvas: &mut sk::Pixmap, state: State, text: &TextItem) {
 }
 
 /// Render an outline glyph into the canvas. This is the "normal" case.
+///
+/// The `offset` is the glyph's horizontal position in the text run.
 fn render_outline_glyph(
     canvas: &mut sk::Pixmap,
     state: State,
     text: &TextItem,
     id: GlyphId,
+    offset: f32,
 ) -> Option<()> {
     let ts = &state.transform;
     let ppem = text.size.to_f32() * ts.sy;
@@ -73,41 +76,64 @@ fn render_outline_glyph(
         // system is Y-up.
         let ts = ts.pre_scale(scale, -scale);
         let state_ts = state.pre_concat(sk::Transform::from_scale(scale, -scale));
-        let paint = paint::to_sk_paint(
-            &text.fill,
-            state_ts,
-            Size::zero(),
-            true,
-            None,
-            &mut pixmap,
-            None,
-        );
-        canvas.fill_path(&path, &paint, rule, ts, state.mask);
-
-        if let Some(FixedStroke { paint, thickness, cap, join, dash, miter_limit }) =
-            &text.stroke
-        {
-            if thickness.to_f32() > 0.0 {
-                let dash = dash.as_ref().and_then(shape::to_sk_dash_pattern);
-
-                let paint = paint::to_sk_paint(
-                    paint,
-                    state_ts,
-                    Size::zero(),
-                    true,
-                    None,
-                    &mut pixmap,
-                    None,
-                );
-                let stroke = sk::Stroke {
-                    width: thickness.to_f32() / scale, // When we scale the path, we need to scale the stroke width, too.
-                    line_cap: shape::to_sk_line_cap(*cap),
-                    line_join: shape::to_sk_line_join(*join),
-                    dash,
-                    miter_limit: miter_limit.get() as f32,
-                };
-
-                canvas.stroke_path(&path, &paint, &stroke, ts, state.mask);
+        let fill = |canvas: &mut sk::Pixmap, pixmap: &mut Option<Arc<sk::Pixmap>>| {
+            let paint = paint::to_sk_paint(
+                &text.fill,
+                state_ts,
+                Size::zero(),
+                true,
+                None,
+                pixmap,
+                None,
+            );
+            canvas.fill_path(&path, &paint, rule, ts, state.mask);
+        };
+
+        let stroke = |canvas: &mut sk::Pixmap, pixmap: &mut Option<Arc<sk::Pixmap>>| {
+            if let Some(FixedStroke { paint, thickness, cap, join, dash, miter_limit }) =
+                &text.stroke
+            {
+                if thickness.to_f32() > 0.0 {
+                    let dash = dash.as_ref().and_then(shape::to_sk_dash_pattern);
+
+                    // Paints that are relative to the text itself fill the
+                    // text run's box, which we map into the glyph's
+                    // coordinate system.
+                    let (origin, size) = text.bbox();
+                    let fill_transform =
+                        sk::Transform::from_scale(1.0 / scale, -1.0 / scale)
+                            .pre_translate(origin.x.to_f32() - offset, origin.y.to_f32());
+
+                    let paint = paint::to_sk_paint(
+                        paint,
+                        state_ts,
+                        size,
+                        true,
+                        Some(fill_transform),
+                        pixmap,
+                        None,
+                    );
+                    let stroke = sk::Stroke {
+                        width: thickness.to_f32() / scale, // When we scale the path, we need to scale the stroke width, too.
+                        line_cap: shape::to_sk_line_cap(*cap),
+                        line_join: shape::to_sk_line_join(*join),
+                        dash,
+                        miter_limit: miter_limit.get() as f32,
+                    };
+
+                    canvas.stroke_path(&path, &paint, &stroke, ts, state.mask);
+                }
+            }
+        };
+
+        match text.paint_order {
+            PaintOrder::FillStroke => {
+                fill(canvas, &mut pixmap);
+                stroke(canvas, &mut pixmap);
+            }
+            PaintOrder::StrokeFill => {
+                stroke(canvas, &mut pixmap);
+                fill(canvas, &mut pixmap);
             }
         }
         return Some(());
@@ -1,7 +1,9 @@
 use base64::Engine;
 use ecow::{eco_format, EcoString};
+use typst::foundations::Smart;
 use typst::layout::{Abs, Axes};
-use typst::visualize::{Image, ImageFormat, RasterFormat, VectorFormat};
+use typst::utils::hash128;
+use typst::visualize::{Image, ImageFormat, ImageScaling, RasterFormat, VectorFormat};
 
 use crate::SVGRenderer;
 
@@ -14,6 +16,49 @@ impl SVGRenderer {
         self.xml.write_attribute("width", &size.x.to_pt());
         self.xml.write_attribute("height", &size.y.to_pt());
         self.xml.write_attribute("preserveAspectRatio", "none");
+        if let Some(fill) = image.fill() {
+            let id = self.image_fills.insert_with(hash128(&fill), || fill);
+            self.xml.write_attribute_fmt("filter", format_args!("url(#{id})"));
+        }
+        match image.scaling() {
+            Smart::Auto => {}
+            Smart::Custom(ImageScaling::Smooth) => {
+                self.xml.write_attribute("style", "image-rendering: smooth");
+            }
+            Smart::Custom(ImageScaling::Pixelated) => {
+                self.xml.write_attribute("style", "image-rendering: pixelated");
+            }
+        }
+        self.xml.end_element();
+    }
+}
+
+impl SVGRenderer {
+    /// Write the filters that paint the black pixels of black-and-white
+    /// images with their fill and make their white pixels transparent.
+    pub(super) fn write_image_fills(&mut self) {
+        if self.image_fills.is_empty() {
+            return;
+        }
+
+        self.xml.start_element("defs");
+        self.xml.write_attribute("id", "image-fills");
+
+        for (id, fill) in self.image_fills.iter() {
+            let [r, g, b, a] = fill.to_rgb().to_vec4();
+            self.xml.start_element("filter");
+            self.xml.write_attribute("id", &id);
+            self.xml.write_attribute("color-interpolation-filters", "sRGB");
+            self.xml.start_element("feColorMatrix");
+            self.xml.write_attribute("type", "matrix");
+            self.xml.write_attribute_fmt(
+                "values",
+                format_args!("0 0 0 0 {r} 0 0 0 0 {g} 0 0 0 0 {b} {} 0 0 0 {a}", -a),
+            );
+            self.xml.end_element();
+            self.xml.end_element();
+        }
+
         self.xml.end_element();
     }
 }
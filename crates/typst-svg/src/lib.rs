@@ -15,7 +15,7 @@ use typst::layout::{
 };
 use typst::model::Document;
 use typst::utils::hash128;
-use typst::visualize::{Gradient, Pattern};
+use typst::visualize::{Color, Gradient, Pattern};
 use xmlwriter::XmlWriter;
 
 use crate::paint::{GradientRef, PatternRef, SVGSubGradient};
@@ -102,6 +102,8 @@ struct SVGRenderer {
     patterns: Deduplicator<Pattern>,
     /// These are the gradients that compose a conic gradient.
     conic_subgradients: Deduplicator<SVGSubGradient>,
+    /// The fills of black-and-white images, written as filters.
+    image_fills: Deduplicator<Color>,
 }
 
 /// Contextual information for rendering.
@@ -154,6 +156,7 @@ impl SVGRenderer {
             conic_subgradients: Deduplicator::new('s'),
             pattern_refs: Deduplicator::new('p'),
             patterns: Deduplicator::new('t'),
+            image_fills: Deduplicator::new('m'),
         }
     }
 
@@ -247,6 +250,7 @@ impl SVGRenderer {
         self.write_subgradients();
         self.write_patterns();
         self.write_pattern_refs();
+        self.write_image_fills();
         self.xml.end_document()
     }
 
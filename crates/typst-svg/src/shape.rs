@@ -1,8 +1,9 @@
 use ecow::EcoString;
 use ttf_parser::OutlineBuilder;
-use typst::layout::{Abs, Ratio, Size, Transform};
+use typst::layout::{Abs, Point, Ratio, Size, Transform};
 use typst::visualize::{
-    FixedStroke, Geometry, LineCap, LineJoin, Paint, Path, PathItem, RelativeTo, Shape,
+    FixedStroke, Geometry, LineCap, LineJoin, Paint, PaintOrder, Path, PathItem,
+    RelativeTo, Shape,
 };
 
 use crate::paint::ColorEncode;
@@ -15,21 +16,28 @@ impl SVGRenderer {
         self.xml.write_attribute("class", "typst-shape");
 
         if let Some(paint) = &shape.fill {
+            let size = shape.geometry.bbox_size();
             self.write_fill(
                 paint,
-                self.shape_fill_size(state, paint, shape),
-                self.shape_paint_transform(state, paint, shape),
+                self.shape_fill_size(state, paint, size),
+                self.shape_paint_transform(state, paint, Point::zero(), size),
             );
         } else {
             self.xml.write_attribute("fill", "none");
         }
 
         if let Some(stroke) = &shape.stroke {
+            // Lines and straight paths have no area, so their paint is
+            // relative to the area that their stroke covers instead.
+            let (offset, size) = shape.geometry.stroke_paint_box(stroke.thickness);
             self.write_stroke(
                 stroke,
-                self.shape_fill_size(state, &stroke.paint, shape),
-                self.shape_paint_transform(state, &stroke.paint, shape),
+                self.shape_fill_size(state, &stroke.paint, size),
+                self.shape_paint_transform(state, &stroke.paint, offset, size),
             );
+            if shape.paint_order == PaintOrder::StrokeFill {
+                self.xml.write_attribute("paint-order", "stroke");
+            }
         }
 
         let path = convert_geometry_to_path(&shape.geometry);
@@ -37,14 +45,15 @@ impl SVGRenderer {
         self.xml.end_element();
     }
 
-    /// Calculate the transform of the shape's fill or stroke.
+    /// Calculate the transform of the shape's fill or stroke, given the box
+    /// relative to which it is painted.
     fn shape_paint_transform(
         &self,
         state: State,
         paint: &Paint,
-        shape: &Shape,
+        offset: Point,
+        mut shape_size: Size,
     ) -> Transform {
-        let mut shape_size = shape.geometry.bbox_size();
         // Edge cases for strokes.
         if shape_size.x.to_pt() == 0.0 {
             shape_size.x = Abs::pt(1.0);
@@ -56,10 +65,12 @@ impl SVGRenderer {
 
         if let Paint::Gradient(gradient) = paint {
             match gradient.unwrap_relative(false) {
-                RelativeTo::Self_ => Transform::scale(
-                    Ratio::new(shape_size.x.to_pt()),
-                    Ratio::new(shape_size.y.to_pt()),
-                ),
+                RelativeTo::Self_ => {
+                    Transform::translate(offset.x, offset.y).pre_concat(Transform::scale(
+                        Ratio::new(shape_size.x.to_pt()),
+                        Ratio::new(shape_size.y.to_pt()),
+                    ))
+                }
                 RelativeTo::Parent => Transform::scale(
                     Ratio::new(state.size.x.to_pt()),
                     Ratio::new(state.size.y.to_pt()),
@@ -68,7 +79,7 @@ impl SVGRenderer {
             }
         } else if let Paint::Pattern(pattern) = paint {
             match pattern.unwrap_relative(false) {
-                RelativeTo::Self_ => Transform::identity(),
+                RelativeTo::Self_ => Transform::translate(offset.x, offset.y),
                 RelativeTo::Parent => state.transform.invert().unwrap(),
             }
         } else {
@@ -76,9 +87,9 @@ impl SVGRenderer {
         }
     }
 
-    /// Calculate the size of the shape's fill.
-    fn shape_fill_size(&self, state: State, paint: &Paint, shape: &Shape) -> Size {
-        let mut shape_size = shape.geometry.bbox_size();
+    /// Calculate the size of the shape's fill or stroke, given the size of the
+    /// box relative to which it is painted.
+    fn shape_fill_size(&self, state: State, paint: &Paint, mut shape_size: Size) -> Size {
         // Edge cases for strokes.
         if shape_size.x.to_pt() == 0.0 {
             shape_size.x = Abs::pt(1.0);
@@ -3,10 +3,11 @@ use std::io::Read;
 use base64::Engine;
 use ecow::EcoString;
 use ttf_parser::GlyphId;
+use typst::foundations::Smart;
 use typst::layout::{Abs, Point, Ratio, Size, Transform};
 use typst::text::{Font, TextItem};
 use typst::utils::hash128;
-use typst::visualize::{Image, Paint, RasterFormat, RelativeTo};
+use typst::visualize::{Image, Paint, PaintOrder, RasterFormat, RelativeTo};
 
 use crate::{SVGRenderer, State, SvgMatrix, SvgPathBuilder};
 
@@ -139,25 +140,52 @@ impl SVGRenderer {
         self.write_fill(
             &text.fill,
             Size::new(Abs::pt(width), Abs::pt(height)),
-            self.text_paint_transform(state, &text.fill),
+            self.text_paint_transform(state, &text.fill, text, x_offset),
         );
         if let Some(stroke) = &text.stroke {
+            // Paints that are relative to the text itself fill the text run's
+            // box instead of the glyph's.
+            let size = if stroke.paint.relative() == Smart::Custom(RelativeTo::Self_) {
+                text.bbox().1
+            } else {
+                Size::new(Abs::pt(width), Abs::pt(height))
+            };
             self.write_stroke(
                 stroke,
-                Size::new(Abs::pt(width), Abs::pt(height)),
-                self.text_paint_transform(state, &stroke.paint),
+                size,
+                self.text_paint_transform(state, &stroke.paint, text, x_offset),
             );
+            if text.paint_order == PaintOrder::StrokeFill {
+                self.xml.write_attribute("paint-order", "stroke");
+            }
         }
         self.xml.end_element();
 
         Some(())
     }
 
-    fn text_paint_transform(&self, state: State, paint: &Paint) -> Transform {
+    /// Calculate the transform of a glyph's fill or stroke. The glyph lies at
+    /// `x_offset` in the text run.
+    fn text_paint_transform(
+        &self,
+        state: State,
+        paint: &Paint,
+        text: &TextItem,
+        x_offset: f64,
+    ) -> Transform {
+        // Maps the text run's box into the glyph's coordinate system, which
+        // is flipped vertically.
+        let (origin, size) = text.bbox();
+        let own = Transform::scale(Ratio::one(), -Ratio::one())
+            .pre_concat(Transform::translate(origin.x - Abs::pt(x_offset), origin.y));
+
         match paint {
             Paint::Solid(_) => Transform::identity(),
             Paint::Gradient(gradient) => match gradient.unwrap_relative(true) {
-                RelativeTo::Self_ => Transform::identity(),
+                RelativeTo::Self_ => own.pre_concat(Transform::scale(
+                    Ratio::new(size.x.to_pt()),
+                    Ratio::new(size.y.to_pt()),
+                )),
                 RelativeTo::Parent => Transform::scale(
                     Ratio::new(state.size.x.to_pt()),
                     Ratio::new(state.size.y.to_pt()),
@@ -165,7 +193,7 @@ impl SVGRenderer {
                 .post_concat(state.transform.invert().unwrap()),
             },
             Paint::Pattern(pattern) => match pattern.unwrap_relative(true) {
-                RelativeTo::Self_ => Transform::identity(),
+                RelativeTo::Self_ => own,
                 RelativeTo::Parent => state.transform.invert().unwrap(),
             },
         }
@@ -242,7 +270,9 @@ fn convert_bitmap_glyph_to_image(font: &Font, id: GlyphId) -> Option<(Image, f64
     if raster.format != ttf_parser::RasterImageFormat::PNG {
         return None;
     }
-    let image = Image::new(raster.data.into(), RasterFormat::Png.into(), None).ok()?;
+    let image =
+        Image::new(raster.data.into(), RasterFormat::Png.into(), None, Smart::Auto, None)
+            .ok()?;
     Some((image, raster.x as f64, raster.y as f64))
 }
 
@@ -8,7 +8,7 @@ use crate::layout::{
     Ratio, Regions, Rel, Sides, Size, Spacing, VElem,
 };
 use crate::utils::Numeric;
-use crate::visualize::{clip_rect, Paint, Stroke};
+use crate::visualize::{clip_rect, Paint, PaintOrder, Stroke};
 
 /// An inline-level container that sizes content.
 ///
@@ -172,7 +172,14 @@ impl Packed<BoxElem> {
         if fill.is_some() || stroke.iter().any(Option::is_some) {
             let outset = self.outset(styles).unwrap_or_default();
             let radius = self.radius(styles).unwrap_or_default();
-            frame.fill_and_stroke(fill, stroke, outset, radius, self.span());
+            frame.fill_and_stroke(
+                fill,
+                stroke,
+                PaintOrder::default(),
+                outset,
+                radius,
+                self.span(),
+            );
         }
 
         // Apply metadata.
@@ -452,6 +459,7 @@ impl LayoutMultiple for Packed<BlockElem> {
                 frame.fill_and_stroke(
                     fill.clone(),
                     stroke.clone(),
+                    PaintOrder::default(),
                     outset,
                     radius,
                     self.span(),
@@ -13,7 +13,8 @@ use crate::syntax::Span;
 use crate::text::TextItem;
 use crate::utils::{LazyHash, Numeric};
 use crate::visualize::{
-    ellipse, styled_rect, Color, FixedStroke, Geometry, Image, Paint, Path, Shape,
+    ellipse, styled_rect, Color, FixedStroke, Geometry, Image, Paint, PaintOrder, Path,
+    Shape,
 };
 
 /// A finished layout with items at fixed positions.
@@ -337,6 +338,7 @@ impl Frame {
         &mut self,
         fill: Option<Paint>,
         stroke: Sides<Option<FixedStroke>>,
+        paint_order: PaintOrder,
         outset: Sides<Rel<Abs>>,
         radius: Corners<Rel<Abs>>,
         span: Span,
@@ -345,7 +347,7 @@ impl Frame {
         let size = self.size() + outset.sum_by_axis();
         let pos = Point::new(-outset.left, -outset.top);
         self.prepend_multiple(
-            styled_rect(size, radius, fill, stroke)
+            styled_rect(size, radius, fill, stroke, paint_order)
                 .into_iter()
                 .map(|x| (pos, FrameItem::Shape(x, span))),
         )
@@ -228,6 +228,7 @@ impl<'a> ShapedText<'a> {
         let decos = TextElem::deco_in(self.styles);
         let fill = TextElem::fill_in(self.styles);
         let stroke = TextElem::stroke_in(self.styles);
+        let paint_order = TextElem::paint_order_in(self.styles);
         let span_offset = TextElem::span_offset_in(self.styles);
 
         for ((font, y_offset), group) in
@@ -309,6 +310,7 @@ impl<'a> ShapedText<'a> {
                 region: self.region,
                 fill: fill.clone(),
                 stroke: stroke.clone().map(|s| s.unwrap_or_default()),
+                paint_order,
                 text: self.text[range.start - self.base..range.end - self.base].into(),
                 glyphs,
             };
@@ -13,7 +13,7 @@ use crate::math::{
 };
 use crate::syntax::Span;
 use crate::text::{Font, Glyph, Lang, Region, TextElem, TextItem};
-use crate::visualize::Paint;
+use crate::visualize::{Paint, PaintOrder};
 
 #[derive(Debug, Clone)]
 pub enum MathFragment {
@@ -345,6 +345,7 @@ impl GlyphFragment {
             region: self.region,
             text: self.c.into(),
             stroke: None,
+            paint_order: PaintOrder::default(),
             glyphs: vec![Glyph {
                 id: self.id.0,
                 x_advance: Em::from_length(self.width, self.font_size),
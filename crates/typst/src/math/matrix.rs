@@ -16,7 +16,7 @@ use crate::math::{
 use crate::syntax::{Span, Spanned};
 use crate::text::TextElem;
 use crate::utils::Numeric;
-use crate::visualize::{FixedStroke, Geometry, LineCap, Shape, Stroke};
+use crate::visualize::{FixedStroke, Geometry, LineCap, PaintOrder, Shape, Stroke};
 
 const DEFAULT_ROW_GAP: Em = Em::new(0.5);
 const DEFAULT_COL_GAP: Em = Em::new(0.5);
@@ -546,6 +546,7 @@ fn line_item(length: Abs, vertical: bool, stroke: FixedStroke, span: Span) -> Fr
             geometry: line_geom,
             fill: None,
             stroke: Some(stroke),
+            paint_order: PaintOrder::default(),
         },
         span,
     )
@@ -12,7 +12,9 @@ use crate::syntax::Span;
 use crate::text::{
     BottomEdge, BottomEdgeMetric, TextElem, TextItem, TopEdge, TopEdgeMetric,
 };
-use crate::visualize::{styled_rect, Color, FixedStroke, Geometry, Paint, Stroke};
+use crate::visualize::{
+    styled_rect, Color, FixedStroke, Geometry, Paint, PaintOrder, Stroke,
+};
 
 /// Underlines text.
 ///
@@ -423,7 +425,13 @@ pub(crate) fn decorate(
     {
         let (top, bottom) = determine_edges(text, *top_edge, *bottom_edge);
         let size = Size::new(width + 2.0 * deco.extent, top - bottom);
-        let rects = styled_rect(size, *radius, fill.clone(), stroke.clone());
+        let rects = styled_rect(
+            size,
+            *radius,
+            fill.clone(),
+            stroke.clone(),
+            PaintOrder::default(),
+        );
         let origin = Point::new(pos.x - deco.extent, pos.y - top - shift);
         frame.prepend_multiple(
             rects
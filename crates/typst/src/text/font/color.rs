@@ -6,10 +6,11 @@ use ecow::EcoString;
 use ttf_parser::GlyphId;
 use usvg::{TreeParsing, TreeWriting};
 
+use crate::foundations::Smart;
 use crate::layout::{Abs, Axes, Em, Frame, FrameItem, Point, Size};
 use crate::syntax::Span;
 use crate::text::{Font, Glyph, Lang, TextItem};
-use crate::visualize::{Color, Image, Paint, Rgb};
+use crate::visualize::{Color, Image, Paint, PaintOrder, Rgb};
 
 /// Tells if a glyph is a color glyph or not in a given font.
 pub fn is_color_glyph(font: &Font, g: &Glyph) -> bool {
@@ -54,6 +55,8 @@ fn draw_raster_glyph(
         raster_image.data.into(),
         typst::visualize::ImageFormat::Raster(typst::visualize::RasterFormat::Png),
         None,
+        Smart::Auto,
+        None,
     )
     .unwrap();
 
@@ -103,6 +106,7 @@ impl<'f, 't> ColrPainter<'f, 't> {
                 size: Abs::pt(self.font.units_per_em()),
                 fill,
                 stroke: None,
+                paint_order: PaintOrder::default(),
                 lang: Lang::ENGLISH,
                 region: None,
                 text: EcoString::new(),
@@ -217,6 +221,8 @@ fn draw_svg_glyph(
         wrapper_svg.into_bytes().into(),
         typst::visualize::ImageFormat::Vector(typst::visualize::VectorFormat::Svg),
         None,
+        Smart::Auto,
+        None,
     )
     .unwrap();
     let position = Point::new(Abs::pt(left), Abs::pt(top) + upem);
@@ -3,10 +3,10 @@ use std::ops::Range;
 
 use ecow::EcoString;
 
-use crate::layout::{Abs, Em};
+use crate::layout::{Abs, Em, Point, Size};
 use crate::syntax::Span;
 use crate::text::{Font, Lang, Region};
-use crate::visualize::{FixedStroke, Paint};
+use crate::visualize::{FixedStroke, Paint, PaintOrder};
 
 /// A run of shaped text.
 #[derive(Clone, Eq, PartialEq, Hash)]
@@ -19,6 +19,8 @@ pub struct TextItem {
     pub fill: Paint,
     /// Glyph stroke.
     pub stroke: Option<FixedStroke>,
+    /// In which order the glyph fill and stroke are painted.
+    pub paint_order: PaintOrder,
     /// The natural language of the text.
     pub lang: Lang,
     /// The region of the text.
@@ -35,6 +37,19 @@ impl TextItem {
     pub fn width(&self) -> Abs {
         self.glyphs.iter().map(|g| g.x_advance).sum::<Em>().at(self.size)
     }
+
+    /// The box that paints relative to the text run itself fill: It spans the
+    /// run's width and reaches from the font's ascender down to its
+    /// descender.
+    ///
+    /// Returns the box's top-left corner relative to the run's baseline origin
+    /// and its size.
+    pub fn bbox(&self) -> (Point, Size) {
+        let metrics = self.font.metrics();
+        let top = metrics.ascender.at(self.size);
+        let bottom = metrics.descender.at(self.size);
+        (Point::with_y(-top), Size::new(self.width(), top - bottom))
+    }
 }
 
 impl Debug for TextItem {
@@ -45,7 +45,7 @@ use crate::foundations::{
 use crate::layout::{Abs, Axis, Dir, Em, Length, Ratio, Rel};
 use crate::model::ParElem;
 use crate::syntax::Spanned;
-use crate::visualize::{Color, Paint, RelativeTo, Stroke};
+use crate::visualize::{Color, Paint, PaintOrder, RelativeTo, Stroke};
 use crate::World;
 
 /// Text styling.
@@ -266,6 +266,10 @@ pub struct TextElem {
 
     /// How to stroke the text.
     ///
+    /// Gradients and patterns on the stroke are relative to the closest
+    /// ancestor container by default. With `{relative: "self"}`, they instead
+    /// span each run of text from the font's ascender to its descender.
+    ///
     /// ```example
     /// #text(stroke: 0.5pt + red)[Stroked]
     /// ```
@@ -273,6 +277,20 @@ pub struct TextElem {
     #[ghost]
     pub stroke: Option<Stroke>,
 
+    /// In which order to paint the glyphs' fill and stroke.
+    ///
+    /// By default, the stroke is painted on top of the fill, so a thick stroke
+    /// eats into the letterforms. With `{"stroke-fill"}`, the stroke is painted
+    /// underneath the fill instead and only its outer half stays visible.
+    ///
+    /// ```example
+    /// #set text(size: 16pt, stroke: 2pt + aqua)
+    /// #text(paint-order: "fill-stroke")[Outline] \
+    /// #text(paint-order: "stroke-fill")[Outline]
+    /// ```
+    #[ghost]
+    pub paint_order: PaintOrder,
+
     /// The amount of space that should be added between characters.
     ///
     /// ```example
@@ -13,7 +13,7 @@ use std::sync::Arc;
 use comemo::Tracked;
 use ecow::EcoString;
 
-use crate::diag::{bail, At, SourceResult, StrResult};
+use crate::diag::{bail, warning, At, SourceResult, StrResult};
 use crate::engine::Engine;
 use crate::foundations::{
     cast, elem, func, scope, Bytes, Cast, Content, NativeElement, Packed, Resolve, Smart,
@@ -28,7 +28,7 @@ use crate::model::Figurable;
 use crate::syntax::{Span, Spanned};
 use crate::text::{families, LocalName};
 use crate::utils::LazyHash;
-use crate::visualize::Path;
+use crate::visualize::{Color, Path};
 use crate::World;
 
 /// A raster or vector graphic.
@@ -96,9 +96,27 @@ pub struct ImageElem {
     /// ```
     #[default(ImageFit::Cover)]
     pub fit: ImageFit,
+
+    /// A hint to viewers how they should scale the image.
+    ///
+    /// When set to `{auto}`, the default is left up to the viewer. Smooth
+    /// scaling suits photos, while pixelated scaling keeps pixel art and
+    /// similar graphics crisp.
+    ///
+    /// _Note:_ The exact look may differ across exporters and viewers.
+    pub scaling: Smart<ImageScaling>,
+
+    /// The color with which the black pixels of a black-and-white raster
+    /// image are painted. Its white pixels then become transparent.
+    ///
+    /// This is useful to recolor scanned signatures, logos, and line art. A
+    /// black-and-white image is a PNG, JPEG, or GIF whose pixels are all either
+    /// pure black or pure white. Other images are not affected.
+    pub fill: Option<Color>,
 }
 
 #[scope]
+#[allow(clippy::too_many_arguments)]
 impl ImageElem {
     /// Decode a raster or vector graphic from bytes or a string.
     ///
@@ -133,6 +151,13 @@ impl ImageElem {
         /// How the image should adjust itself to a given area.
         #[named]
         fit: Option<ImageFit>,
+        /// A hint to viewers how they should scale the image.
+        #[named]
+        scaling: Option<Smart<ImageScaling>>,
+        /// The color with which the black pixels of a black-and-white raster
+        /// image are painted.
+        #[named]
+        fill: Option<Option<Color>>,
     ) -> StrResult<Content> {
         let mut elem = ImageElem::new(EcoString::new(), data);
         if let Some(format) = format {
@@ -150,6 +175,12 @@ impl ImageElem {
         if let Some(fit) = fit {
             elem.push_fit(fit);
         }
+        if let Some(scaling) = scaling {
+            elem.push_scaling(scaling);
+        }
+        if let Some(fill) = fill {
+            elem.push_fill(fill);
+        }
         Ok(elem.pack().spanned(span))
     }
 }
@@ -194,11 +225,34 @@ impl LayoutSingle for Packed<ImageElem> {
             data.clone().into(),
             format,
             self.alt(styles),
+            self.scaling(styles),
+            self.fill(styles),
             engine.world,
             &families(styles).map(|s| s.into()).collect::<Vec<_>>(),
         )
         .at(self.span())?;
 
+        if let ImageKind::Svg(svg) = image.kind() {
+            let missing = svg.missing_families();
+            if !missing.is_empty() {
+                engine.tracer.warn(warning!(
+                    self.span(),
+                    "unknown font {} in SVG: {}",
+                    if missing.len() == 1 { "family" } else { "families" },
+                    missing.join(", ");
+                    hint: "the text falls back to another font"
+                ));
+            }
+        }
+
+        if self.fill(styles).is_some() && image.fill().is_none() {
+            engine.tracer.warn(warning!(
+                self.span(),
+                "image fill has no effect";
+                hint: "only black-and-white raster images can be filled"
+            ));
+        }
+
         let sizing = Axes::new(self.width(styles), self.height(styles));
         let region = sizing
             .zip_map(regions.base(), |s, r| s.map(|v| v.resolve(styles).relative_to(r)))
@@ -286,6 +340,16 @@ pub enum ImageFit {
     Stretch,
 }
 
+/// The image scaling algorithm a viewer should use.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Cast)]
+pub enum ImageScaling {
+    /// Scale with a smoothing algorithm such as bilinear interpolation.
+    Smooth,
+    /// Scale with nearest neighbor or a similar algorithm to preserve the
+    /// pixelated look of the image.
+    Pixelated,
+}
+
 /// A loaded raster or vector image.
 ///
 /// Values of this type are cheap to clone and hash.
@@ -299,6 +363,10 @@ struct Repr {
     kind: ImageKind,
     /// A text describing the image.
     alt: Option<EcoString>,
+    /// The scaling algorithm to use.
+    scaling: Smart<ImageScaling>,
+    /// The color of the black pixels of a black-and-white image.
+    fill: Option<Color>,
 }
 
 /// A kind of image.
@@ -322,6 +390,8 @@ impl Image {
         data: Bytes,
         format: ImageFormat,
         alt: Option<EcoString>,
+        scaling: Smart<ImageScaling>,
+        fill: Option<Color>,
     ) -> StrResult<Image> {
         let kind = match format {
             ImageFormat::Raster(format) => {
@@ -332,7 +402,7 @@ impl Image {
             }
         };
 
-        Ok(Self(Arc::new(LazyHash::new(Repr { kind, alt }))))
+        Ok(Self::from_repr(kind, alt, scaling, fill))
     }
 
     /// Create a possibly font-dependant image from a buffer and a format.
@@ -342,6 +412,8 @@ impl Image {
         data: Bytes,
         format: ImageFormat,
         alt: Option<EcoString>,
+        scaling: Smart<ImageScaling>,
+        fill: Option<Color>,
         world: Tracked<dyn World + '_>,
         families: &[String],
     ) -> StrResult<Image> {
@@ -354,7 +426,22 @@ impl Image {
             }
         };
 
-        Ok(Self(Arc::new(LazyHash::new(Repr { kind, alt }))))
+        Ok(Self::from_repr(kind, alt, scaling, fill))
+    }
+
+    /// Create an image from its decoded parts.
+    fn from_repr(
+        kind: ImageKind,
+        alt: Option<EcoString>,
+        scaling: Smart<ImageScaling>,
+        fill: Option<Color>,
+    ) -> Image {
+        // Only black-and-white images can be filled.
+        let fill = fill.filter(|_| match &kind {
+            ImageKind::Raster(raster) => raster.is_bilevel(),
+            ImageKind::Svg(_) => false,
+        });
+        Self(Arc::new(LazyHash::new(Repr { kind, alt, scaling, fill })))
     }
 
     /// The raw image data.
@@ -402,6 +489,18 @@ impl Image {
         self.0.alt.as_deref()
     }
 
+    /// The scaling algorithm to use.
+    pub fn scaling(&self) -> Smart<ImageScaling> {
+        self.0.scaling
+    }
+
+    /// The color with which the black pixels of the image are painted, while
+    /// its white pixels are transparent. Only black-and-white raster images
+    /// have one.
+    pub fn fill(&self) -> Option<Color> {
+        self.0.fill
+    }
+
     /// The decoded image.
     pub fn kind(&self) -> &ImageKind {
         &self.0.kind
@@ -415,6 +514,8 @@ impl Debug for Image {
             .field("width", &self.width())
             .field("height", &self.height())
             .field("alt", &self.alt())
+            .field("scaling", &self.scaling())
+            .field("fill", &self.fill())
             .finish()
     }
 }
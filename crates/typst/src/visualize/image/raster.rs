@@ -24,6 +24,7 @@ struct Repr {
     dynamic: image::DynamicImage,
     icc: Option<Vec<u8>>,
     dpi: Option<f64>,
+    rotation: Option<u32>,
 }
 
 impl RasterImage {
@@ -53,14 +54,15 @@ impl RasterImage {
             .ok();
 
         // Apply rotation from EXIF metadata.
-        if let Some(rotation) = exif.as_ref().and_then(exif_rotation) {
+        let rotation = exif.as_ref().and_then(exif_rotation);
+        if let Some(rotation) = rotation {
             apply_rotation(&mut dynamic, rotation);
         }
 
         // Extract pixel density.
         let dpi = determine_dpi(&data, exif.as_ref());
 
-        Ok(Self(Arc::new(Repr { data, format, dynamic, icc, dpi })))
+        Ok(Self(Arc::new(Repr { data, format, dynamic, icc, dpi, rotation })))
     }
 
     /// The raw image data.
@@ -93,10 +95,28 @@ impl RasterImage {
         &self.0.dynamic
     }
 
+    /// Whether the image is grayscale with only black and white pixels.
+    pub fn is_bilevel(&self) -> bool {
+        match self.dynamic() {
+            DynamicImage::ImageLuma8(luma) => {
+                luma.as_raw().iter().all(|&v| v == 0 || v == 255)
+            }
+            _ => false,
+        }
+    }
+
     /// Access the ICC profile, if any.
     pub fn icc(&self) -> Option<&[u8]> {
         self.0.icc.as_deref()
     }
+
+    /// The EXIF orientation of the image, if any.
+    ///
+    /// The orientation is already applied to the decoded pixels, so this is
+    /// only relevant when working with the raw image data.
+    pub fn exif_rotation(&self) -> Option<u32> {
+        self.0.rotation
+    }
 }
 
 impl Hash for Repr {
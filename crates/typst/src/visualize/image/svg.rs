@@ -24,6 +24,7 @@ struct Repr {
     data: Bytes,
     size: Axes<f64>,
     font_hash: u128,
+    missing_families: Vec<EcoString>,
     tree: sync::SyncTree,
 }
 
@@ -38,6 +39,7 @@ impl SvgImage {
             data,
             size: tree_size(&tree),
             font_hash: 0,
+            missing_families: vec![],
             // Safety: We just created the tree and hold the only reference.
             tree: unsafe { sync::SyncTree::new(tree) },
         })))
@@ -53,16 +55,19 @@ impl SvgImage {
         let mut tree =
             usvg::Tree::from_data(&data, &OPTIONS).map_err(format_usvg_error)?;
         let mut font_hash = 0;
+        let mut missing_families = vec![];
         if tree.has_text_nodes() {
-            let (fontdb, hash) = load_svg_fonts(world, &mut tree, families);
+            let (fontdb, hash, missing) = load_svg_fonts(world, &mut tree, families);
             tree.postprocess(PostProcessingSteps::default(), &fontdb);
             font_hash = hash;
+            missing_families = missing;
         }
         tree.calculate_bounding_boxes();
         Ok(Self(Arc::new(Repr {
             data,
             size: tree_size(&tree),
             font_hash,
+            missing_families,
             // Safety: We just created the tree and hold the only reference.
             tree: unsafe { sync::SyncTree::new(tree) },
         })))
@@ -73,6 +78,12 @@ impl SvgImage {
         &self.0.data
     }
 
+    /// The font families referenced by the SVG's text that are not
+    /// available. Text in these families falls back to another font.
+    pub fn missing_families(&self) -> &[EcoString] {
+        &self.0.missing_families
+    }
+
     /// The SVG's width in pixels.
     pub fn width(&self) -> f64 {
         self.0.size.x
@@ -152,8 +163,9 @@ fn load_svg_fonts(
     world: Tracked<dyn World + '_>,
     tree: &mut usvg::Tree,
     families: &[String],
-) -> (fontdb::Database, u128) {
+) -> (fontdb::Database, u128, Vec<EcoString>) {
     let book = world.book();
+    let mut missing = Vec::<EcoString>::new();
     let mut fontdb = fontdb::Database::new();
     let mut hasher = siphasher::sip128::SipHasher13::new();
     let mut loaded = HashMap::<usize, Option<String>>::new();
@@ -183,6 +195,21 @@ fn load_svg_fonts(
                     let Some(text) = chunk.text.get(span.start..span.end) else {
                         continue;
                     };
+                    // Remember the families that we don't know, unless another
+                    // family in the span's list resolves. Generic families are
+                    // resolved by the fallback below.
+                    let resolves = |family: &String| {
+                        is_generic_family(family)
+                            || book.contains_family(&family.to_lowercase())
+                    };
+                    if !span.font.families.iter().any(resolves) {
+                        for family in &span.font.families {
+                            if !missing.iter().any(|m| m == family) {
+                                missing.push(family.as_str().into());
+                            }
+                        }
+                    }
+
                     let variant = FontVariant {
                         style: span.font.style.into(),
                         weight: FontWeight::from_number(span.font.weight),
@@ -219,7 +246,28 @@ fn load_svg_fonts(
         });
     }
 
-    (fontdb, hasher.finish128().as_u128())
+    (fontdb, hasher.finish128().as_u128(), missing)
+}
+
+/// Whether a font family is one of CSS's generic families, which don't name a
+/// specific font.
+fn is_generic_family(family: &str) -> bool {
+    matches!(
+        family,
+        "" | "serif"
+            | "sans-serif"
+            | "monospace"
+            | "cursive"
+            | "fantasy"
+            | "system-ui"
+            | "ui-serif"
+            | "ui-sans-serif"
+            | "ui-monospace"
+            | "ui-rounded"
+            | "emoji"
+            | "math"
+            | "fangsong"
+    )
 }
 
 /// Search for all font families referenced by an SVG.
@@ -2,7 +2,7 @@ use std::fmt::{self, Debug, Formatter};
 
 use ecow::EcoString;
 
-use crate::foundations::{cast, Repr, Smart};
+use crate::foundations::{cast, Cast, Repr, Smart};
 use crate::visualize::{Color, Gradient, Pattern, RelativeTo};
 
 /// How a fill or stroke should be painted.
@@ -100,3 +100,15 @@ cast! {
     gradient: Gradient => Self::Gradient(gradient),
     pattern: Pattern => Self::Pattern(pattern),
 }
+
+/// The order in which the fill and the stroke of an item are painted.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash, Cast)]
+pub enum PaintOrder {
+    /// The fill is painted first and the stroke on top of it. The stroke
+    /// thus covers half of its thickness of the filled area.
+    #[default]
+    FillStroke,
+    /// The stroke is painted first and the fill on top of it. Only the outer
+    /// half of the stroke remains visible.
+    StrokeFill,
+}
@@ -8,7 +8,7 @@ use crate::foundations::{
 use crate::layout::{
     Abs, Axes, Frame, FrameItem, LayoutSingle, Length, Point, Regions, Rel, Size,
 };
-use crate::visualize::{FixedStroke, Geometry, Paint, Shape, Stroke};
+use crate::visualize::{FixedStroke, Geometry, Paint, PaintOrder, Shape, Stroke};
 
 use PathVertex::{AllControlPoints, MirroredControlPoint, Vertex};
 
@@ -44,6 +44,10 @@ pub struct PathElem {
     #[fold]
     pub stroke: Smart<Option<Stroke>>,
 
+    /// In which order to paint the path's fill and stroke. See the
+    /// [rectangle's documentation]($rect.paint-order) for more details.
+    pub paint_order: PaintOrder,
+
     /// Whether to close this path with one last bezier curve. This curve will
     /// takes into account the adjacent control points. If you want to close
     /// with a straight line, simply add one last point that's the same as the
@@ -145,7 +149,12 @@ impl LayoutSingle for Packed<PathElem> {
         };
 
         let mut frame = Frame::soft(size);
-        let shape = Shape { geometry: Geometry::Path(path), stroke, fill };
+        let shape = Shape {
+            geometry: Geometry::Path(path),
+            stroke,
+            fill,
+            paint_order: self.paint_order(styles),
+        };
         frame.push(Point::zero(), FrameItem::Shape(shape, self.span()));
         Ok(frame)
     }
@@ -33,9 +33,10 @@ use crate::World;
 ///
 /// Patterns are also supported on text, but only when setting the
 /// [relativeness]($pattern.relative) to either `{auto}` (the default value) or
-/// `{"parent"}`. To create word-by-word or glyph-by-glyph patterns, you can
-/// wrap the words or characters of your text in [boxes]($box) manually or
-/// through a [show rule]($styling/#show-rules).
+/// `{"parent"}`. The only exception are text [strokes]($text.stroke), which can
+/// also be relative to the text itself. To create word-by-word or
+/// glyph-by-glyph patterns, you can wrap the words or characters of your text
+/// in [boxes]($box) manually or through a [show rule]($styling/#show-rules).
 ///
 /// ```example
 /// #let pat = pattern(
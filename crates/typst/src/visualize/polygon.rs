@@ -10,7 +10,7 @@ use crate::layout::{
 };
 use crate::syntax::Span;
 use crate::utils::Numeric;
-use crate::visualize::{FixedStroke, Geometry, Paint, Path, Shape, Stroke};
+use crate::visualize::{FixedStroke, Geometry, Paint, PaintOrder, Path, Shape, Stroke};
 
 /// A closed polygon.
 ///
@@ -46,6 +46,10 @@ pub struct PolygonElem {
     #[fold]
     pub stroke: Smart<Option<Stroke>>,
 
+    /// In which order to paint the polygon's fill and stroke. See the
+    /// [rectangle's documentation]($rect.paint-order) for more details.
+    pub paint_order: PaintOrder,
+
     /// The vertices of the polygon. Each point is specified as an array of two
     /// [relative lengths]($relative).
     #[variadic]
@@ -169,7 +173,12 @@ impl LayoutSingle for Packed<PolygonElem> {
         }
         path.close_path();
 
-        let shape = Shape { geometry: Geometry::Path(path), stroke, fill };
+        let shape = Shape {
+            geometry: Geometry::Path(path),
+            stroke,
+            fill,
+            paint_order: self.paint_order(styles),
+        };
         frame.push(Point::zero(), FrameItem::Shape(shape, self.span()));
         Ok(frame)
     }
@@ -8,8 +8,8 @@ use crate::layout::{
     Point, Ratio, Regions, Rel, Sides, Size,
 };
 use crate::syntax::Span;
-use crate::utils::Get;
-use crate::visualize::{FixedStroke, Paint, Path, Stroke};
+use crate::utils::{Get, Numeric};
+use crate::visualize::{FixedStroke, Paint, PaintOrder, Path, Stroke};
 
 /// A rectangle with optional content.
 ///
@@ -72,6 +72,24 @@ pub struct RectElem {
     #[fold]
     pub stroke: Smart<Sides<Option<Option<Stroke>>>>,
 
+    /// In which order to paint the rectangle's fill and stroke.
+    ///
+    /// By default, the stroke is painted on top of the fill, so that it
+    /// covers half of its thickness of the filled area. With
+    /// `{"stroke-fill"}`, the stroke is painted underneath the fill instead
+    /// and only its outer half stays visible.
+    ///
+    /// ```example
+    /// #set rect(fill: aqua, stroke: 6pt + blue.transparentize(50%))
+    /// #stack(
+    ///   dir: ltr,
+    ///   spacing: 1cm,
+    ///   rect(paint-order: "fill-stroke"),
+    ///   rect(paint-order: "stroke-fill"),
+    /// )
+    /// ```
+    pub paint_order: PaintOrder,
+
     /// How much to round the rectangle's corners, relative to the minimum of
     /// the width and height divided by two. This can be:
     ///
@@ -148,6 +166,7 @@ impl LayoutSingle for Packed<RectElem> {
             Axes::new(self.width(styles), self.height(styles)),
             self.fill(styles),
             self.stroke(styles),
+            self.paint_order(styles),
             self.inset(styles),
             self.outset(styles),
             self.radius(styles),
@@ -209,6 +228,10 @@ pub struct SquareElem {
     #[fold]
     pub stroke: Smart<Sides<Option<Option<Stroke>>>>,
 
+    /// In which order to paint the square's fill and stroke. See the
+    /// [rectangle's documentation]($rect.paint-order) for more details.
+    pub paint_order: PaintOrder,
+
     /// How much to round the square's corners. See the
     /// [rectangle's documentation]($rect.radius) for more details.
     #[resolve]
@@ -254,6 +277,7 @@ impl LayoutSingle for Packed<SquareElem> {
             Axes::new(self.width(styles), self.height(styles)),
             self.fill(styles),
             self.stroke(styles),
+            self.paint_order(styles),
             self.inset(styles),
             self.outset(styles),
             self.radius(styles),
@@ -294,6 +318,10 @@ pub struct EllipseElem {
     #[fold]
     pub stroke: Smart<Option<Stroke>>,
 
+    /// In which order to paint the ellipse's fill and stroke. See the
+    /// [rectangle's documentation]($rect.paint-order) for more details.
+    pub paint_order: PaintOrder,
+
     /// How much to pad the ellipse's content. See the
     /// [box's documentation]($box.inset) for more details.
     #[resolve]
@@ -332,6 +360,7 @@ impl LayoutSingle for Packed<EllipseElem> {
             Axes::new(self.width(styles), self.height(styles)),
             self.fill(styles),
             self.stroke(styles).map(|s| Sides::splat(Some(s))),
+            self.paint_order(styles),
             self.inset(styles),
             self.outset(styles),
             Corners::splat(None),
@@ -399,6 +428,10 @@ pub struct CircleElem {
     #[default(Smart::Auto)]
     pub stroke: Smart<Option<Stroke>>,
 
+    /// In which order to paint the circle's fill and stroke. See the
+    /// [rectangle's documentation]($rect.paint-order) for more details.
+    pub paint_order: PaintOrder,
+
     /// How much to pad the circle's content. See the
     /// [box's documentation]($box.inset) for more details.
     #[resolve]
@@ -435,6 +468,7 @@ impl LayoutSingle for Packed<CircleElem> {
             Axes::new(self.width(styles), self.height(styles)),
             self.fill(styles),
             self.stroke(styles).map(|s| Sides::splat(Some(s))),
+            self.paint_order(styles),
             self.inset(styles),
             self.outset(styles),
             Corners::splat(None),
@@ -454,6 +488,7 @@ fn layout(
     sizing: Axes<Smart<Rel<Length>>>,
     fill: Option<Paint>,
     stroke: Smart<Sides<Option<Option<Stroke<Abs>>>>>,
+    paint_order: PaintOrder,
     inset: Sides<Option<Rel<Abs>>>,
     outset: Sides<Option<Rel<Abs>>>,
     radius: Corners<Option<Rel<Abs>>>,
@@ -521,12 +556,13 @@ fn layout(
             let outset = outset.unwrap_or_default().relative_to(frame.size());
             let size = frame.size() + outset.sum_by_axis();
             let pos = Point::new(-outset.left, -outset.top);
-            let shape = ellipse(size, fill, stroke.left);
+            let shape = Shape { paint_order, ..ellipse(size, fill, stroke.left) };
             frame.prepend(pos, FrameItem::Shape(shape, span));
         } else {
             frame.fill_and_stroke(
                 fill,
                 stroke,
+                paint_order,
                 outset.unwrap_or_default(),
                 radius.unwrap_or_default(),
                 span,
@@ -571,6 +607,8 @@ pub struct Shape {
     pub fill: Option<Paint>,
     /// The shape's border stroke.
     pub stroke: Option<FixedStroke>,
+    /// In which order to paint the fill and the stroke.
+    pub paint_order: PaintOrder,
 }
 
 /// A shape's geometry.
@@ -587,12 +625,22 @@ pub enum Geometry {
 impl Geometry {
     /// Fill the geometry without a stroke.
     pub fn filled(self, fill: Paint) -> Shape {
-        Shape { geometry: self, fill: Some(fill), stroke: None }
+        Shape {
+            geometry: self,
+            fill: Some(fill),
+            stroke: None,
+            paint_order: PaintOrder::default(),
+        }
     }
 
     /// Stroke the geometry without a fill.
     pub fn stroked(self, stroke: FixedStroke) -> Shape {
-        Shape { geometry: self, fill: None, stroke: Some(stroke) }
+        Shape {
+            geometry: self,
+            fill: None,
+            stroke: Some(stroke),
+            paint_order: PaintOrder::default(),
+        }
     }
 
     /// The bounding box of the geometry.
@@ -603,6 +651,37 @@ impl Geometry {
             Self::Path(p) => p.bbox_size(),
         }
     }
+
+    /// Whether the geometry encloses an area, unlike lines and straight
+    /// paths.
+    pub fn has_area(&self) -> bool {
+        let size = self.bbox_size();
+        !matches!(self, Self::Line(_)) && !size.x.is_zero() && !size.y.is_zero()
+    }
+
+    /// The box relative to which a stroke of the given thickness is painted,
+    /// given by its top-left corner (relative to the geometry's origin) and
+    /// its size.
+    ///
+    /// This is the bounding box for geometry that has an area. For other
+    /// geometry, it is the bounding box grown by half the thickness on every
+    /// side, covering the stroke instead.
+    pub fn stroke_paint_box(&self, thickness: Abs) -> (Point, Size) {
+        if self.has_area() {
+            return (Point::zero(), self.bbox_size());
+        }
+
+        let (origin, size) = match self {
+            Self::Line(line) => (
+                Point::new(line.x.min(Abs::zero()), line.y.min(Abs::zero())),
+                Size::new(line.x.abs(), line.y.abs()),
+            ),
+            _ => (Point::zero(), self.bbox_size()),
+        };
+
+        let half = thickness / 2.0;
+        (origin - Point::splat(half), size + Size::splat(thickness))
+    }
 }
 
 /// Produce a shape that approximates an axis-aligned ellipse.
@@ -627,7 +706,12 @@ pub(crate) fn ellipse(
     path.cubic_to(point(rx, my), point(mx, ry), point(z, ry));
     path.cubic_to(point(-mx, ry), point(-rx, my), point(-rx, z));
 
-    Shape { geometry: Geometry::Path(path), stroke, fill }
+    Shape {
+        geometry: Geometry::Path(path),
+        stroke,
+        fill,
+        paint_order: PaintOrder::default(),
+    }
 }
 
 /// Creates a new rectangle as a path.
@@ -677,11 +761,12 @@ pub(crate) fn styled_rect(
     radius: Corners<Rel<Abs>>,
     fill: Option<Paint>,
     stroke: Sides<Option<FixedStroke>>,
+    paint_order: PaintOrder,
 ) -> Vec<Shape> {
     if stroke.is_uniform() && radius.iter().cloned().all(Rel::is_zero) {
-        simple_rect(size, fill, stroke.top)
+        simple_rect(size, fill, stroke.top, paint_order)
     } else {
-        segmented_rect(size, radius, fill, stroke)
+        segmented_rect(size, radius, fill, stroke, paint_order)
     }
 }
 
@@ -690,8 +775,14 @@ fn simple_rect(
     size: Size,
     fill: Option<Paint>,
     stroke: Option<FixedStroke>,
+    paint_order: PaintOrder,
 ) -> Vec<Shape> {
-    vec![Shape { geometry: Geometry::Rect(size), fill, stroke }]
+    vec![Shape {
+        geometry: Geometry::Rect(size),
+        fill,
+        stroke,
+        paint_order,
+    }]
 }
 
 fn corners_control_points(
@@ -729,6 +820,7 @@ fn segmented_rect(
     radius: Corners<Rel<Abs>>,
     fill: Option<Paint>,
     strokes: Sides<Option<FixedStroke>>,
+    paint_order: PaintOrder,
 ) -> Vec<Shape> {
     let mut res = vec![];
     let stroke_widths = strokes
@@ -768,6 +860,7 @@ fn segmented_rect(
             geometry: Geometry::Path(path),
             fill: Some(fill),
             stroke: None,
+            paint_order: PaintOrder::default(),
         });
         stroke_insert += 1;
     }
@@ -803,6 +896,12 @@ fn segmented_rect(
         let (shape, _) = segment(Corner::TopLeft, Corner::TopLeft, &corners, stroke);
         res.push(shape);
     }
+
+    // move the fill above all segments if the stroke is painted first
+    if paint_order == PaintOrder::StrokeFill && stroke_insert > 0 {
+        let fill = res.remove(0);
+        res.push(fill);
+    }
     res
 }
 
@@ -907,6 +1006,7 @@ fn stroke_segment(
         geometry: Geometry::Path(path),
         stroke: Some(stroke),
         fill: None,
+        paint_order: PaintOrder::default(),
     }
 }
 
@@ -1005,6 +1105,7 @@ fn fill_segment(
         geometry: Geometry::Path(path),
         stroke: None,
         fill: Some(stroke.paint),
+        paint_order: PaintOrder::default(),
     }
 }
 
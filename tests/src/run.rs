@@ -6,12 +6,12 @@ use ecow::eco_vec;
 use tiny_skia as sk;
 use typst::diag::SourceDiagnostic;
 use typst::eval::Tracer;
-use typst::foundations::Smart;
 use typst::introspection::Meta;
 use typst::layout::{Abs, Frame, FrameItem, Page, Transform};
 use typst::model::Document;
 use typst::visualize::Color;
 use typst::WorldExt;
+use typst_pdf::PdfOptions;
 
 use crate::collect::{FileSize, NoteKind, Test};
 use crate::world::TestWorld;
@@ -102,6 +102,10 @@ impl<'a> Runner<'a> {
             self.check_diagnostic(NoteKind::Warning, warning);
         }
 
+        if let Some(doc) = &doc {
+            self.check_export(doc);
+        }
+
         self.handle_not_emitted();
         self.handle_not_annotated();
 
@@ -173,13 +177,6 @@ impl<'a> Runner<'a> {
         let data = pixmap_live.encode_png().unwrap();
         std::fs::write(&live_path, data).unwrap();
 
-        // Write PDF if requested.
-        if crate::ARGS.pdf() {
-            let pdf_path = format!("{}/pdf/{}.pdf", crate::STORE_PATH, self.test.name);
-            let pdf = typst_pdf::pdf(document, Smart::Auto, None, None);
-            std::fs::write(pdf_path, pdf).unwrap();
-        }
-
         // Write SVG if requested.
         if crate::ARGS.svg() {
             let svg_path = format!("{}/svg/{}.svg", crate::STORE_PATH, self.test.name);
@@ -238,6 +235,51 @@ impl<'a> Runner<'a> {
         }
     }
 
+    /// Export the document to PDF and check the diagnostics arising during
+    /// export.
+    ///
+    /// Many tests contain characters without a glyph on purpose, so the
+    /// export diagnostics are only checked against annotations and not
+    /// reported as unannotated. Export is thus skipped for tests without any
+    /// diagnostic annotations unless a PDF is requested.
+    fn check_export(&mut self, document: &Document) {
+        let annotated = self
+            .seen
+            .iter()
+            .zip(&self.test.notes)
+            .any(|(&seen, note)| !seen && note.kind != NoteKind::Hint);
+        if !annotated && !crate::ARGS.pdf() {
+            return;
+        }
+
+        let mut tracer = Tracer::new();
+        let result =
+            typst_pdf::pdf_with_options(document, &PdfOptions::default(), &mut tracer);
+
+        let errors = match result {
+            Ok(pdf) => {
+                // Write PDF if requested.
+                if crate::ARGS.pdf() {
+                    let pdf_path =
+                        format!("{}/pdf/{}.pdf", crate::STORE_PATH, self.test.name);
+                    std::fs::write(pdf_path, pdf).unwrap();
+                }
+                eco_vec![]
+            }
+            Err(errors) => errors,
+        };
+
+        for error in &errors {
+            self.check_diagnostic(NoteKind::Error, error);
+        }
+
+        let not_annotated = std::mem::take(&mut self.not_annotated);
+        for warning in &tracer.warnings() {
+            self.check_diagnostic(NoteKind::Warning, warning);
+        }
+        self.not_annotated = not_annotated;
+    }
+
     /// Compare a subset of notes with a given kind against diagnostics of
     /// that same kind.
     fn check_diagnostic(&mut self, kind: NoteKind, diag: &SourceDiagnostic) {
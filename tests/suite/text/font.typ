@@ -76,3 +76,9 @@ I
 // Warning: 23-56 unknown font family: non-existing-fonts
 #let var = text(font: ("list-of", "non-existing-fonts"))[don't]
 #var
+
+--- text-font-missing-glyph-warning ---
+// Missing glyphs are shown as the font's replacement glyph.
+#set page(height: 20pt, margin: 0pt)
+// Warning: 49-50 the font `Linux Libertine` does not contain a glyph for '😀'
+#text(font: "Linux Libertine", fallback: false)[😀]
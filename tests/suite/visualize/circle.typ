@@ -67,3 +67,14 @@ Expanded by height.
   circle(width: 10%),
   circle(height: 50%),
 )
+
+--- circle-paint-order ---
+// Test painting the stroke below the fill.
+#set page(width: auto, height: auto, margin: 5pt)
+#set circle(radius: 10pt, fill: aqua.transparentize(50%), stroke: 6pt + blue.transparentize(50%))
+#stack(
+  dir: ltr,
+  spacing: 10pt,
+  circle(paint-order: "fill-stroke"),
+  circle(paint-order: "stroke-fill"),
+)
@@ -29,3 +29,14 @@ Auto-sized ellipse.
 An inline
 #box(ellipse(width: 8pt, height: 6pt, outset: (top: 3pt, rest: 5.5pt)))
 ellipse.
+
+--- ellipse-paint-order ---
+// Test painting the stroke below the fill.
+#set page(width: auto, height: auto, margin: 5pt)
+#set ellipse(width: 30pt, height: 20pt, fill: aqua.transparentize(50%), stroke: 6pt + blue.transparentize(50%))
+#stack(
+  dir: ltr,
+  spacing: 10pt,
+  ellipse(paint-order: "fill-stroke"),
+  ellipse(paint-order: "stroke-fill"),
+)
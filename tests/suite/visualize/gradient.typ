@@ -160,6 +160,19 @@
 #line(length: 100%, angle: 10deg, stroke: 1pt + gradient.linear(red, blue))
 #line(length: 100%, angle: 10deg, stroke: 1pt + gradient.linear(red, blue, relative: "parent"))
 
+--- gradient-linear-line-stroke-area ---
+// Test that gradients on lines and straight paths span the area that their
+// stroke covers, across the stroke for the horizontal and vertical ones.
+#set page(width: auto, height: auto, margin: 5pt)
+#stack(
+  dir: ltr,
+  spacing: 5pt,
+  line(length: 40pt, stroke: 8pt + gradient.linear(red, blue, angle: 90deg)),
+  line(length: 40pt, angle: 90deg, stroke: 8pt + gradient.linear(red, blue)),
+  line(end: (30pt, 30pt), stroke: 8pt + gradient.linear(red, blue)),
+  path(stroke: 8pt + gradient.linear(red, blue, angle: 90deg), (0pt, 0pt), (40pt, 0pt)),
+)
+
 --- gradient-radial-hsl ---
 #square(
   size: 100pt,
@@ -355,6 +368,21 @@
 // Error: 17-61 gradients and patterns on text must be relative to the parent
 #set text(fill: gradient.linear(red, blue, relative: "self"))
 
+--- gradient-text-stroke-self-scaled ---
+// Test that self-relative gradient strokes span the text run and that both
+// the gradient and the stroke width scale along with a group.
+#set page(width: auto, height: auto, margin: 5pt)
+#set text(size: 12pt, stroke: 1pt + gradient.linear(red, blue, relative: "self"))
+Stroked
+#scale(200%, reflow: true)[Stroked]
+
+--- gradient-text-stroke-parent-scaled ---
+// Test that parent-relative gradient strokes scale along with a group.
+#set page(width: auto, height: auto, margin: 5pt)
+#set text(size: 12pt, stroke: 1pt + gradient.linear(red, blue))
+Stroked
+#scale(200%, reflow: true)[Stroked]
+
 --- gradient-text-global ---
 // Test that gradient fills on text work for globally defined gradients.
 #set page(width: 200pt, height: auto, margin: 10pt, background: {
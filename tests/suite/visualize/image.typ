@@ -41,6 +41,41 @@
 Stuff
 #image("/assets/images/rhino.png")
 
+--- image-scaling ---
+// Test smooth and pixelated scaling of a four by four checkerboard.
+#set page(width: auto, height: auto, margin: 5pt)
+#let checkerboard = bytes((
+  137, 80, 78, 71, 13, 10, 26, 10, 0, 0, 0, 13,
+  73, 72, 68, 82, 0, 0, 0, 4, 0, 0, 0, 4,
+  8, 0, 0, 0, 0, 140, 154, 193, 162, 0, 0, 0,
+  15, 73, 68, 65, 84, 120, 218, 99, 96, 248, 15, 133,
+  12, 112, 22, 0, 79, 196, 7, 249, 68, 111, 17, 49,
+  0, 0, 0, 0, 73, 69, 78, 68, 174, 66, 96, 130,
+))
+#box(image.decode(checkerboard, width: 40pt, scaling: "smooth"))
+#box(image.decode(checkerboard, width: 40pt, scaling: "pixelated"))
+
+--- image-fill ---
+// Test that the black pixels of a black-and-white image are recolored and
+// its white pixels become transparent.
+#set page(width: auto, height: auto, margin: 5pt, fill: yellow)
+#let checkerboard = bytes((
+  137, 80, 78, 71, 13, 10, 26, 10, 0, 0, 0, 13,
+  73, 72, 68, 82, 0, 0, 0, 4, 0, 0, 0, 4,
+  8, 0, 0, 0, 0, 140, 154, 193, 162, 0, 0, 0,
+  15, 73, 68, 65, 84, 120, 218, 99, 96, 248, 15, 133,
+  12, 112, 22, 0, 79, 196, 7, 249, 68, 111, 17, 49,
+  0, 0, 0, 0, 73, 69, 78, 68, 174, 66, 96, 130,
+))
+#box(image.decode(checkerboard, width: 20pt, scaling: "pixelated"))
+#box(image.decode(checkerboard, width: 20pt, scaling: "pixelated", fill: red))
+#box(image.decode(checkerboard, width: 20pt, scaling: "pixelated", fill: blue.transparentize(50%)))
+
+--- image-fill-no-effect ---
+// Warning: 2-133 image fill has no effect
+// Hint: 2-133 only black-and-white raster images can be filled
+#image.decode(`<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10"/></svg>`.text, fill: red)
+
 --- image-baseline-with-box ---
 // Test baseline.
 A #box(image("/assets/images/tiger.jpg", height: 1cm, width: 80%)) B
@@ -104,6 +139,19 @@ A #box(image("/assets/images/tiger.jpg", height: 1cm, width: 80%)) B
 // Error: 2-91 failed to decode image (Format error decoding Png: Invalid PNG signature.)
 #image.decode(read("/assets/images/tiger.jpg", encoding: none), format: "png", width: 80%)
 
+--- image-svg-unknown-font ---
+// Text in an unknown family falls back to the document's font.
+#set page(height: 20pt, margin: 0pt)
+// Warning: 2-158 unknown font family in SVG: Nonexistent Sans
+// Hint: 2-158 the text falls back to another font
+#image.decode(`<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><text y="8" font-family="Nonexistent Sans">A</text></svg>`.text, format: "svg")
+
+--- image-svg-font-fallback-list ---
+// An unknown family doesn't warn if another family in the list is known or
+// generic.
+#set page(height: 20pt, margin: 0pt)
+#image.decode(`<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><text y="8" font-family="Nonexistent Sans, Linux Libertine">A</text><text x="3" y="8" font-family="Nonexistent Sans, system-ui">B</text><text x="6" y="8" font-family="'Nonexistent Sans', ui-monospace">C</text></svg>`.text, format: "svg")
+
 --- issue-870-image-rotation ---
 // Ensure that EXIF rotation is applied.
 // https://github.com/image-rs/image/issues/1045
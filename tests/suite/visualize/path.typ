@@ -63,3 +63,14 @@
     (10pt, 10pt),
   ),
 )
+
+--- path-paint-order ---
+// Test painting the stroke below the fill.
+#set page(width: auto, height: auto, margin: 5pt)
+#set path(fill: aqua.transparentize(50%), stroke: 6pt + blue.transparentize(50%))
+#stack(
+  dir: ltr,
+  spacing: 10pt,
+  path(paint-order: "fill-stroke", (0pt, 0pt), (20pt, 30pt), (40pt, 0pt)),
+  path(paint-order: "stroke-fill", (0pt, 0pt), (20pt, 30pt), (40pt, 0pt)),
+)
@@ -49,3 +49,14 @@
 --- polygon-bad-point-array ---
 // Error: 10-17 point array must contain exactly two entries
 #polygon((50pt,))
+
+--- polygon-paint-order ---
+// Test painting the stroke below the fill.
+#set page(width: auto, height: auto, margin: 5pt)
+#set polygon(fill: aqua.transparentize(50%), stroke: 6pt + blue.transparentize(50%))
+#stack(
+  dir: ltr,
+  spacing: 10pt,
+  polygon(paint-order: "fill-stroke", (0pt, 0pt), (20pt, 30pt), (40pt, 0pt)),
+  polygon(paint-order: "stroke-fill", (0pt, 0pt), (20pt, 30pt), (40pt, 0pt)),
+)
@@ -105,3 +105,25 @@
 #align(right, rect(width: -1cm, fill: gradient.linear(red, blue))[Reverse right])
 
 #align(right, rect(width: 1cm, fill: gradient.linear(red, blue))[Right])
+
+--- rect-paint-order ---
+// Test painting the stroke below the fill, also with strokes per side and
+// rounded corners.
+#set page(width: auto, height: auto, margin: 5pt)
+#set rect(
+  width: 30pt,
+  height: 20pt,
+  fill: aqua.transparentize(50%),
+  stroke: 6pt + blue.transparentize(50%),
+)
+#stack(
+  dir: ltr,
+  spacing: 10pt,
+  rect(paint-order: "fill-stroke"),
+  rect(paint-order: "stroke-fill"),
+  rect(
+    paint-order: "stroke-fill",
+    radius: 5pt,
+    stroke: (left: 6pt + red.transparentize(50%), rest: 6pt + blue.transparentize(50%)),
+  ),
+)
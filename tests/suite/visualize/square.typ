@@ -144,3 +144,14 @@
 // Test that square sets correct base for its content.
 #set page(height: 80pt)
 #square(width: 40%, rect(width: 60%, height: 80%))
+
+--- square-paint-order ---
+// Test painting the stroke below the fill.
+#set page(width: auto, height: auto, margin: 5pt)
+#set square(size: 20pt, fill: aqua.transparentize(50%), stroke: 6pt + blue.transparentize(50%))
+#stack(
+  dir: ltr,
+  spacing: 10pt,
+  square(paint-order: "fill-stroke"),
+  square(paint-order: "stroke-fill"),
+)
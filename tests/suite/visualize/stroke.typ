@@ -94,6 +94,16 @@
 
 #text(stroke: 1pt + gradient.linear(..color.map.rainbow), v)
 
+--- stroke-text-paint-order-fill-stroke ---
+// The stroke is painted on top of the fill and eats into the glyphs.
+#set page(width: auto, height: auto, margin: 5pt)
+#text(size: 12pt, stroke: 2pt + aqua, paint-order: "fill-stroke")[Outline]
+
+--- stroke-text-paint-order-stroke-fill ---
+// The stroke is painted below the fill, so only its outer half shows.
+#set page(width: auto, height: auto, margin: 5pt)
+#text(size: 12pt, stroke: 2pt + aqua, paint-order: "stroke-fill")[Outline]
+
 --- stroke-folding ---
 // Test stroke folding.
 #let sq(..args) = box(square(size: 10pt, ..args))